@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+
+use tauri::State;
+
+use crate::{
+    adapters::{
+        models::{ActiveAdapter, Adapter},
+        service,
+    },
+    infrastructure::{database::Database, error::AppResult},
+};
+
+#[tauri::command]
+pub async fn import_lora_adapter(path: PathBuf, db: State<'_, Database>) -> AppResult<Adapter> {
+    service::import_adapter(&db, &path)
+}
+
+#[tauri::command]
+pub async fn get_lora_adapters(db: State<'_, Database>) -> AppResult<Vec<Adapter>> {
+    service::get_adapters(&db)
+}
+
+#[tauri::command]
+pub async fn delete_lora_adapter(id: String, db: State<'_, Database>) -> AppResult<()> {
+    service::delete_adapter(&db, &id)
+}
+
+#[tauri::command]
+pub async fn get_conversation_adapters(
+    conversation_id: String,
+    db: State<'_, Database>,
+) -> AppResult<Vec<ActiveAdapter>> {
+    service::get_conversation_adapters(&db, &conversation_id)
+}
+
+#[tauri::command]
+pub async fn set_conversation_adapters(
+    conversation_id: String,
+    adapters: Vec<ActiveAdapter>,
+    db: State<'_, Database>,
+) -> AppResult<Vec<ActiveAdapter>> {
+    service::set_conversation_adapters(&db, &conversation_id, adapters)
+}
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+// A GGUF LoRA adapter imported into the app's data directory
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Adapter {
+    pub id: String,
+    pub name: String,
+    pub size: u64,
+}
+
+// An adapter attached to a conversation, also recorded on the replies generated with it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ActiveAdapter {
+    pub id: String,
+    // Filled in from the imported adapter, callers only need to pass the id
+    #[serde(default)]
+    pub name: String,
+    pub scale: f32,
+}
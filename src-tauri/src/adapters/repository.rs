@@ -0,0 +1,126 @@
+use rusqlite::params;
+
+use crate::{
+    adapters::models::{ActiveAdapter, Adapter},
+    infrastructure::{
+        database::Database,
+        error::{AppError, AppResult},
+    },
+};
+
+pub fn add_adapter(db: &Database, adapter: &Adapter) -> AppResult<()> {
+    let conn = db.get_conn()?;
+    conn.execute(
+        "INSERT INTO lora_adapters (id, name, size, created) VALUES (?1, ?2, ?3, DATETIME('now'))",
+        params![adapter.id, adapter.name, adapter.size],
+    )?;
+    Ok(())
+}
+
+pub fn get_adapters(db: &Database) -> AppResult<Vec<Adapter>> {
+    let conn = db.get_conn()?;
+    let mut stmt = conn.prepare("SELECT id, name, size FROM lora_adapters ORDER BY name, id")?;
+    let adapters = stmt
+        .query_map([], |row| {
+            Ok(Adapter {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                size: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(adapters)
+}
+
+pub fn get_adapter(db: &Database, id: &str) -> AppResult<Option<Adapter>> {
+    let conn = db.get_conn()?;
+    let mut stmt = conn.prepare("SELECT id, name, size FROM lora_adapters WHERE id = ?1")?;
+    let mut rows = stmt.query(params![id])?;
+    if let Some(row) = rows.next()? {
+        Ok(Some(Adapter {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            size: row.get(2)?,
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+// Also detaches it from every conversation
+pub fn delete_adapter(db: &Database, id: &str) -> AppResult<()> {
+    let mut conn = db.get_conn()?;
+    let tx = conn.transaction()?;
+    tx.execute(
+        "DELETE FROM conversation_adapters WHERE adapter_id = ?1",
+        params![id],
+    )?;
+    let deleted = tx.execute("DELETE FROM lora_adapters WHERE id = ?1", params![id])?;
+    if deleted == 0 {
+        return Err(AppError::NotFound(format!("Adapter {} not found", id)));
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn get_conversation_adapters(
+    db: &Database,
+    conversation_id: &str,
+) -> AppResult<Vec<ActiveAdapter>> {
+    let conn = db.get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT a.id, a.name, c.scale FROM conversation_adapters c
+            JOIN lora_adapters a ON a.id = c.adapter_id
+            WHERE c.conversation_id = ?1
+            ORDER BY c.position",
+    )?;
+    let adapters = stmt
+        .query_map(params![conversation_id], |row| {
+            Ok(ActiveAdapter {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                scale: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(adapters)
+}
+
+// Replaces the adapters attached to a conversation
+pub fn set_conversation_adapters(
+    db: &Database,
+    conversation_id: &str,
+    adapters: &[ActiveAdapter],
+) -> AppResult<()> {
+    let mut conn = db.get_conn()?;
+    let tx = conn.transaction()?;
+    tx.execute(
+        "DELETE FROM conversation_adapters WHERE conversation_id = ?1",
+        params![conversation_id],
+    )?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO conversation_adapters (conversation_id, adapter_id, scale, position)
+                VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for (position, adapter) in adapters.iter().enumerate() {
+            stmt.execute(params![
+                conversation_id,
+                adapter.id,
+                adapter.scale,
+                position as i64
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn delete_conversation_adapters(db: &Database, conversation_id: &str) -> AppResult<()> {
+    let conn = db.get_conn()?;
+    conn.execute(
+        "DELETE FROM conversation_adapters WHERE conversation_id = ?1",
+        params![conversation_id],
+    )?;
+    Ok(())
+}
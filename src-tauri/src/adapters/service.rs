@@ -0,0 +1,132 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use uuid::Uuid;
+
+use crate::{
+    adapters::{
+        models::{ActiveAdapter, Adapter},
+        repository as dao,
+    },
+    conversation,
+    inference::models::LoraAdapter,
+    infrastructure::{
+        consts,
+        database::Database,
+        error::{AppError, AppResult},
+        path_resolver,
+    },
+};
+
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+
+// Copies a GGUF LoRA adapter into the app so it survives the original being moved
+pub fn import_adapter(db: &Database, path: &Path) -> AppResult<Adapter> {
+    let mut magic = [0u8; 4];
+    let is_gguf = File::open(path)?.read_exact(&mut magic).is_ok() && &magic == GGUF_MAGIC;
+    if !is_gguf {
+        return Err(AppError::Validation(format!(
+            "{} is not a GGUF file",
+            path.display()
+        )));
+    }
+
+    let adapter = Adapter {
+        id: Uuid::new_v4().to_string(),
+        name: path
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string()),
+        size: fs::metadata(path)?.len(),
+    };
+    fs::copy(path, adapter_path(&adapter.id)?)?;
+    if let Err(e) = dao::add_adapter(db, &adapter) {
+        let _ = fs::remove_file(adapter_path(&adapter.id)?);
+        return Err(e);
+    }
+    Ok(adapter)
+}
+
+pub fn get_adapters(db: &Database) -> AppResult<Vec<Adapter>> {
+    dao::get_adapters(db)
+}
+
+// A model that already loaded it keeps its copy in memory until it is unloaded
+pub fn delete_adapter(db: &Database, id: &str) -> AppResult<()> {
+    dao::delete_adapter(db, id)?;
+    let path = adapter_path(id)?;
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+pub fn get_conversation_adapters(
+    db: &Database,
+    conversation_id: &str,
+) -> AppResult<Vec<ActiveAdapter>> {
+    dao::get_conversation_adapters(db, conversation_id)
+}
+
+// Attaches adapters to a conversation in the order given, an empty list detaches them all
+pub fn set_conversation_adapters(
+    db: &Database,
+    conversation_id: &str,
+    adapters: Vec<ActiveAdapter>,
+) -> AppResult<Vec<ActiveAdapter>> {
+    if conversation::service::get_conversation(db, conversation_id)?.is_none() {
+        return Err(AppError::NotFound(format!(
+            "Conversation {} not found",
+            conversation_id
+        )));
+    }
+
+    let mut active: Vec<ActiveAdapter> = Vec::with_capacity(adapters.len());
+    for adapter in adapters {
+        if !adapter.scale.is_finite() || adapter.scale.abs() > consts::MAX_ADAPTER_SCALE {
+            return Err(AppError::Validation(format!(
+                "Adapter scale must be between -{0} and {0}",
+                consts::MAX_ADAPTER_SCALE
+            )));
+        }
+        if active.iter().any(|existing| existing.id == adapter.id) {
+            return Err(AppError::Validation(format!(
+                "Adapter {} is listed twice",
+                adapter.id
+            )));
+        }
+        let stored = dao::get_adapter(db, &adapter.id)?
+            .ok_or_else(|| AppError::NotFound(format!("Adapter {} not found", adapter.id)))?;
+        active.push(ActiveAdapter {
+            name: stored.name,
+            ..adapter
+        });
+    }
+
+    dao::set_conversation_adapters(db, conversation_id, &active)?;
+    Ok(active)
+}
+
+pub fn delete_conversation_adapters(db: &Database, conversation_id: &str) -> AppResult<()> {
+    dao::delete_conversation_adapters(db, conversation_id)
+}
+
+// Files the engine loads for `active`
+pub fn lora_adapters(active: &[ActiveAdapter]) -> AppResult<Vec<LoraAdapter>> {
+    active
+        .iter()
+        .map(|adapter| {
+            Ok(LoraAdapter {
+                path: adapter_path(&adapter.id)?,
+                scale: adapter.scale,
+            })
+        })
+        .collect()
+}
+
+fn adapter_path(id: &str) -> AppResult<PathBuf> {
+    path_resolver::paths()?
+        .app_local_data(Path::new(consts::ADAPTERS_DIR).join(format!("{}.gguf", id)))
+        .map_err(AppError::Internal)
+}
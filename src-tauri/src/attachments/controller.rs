@@ -0,0 +1,14 @@
+use tauri::State;
+
+use crate::{
+    attachments::{models::AttachmentContent, service},
+    infrastructure::{database::Database, error::AppResult},
+};
+
+#[tauri::command]
+pub async fn open_attachment(
+    hash: String,
+    db: State<'_, Database>,
+) -> AppResult<AttachmentContent> {
+    service::open_attachment(&db, &hash)
+}
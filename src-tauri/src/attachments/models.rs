@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+// A local file attached to a user message
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Attachment {
+    pub name: String,
+    pub size: u64,
+    // SHA-256 of the original bytes, also the key they are stored under
+    pub hash: String,
+    pub encoding: String,
+    // Decoded text as placed in the prompt, cut to the attachment token budget
+    pub excerpt: String,
+    pub truncated: bool,
+}
+
+// Full decoded text of a stored attachment
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AttachmentContent {
+    pub name: String,
+    pub encoding: String,
+    pub text: String,
+}
@@ -0,0 +1,26 @@
+use rusqlite::params;
+
+use crate::infrastructure::{database::Database, error::AppResult};
+
+// Identical files share one row, whichever name they were first attached under
+pub fn add_original(db: &Database, hash: &str, name: &str, bytes: &[u8]) -> AppResult<()> {
+    let conn = db.get_conn()?;
+    conn.execute(
+        "INSERT OR IGNORE INTO attachments (hash, name, content, created)
+            VALUES (?1, ?2, ?3, DATETIME('now'))",
+        params![hash, name, bytes],
+    )?;
+    Ok(())
+}
+
+pub fn get_original(db: &Database, hash: &str) -> AppResult<Option<(String, Vec<u8>)>> {
+    let conn = db.get_conn()?;
+    let mut stmt = conn.prepare("SELECT name, content FROM attachments WHERE hash = ?1")?;
+    let mut rows = stmt.query(params![hash])?;
+
+    if let Some(row) = rows.next()? {
+        Ok(Some((row.get(0)?, row.get(1)?)))
+    } else {
+        Ok(None)
+    }
+}
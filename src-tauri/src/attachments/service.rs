@@ -0,0 +1,106 @@
+use std::fs;
+use std::path::Path;
+
+use chardetng::EncodingDetector;
+use encoding_rs::Encoding;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    attachments::{
+        models::{Attachment, AttachmentContent},
+        repository as dao,
+    },
+    configuration::models::Config,
+    infrastructure::{
+        consts,
+        database::Database,
+        error::{AppError, AppResult},
+    },
+};
+
+// Reads a text-like file and cuts its text to `token_budget`, returning the original bytes
+// to be stored with `save_original` once the message is kept
+pub fn attach(path: &Path, token_budget: usize) -> AppResult<(Attachment, Vec<u8>)> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string());
+    if fs::metadata(path)?.len() > consts::ATTACHMENT_MAX_BYTES {
+        return Err(AppError::Validation(format!(
+            "{} is larger than {} MiB",
+            name,
+            consts::ATTACHMENT_MAX_BYTES / (1024 * 1024)
+        )));
+    }
+    let bytes = fs::read(path)?;
+    if is_binary(&bytes) {
+        return Err(AppError::Validation(format!("{} is not a text file", name)));
+    }
+
+    let (text, encoding) = decode(&bytes);
+    let hash = format!("{:x}", Sha256::digest(&bytes));
+
+    let (excerpt, truncated) = truncate(&text, token_budget * consts::CHARS_PER_TOKEN);
+    let attachment = Attachment {
+        name,
+        size: bytes.len() as u64,
+        hash,
+        encoding: encoding.name().to_string(),
+        excerpt: excerpt.to_string(),
+        truncated,
+    };
+    Ok((attachment, bytes))
+}
+
+pub fn save_original(db: &Database, attachment: &Attachment, bytes: &[u8]) -> AppResult<()> {
+    dao::add_original(db, &attachment.hash, &attachment.name, bytes)
+}
+
+// Tokens each of `count` attachments may use, together they take at most a quarter of the context
+pub fn token_budget(config: &Config, count: usize) -> usize {
+    let budget = (config.max_context_length as usize / 4).min(consts::ATTACHMENT_MAX_TOKENS);
+    budget / count.max(1)
+}
+
+pub fn open_attachment(db: &Database, hash: &str) -> AppResult<AttachmentContent> {
+    let (name, bytes) = dao::get_original(db, hash)?
+        .ok_or_else(|| AppError::NotFound(format!("Attachment {} not found", hash)))?;
+    let (text, encoding) = decode(&bytes);
+    Ok(AttachmentContent {
+        name,
+        encoding: encoding.name().to_string(),
+        text,
+    })
+}
+
+// A byte order mark wins, otherwise the encoding is guessed from the content
+pub fn decode(bytes: &[u8]) -> (String, &'static Encoding) {
+    if let Some((encoding, bom_length)) = Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_length..]);
+        return (text.into_owned(), encoding);
+    }
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    let encoding = detector.guess(None, true);
+    let (text, _) = encoding.decode_without_bom_handling(bytes);
+    (text.into_owned(), encoding)
+}
+
+// NUL bytes never appear in text, except in UTF-16 which always starts with a BOM
+fn is_binary(bytes: &[u8]) -> bool {
+    Encoding::for_bom(bytes).is_none()
+        && bytes[..bytes.len().min(consts::BINARY_SNIFF_BYTES)].contains(&0)
+}
+
+// Keeps whole lines when a line break falls in the second half of the allowed length
+fn truncate(text: &str, max_chars: usize) -> (&str, bool) {
+    let Some((cut, _)) = text.char_indices().nth(max_chars) else {
+        return (text, false);
+    };
+    let end = match text[..cut].rfind('\n') {
+        Some(newline) if newline >= cut / 2 => newline,
+        _ => cut,
+    };
+    (&text[..end], true)
+}
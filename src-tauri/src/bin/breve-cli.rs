@@ -0,0 +1,309 @@
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use breve_lib::{
+    conversation::{self, models::Conversation},
+    inference,
+    infrastructure::{
+        consts,
+        context::Context,
+        database::Database,
+        error::{AppError, AppResult},
+        events::{Event, EventSink},
+        path_resolver,
+    },
+    models, search,
+};
+use clap::{Parser, Subcommand, ValueEnum};
+
+#[derive(Parser)]
+#[command(
+    name = "breve-cli",
+    about = "Chat with local models and manage them without a window"
+)]
+struct Cli {
+    /// Data directory, defaults to the one used by the desktop app
+    #[arg(long, env = "BREVE_DATA_DIR", global = true)]
+    data_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Manage local models
+    #[command(subcommand)]
+    Models(ModelsCommand),
+    /// Send a single prompt and print the reply
+    Ask {
+        prompt: String,
+        /// Continue an existing conversation instead of starting a new one
+        #[arg(long)]
+        conversation: Option<String>,
+        /// Attach a text file to the prompt, can be repeated
+        #[arg(long = "attach")]
+        attachments: Vec<PathBuf>,
+    },
+    /// Interactive chat, `/exit` to quit
+    Chat {
+        /// Resume an existing conversation
+        #[arg(long)]
+        conversation: Option<String>,
+    },
+    /// Browse and export stored conversations
+    #[command(subcommand)]
+    Conversations(ConversationsCommand),
+}
+
+#[derive(Subcommand)]
+enum ModelsCommand {
+    /// List catalog models and whether they are downloaded
+    List,
+    /// Download a model from the catalog
+    Download { name: String },
+    /// Delete a downloaded model
+    Delete { name: String },
+    /// Set the model used for chatting
+    Default { name: String },
+}
+
+#[derive(Subcommand)]
+enum ConversationsCommand {
+    /// List stored conversations
+    List,
+    /// Print a conversation to stdout
+    Export {
+        id: String,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Markdown)]
+        format: ExportFormat,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    Json,
+    Markdown,
+}
+
+// Replies go to stdout, download progress to stderr
+struct TerminalSink;
+
+impl EventSink for TerminalSink {
+    fn emit(&self, event: Event) {
+        match event {
+            Event::Stream(content) => {
+                let mut stdout = io::stdout();
+                let _ = write!(stdout, "{}", content.content);
+                let _ = stdout.flush();
+            }
+            Event::DownloadProgress(pct) => eprint!("\rDownloading: {:5.1}%", pct),
+        }
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    let Some(data_dir) = cli.data_dir.or_else(path_resolver::default_data_dir) else {
+        eprintln!("Unable to determine the data directory, pass --data-dir");
+        std::process::exit(1);
+    };
+    path_resolver::init_dir_paths(data_dir);
+
+    if let Err(e) = run(cli.command) {
+        eprintln!("{}", e.message());
+        std::process::exit(1);
+    }
+}
+
+fn run(command: Command) -> AppResult<()> {
+    let db = Database::open(consts::get_db_path()?)?;
+    let mut ctx = Context::init(db)?;
+
+    match command {
+        Command::Models(command) => run_models(command, &mut ctx),
+        Command::Ask {
+            prompt,
+            conversation,
+            attachments,
+        } => {
+            load_model(&mut ctx)?;
+            let conv_id = match conversation {
+                Some(id) => id,
+                None => new_conversation(&ctx.db, &prompt)?,
+            };
+            send(&conv_id, &prompt, &attachments, &mut ctx)
+        }
+        Command::Chat { conversation } => {
+            load_model(&mut ctx)?;
+            chat(conversation, &mut ctx)
+        }
+        Command::Conversations(command) => run_conversations(command, &ctx.db),
+    }
+}
+
+fn run_models(command: ModelsCommand, ctx: &mut Context) -> AppResult<()> {
+    match command {
+        ModelsCommand::List => {
+            let catalog = ctx.config.get_available_models();
+            let downloaded = models::service::downloaded_models(catalog);
+            let mut names: Vec<&String> = catalog.keys().collect();
+            names.sort();
+
+            for name in names {
+                let model = &catalog[name];
+                let mut flags = Vec::new();
+                if downloaded.contains(name) {
+                    flags.push("downloaded");
+                }
+                if ctx.config.default_model == *name {
+                    flags.push("default");
+                }
+                println!(
+                    "{}\t{}\t{:.0} MB\t{}",
+                    name,
+                    model.name,
+                    model.size,
+                    flags.join(",")
+                );
+            }
+            Ok(())
+        }
+        ModelsCommand::Download { name } => {
+            let repo = ctx
+                .config
+                .get_available_models()
+                .get(&name)
+                .ok_or_else(|| AppError::NotFound(format!("Model {} not found", name)))?
+                .repo
+                .clone();
+            let path = path_resolver::paths()?
+                .app_local_data(&name)
+                .map_err(AppError::Internal)?;
+
+            models::service::fetch_model(&repo, &name, &path.to_string_lossy(), &TerminalSink)?;
+            eprintln!();
+            Ok(())
+        }
+        ModelsCommand::Delete { name } => models::service::delete_model(&name, ctx),
+        ModelsCommand::Default { name } => inference::service::set_default_model(name, ctx),
+    }
+}
+
+fn run_conversations(command: ConversationsCommand, db: &Database) -> AppResult<()> {
+    match command {
+        ConversationsCommand::List => {
+            for id in conversation::service::get_conversation_ids(db)? {
+                if let Ok(Some(conv)) = conversation::service::get_conversation(db, &id) {
+                    println!("{}\t{}\t{} messages", conv.id, conv.title, conv.body.len());
+                }
+            }
+            Ok(())
+        }
+        ConversationsCommand::Export { id, format } => {
+            let conv = find_conversation(db, &id)?;
+
+            match format {
+                ExportFormat::Json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&conv)
+                        .map_err(|e| AppError::Internal(e.to_string()))?
+                ),
+                ExportFormat::Markdown => print!("{}", to_markdown(&conv)),
+            }
+            Ok(())
+        }
+    }
+}
+
+fn load_model(ctx: &mut Context) -> AppResult<()> {
+    inference::service::activate_saved_model(ctx);
+    if ctx.inference.is_none() {
+        return Err(AppError::ModelLoadFailed(
+            "No model loaded, download one and set it as default first".into(),
+        ));
+    }
+    Ok(())
+}
+
+fn new_conversation(db: &Database, first_input: &str) -> AppResult<String> {
+    let title: String = first_input.chars().take(40).collect();
+    conversation::service::start_new_conversation(db, title.trim())
+}
+
+// Streams the reply to stdout as it is generated
+fn send(
+    conv_id: &str,
+    user_input: &str,
+    attachments: &[PathBuf],
+    ctx: &mut Context,
+) -> AppResult<()> {
+    conversation::service::continue_conversation(
+        conv_id,
+        user_input,
+        attachments,
+        &TerminalSink,
+        ctx,
+    )?;
+    println!();
+    // Nothing else is waiting on the terminal, so the reply is indexed right away
+    if let Err(e) = search::service::index_conversation(conv_id, ctx) {
+        eprintln!("Failed to index messages: {}", e);
+    }
+    Ok(())
+}
+
+fn chat(conversation: Option<String>, ctx: &mut Context) -> AppResult<()> {
+    let mut conv_id = conversation;
+    if let Some(id) = &conv_id {
+        let conv = find_conversation(&ctx.db, id)?;
+        print!("{}", to_markdown(&conv));
+    }
+
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        let _ = io::stdout().flush();
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+        let input = line.trim();
+        if input.is_empty() {
+            continue;
+        }
+        if input == "/exit" {
+            break;
+        }
+
+        let id = match &conv_id {
+            Some(id) => id.clone(),
+            None => new_conversation(&ctx.db, input)?,
+        };
+        conv_id = Some(id.clone());
+
+        if let Err(e) = send(&id, input, &[], ctx) {
+            eprintln!("{}", e.message());
+        }
+    }
+
+    if let Some(id) = conv_id {
+        eprintln!("Conversation saved as {}", id);
+    }
+    Ok(())
+}
+
+fn find_conversation(db: &Database, id: &str) -> AppResult<Conversation> {
+    conversation::service::get_conversation(db, id)?
+        .ok_or_else(|| AppError::NotFound(format!("Conversation {} not found", id)))
+}
+
+fn to_markdown(conv: &Conversation) -> String {
+    let mut out = format!("# {}\n\n", conv.title);
+    for message in &conv.body {
+        out.push_str(&format!("**{}**\n\n{}\n\n", message.role, message.content));
+    }
+    out
+}
@@ -3,14 +3,21 @@ use std::{collections::HashMap, sync::Arc};
 use tauri::{State, async_runtime::Mutex};
 
 use crate::{
-    configuration::{models::Config, repository},
+    configuration::{
+        models::{Config, ConfigChange},
+        repository,
+    },
     inference,
-    infrastructure::context::Context,
+    infrastructure::{
+        context::Context,
+        error::{AppError, AppResult},
+    },
+    search,
 };
 use serde_json::Value;
 
 #[tauri::command]
-pub async fn get_model_config(app_state: State<'_, Arc<Mutex<Context>>>) -> Result<Config, String> {
+pub async fn get_model_config(app_state: State<'_, Arc<Mutex<Context>>>) -> AppResult<Config> {
     let ctx = app_state.lock().await;
 
     return Ok(ctx.config.clone());
@@ -20,10 +27,11 @@ pub async fn get_model_config(app_state: State<'_, Arc<Mutex<Context>>>) -> Resu
 pub async fn set_model_config(
     app_state: State<'_, Arc<Mutex<Context>>>,
     payload: HashMap<String, Value>,
-) -> Result<(), String> {
+) -> AppResult<ConfigChange> {
     let mut ctx = app_state.lock().await;
 
-    let mut current_config_json = serde_json::to_value(&ctx.config).map_err(|e| e.to_string())?;
+    let mut current_config_json =
+        serde_json::to_value(&ctx.config).map_err(|e| AppError::Internal(e.to_string()))?;
 
     if let Some(obj) = current_config_json.as_object_mut() {
         for (key, value) in payload.clone() {
@@ -31,12 +39,56 @@ pub async fn set_model_config(
         }
     }
 
-    let updated_config: Config = serde_json::from_value(current_config_json)
-        .map_err(|e| format!("Validation failed: {}", e))?;
+    let mut updated_config: Config = serde_json::from_value(current_config_json)
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+    updated_config.limits = ctx.config.limits;
+    updated_config.models = ctx.config.models.clone();
+    updated_config.clamp_to_limits();
 
-    for (key, val) in payload {
-        let _ = repository::set_model_config(key, val.to_string());
+    let change = updated_config.change_from(&ctx.config);
+    let reindex = inference::service::embedding_model(&updated_config)
+        != inference::service::embedding_model(&ctx.config);
+    // A missing embedding model would otherwise only fail once indexing runs
+    if reindex && !updated_config.embedding_model.is_empty() {
+        inference::service::validate_model(&updated_config, &updated_config.embedding_model)?;
     }
-    ctx.config = updated_config;
-    inference::service::activate_model(ctx.config.default_model.clone(), &mut ctx)
+    let previous = std::mem::replace(&mut ctx.config, updated_config);
+    let change = match inference::service::apply_config(change, &mut ctx) {
+        Ok(change) => change,
+        Err(e) => {
+            ctx.config = previous;
+            return Err(e);
+        }
+    };
+
+    // Saved once applied, so settings the model cannot run with are not kept. Values are taken
+    // from the running config, which holds them clamped to the device's limits.
+    let applied =
+        serde_json::to_value(&ctx.config).map_err(|e| AppError::Internal(e.to_string()))?;
+    for key in payload.into_keys() {
+        if let Some(val) = applied.get(&key) {
+            repository::set_model_config(&ctx.db, key, val.to_string())?;
+        }
+    }
+
+    if reindex {
+        search::service::index_in_background(app_state.inner().clone(), None);
+    }
+    Ok(change)
+}
+
+#[tauri::command]
+pub async fn reset_model_config(app_state: State<'_, Arc<Mutex<Context>>>) -> AppResult<Config> {
+    let mut ctx = app_state.lock().await;
+
+    let previous = ctx.config.clone();
+    ctx.config.reset_to_defaults();
+
+    let change = ctx.config.change_from(&previous);
+    if let Err(e) = inference::service::apply_config(change, &mut ctx) {
+        ctx.config = previous;
+        return Err(e);
+    }
+    repository::clear_model_config(&ctx.db)?;
+    Ok(ctx.config.clone())
 }
@@ -1,57 +1,364 @@
 use std::collections::HashMap;
 
+use serde::de::DeserializeOwned;
+
 use crate::{
     configuration::repository,
-    infrastructure::{consts, path_resolver},
-    models::models::Model,
+    inference::template::ChatTemplate,
+    infrastructure::{
+        consts,
+        database::Database,
+        device::{self, DeviceMemory},
+        error::{AppError, AppResult},
+        path_resolver,
+    },
+    models::{self, models::Model},
 };
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 pub struct Config {
     pub default_model: String,
+    // Model used for embeddings, empty to embed with the chat model
+    pub embedding_model: String,
     pub batch_size: u64,
     pub max_context_length: u64,
     pub max_output_length: u64,
     pub system_prompt: String,
     pub temperature: f32,
-    #[serde(skip, default = "crate::infrastructure::consts::default_models")]
-    pub models: &'static HashMap<String, Model>,
+    // Strings that end a reply, on top of the model's own end of turn tokens
+    pub stop_sequences: Vec<String>,
+    // Per model file, used instead of the template embedded in the GGUF
+    pub chat_templates: HashMap<String, ChatTemplate>,
+    pub n_threads: u32,
+    pub n_threads_batch: u32,
+    pub use_mmap: bool,
+    pub use_mlock: bool,
+    pub n_gpu_layers: u32,
+    pub flash_attention: bool,
+    pub kv_cache_type: KvCacheType,
+    // Filled from the database by `init`, carried over by whoever deserializes a new config
+    #[serde(skip)]
+    pub models: HashMap<String, Model>,
+    #[serde(skip)]
+    pub limits: ContextLimits,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KvCacheType {
+    #[default]
+    F16,
+    Q8_0,
+    Q4_0,
+}
+
+impl KvCacheType {
+    // Size of one cached element as (bytes, elements): quantized blocks pack 32 values
+    pub fn element_size(self) -> (u64, u64) {
+        match self {
+            KvCacheType::F16 => (2, 1),
+            KvCacheType::Q8_0 => (34, 32),
+            KvCacheType::Q4_0 => (18, 32),
+        }
+    }
+}
+
+// Least amount of work needed to bring a loaded model in line with a new config
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigChange {
+    None,
+    // Read on every generation: sampler settings, prompt and output budget
+    Sampler,
+    // Baked into the llama context: sizes, threads, attention and cache types
+    ContextRebuild,
+    // Applied while loading weights: model file, mapping, locking and GPU offload
+    ModelReload,
+}
+
+// Memory needed to run a model: the weights plus the KV cache cost of each token
+#[derive(Clone, Copy, Debug)]
+pub struct ModelFootprint {
+    pub model_bytes: u64,
+    pub bytes_per_token: u64,
+    pub n_ctx_train: u64,
+}
+
+impl Default for ModelFootprint {
+    fn default() -> Self {
+        ModelFootprint {
+            model_bytes: consts::DEFAULT_MODEL_SIZE_BYTES,
+            bytes_per_token: consts::DEFAULT_BYTES_PER_TOKEN,
+            n_ctx_train: consts::DEFAULT_N_CTX_TRAIN,
+        }
+    }
+}
+
+// Largest token budget the device can hold for a given model
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ContextLimits {
+    pub batch_size: u64,
+    pub max_context_length: u64,
+    pub max_output_length: u64,
+}
+
+impl ContextLimits {
+    pub fn compute(memory: DeviceMemory, footprint: &ModelFootprint) -> ContextLimits {
+        let memory_for_context = memory.available_bytes.saturating_sub(footprint.model_bytes);
+        let max_context_tokens = memory_for_context / footprint.bytes_per_token.max(1);
+        let batch_size = max_context_tokens
+            .min(footprint.n_ctx_train)
+            .clamp(consts::MIN_BATCH_SIZE, consts::MAX_BATCH_SIZE);
+        let max_output_length = consts::DEFAULT_MAX_OUTPUT_LENGTH.min(batch_size / 2);
+        ContextLimits {
+            batch_size,
+            max_context_length: batch_size - max_output_length,
+            max_output_length,
+        }
+    }
 }
 
 impl Config {
-    pub fn init() -> Config {
-        let global_mem_bytes: u64 = consts::DEFAULT_GLOBAL_MEM_BYTES;
-        let model_size_bytes: u64 = consts::DEFAULT_MODEL_SIZE_BYTES;
-        let memory_for_context: u64 = global_mem_bytes.saturating_sub(model_size_bytes);
-        let bytes_per_token: u64 = consts::DEFAULT_BYTES_PER_TOKEN;
-        let max_context_tokens = memory_for_context / bytes_per_token;
-        let temperature = repository::get_model_config("temperature".to_string())
-            .ok()
-            .flatten()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(consts::DEFAULT_TEMPERATURE);
-        let batch_size = max_context_tokens.clamp(4096, 32768);
-        Config {
-            batch_size: batch_size,
-            max_context_length: batch_size - consts::DEFAULT_MAX_OUTPUT_LENGTH,
-            max_output_length: consts::DEFAULT_MAX_OUTPUT_LENGTH,
+    pub fn init(db: &Database) -> Config {
+        let limits = ContextLimits::compute(device::memory(), &ModelFootprint::default());
+        let mut config = Config {
+            batch_size: limits.batch_size,
+            max_context_length: limits.max_context_length,
+            max_output_length: limits.max_output_length,
             system_prompt: consts::DEFAULT_SYSTEM_PROMPT.to_string(),
-            models: consts::default_models(),
+            models: models::service::load_catalog(db),
             default_model: "".to_string(),
-            temperature: temperature,
+            embedding_model: "".to_string(),
+            temperature: consts::DEFAULT_TEMPERATURE,
+            stop_sequences: Vec::new(),
+            chat_templates: HashMap::new(),
+            n_threads: device::cpu_threads(),
+            n_threads_batch: device::cpu_threads(),
+            use_mmap: consts::DEFAULT_USE_MMAP,
+            use_mlock: consts::DEFAULT_USE_MLOCK,
+            n_gpu_layers: consts::DEFAULT_N_GPU_LAYERS,
+            flash_attention: consts::DEFAULT_FLASH_ATTENTION,
+            kv_cache_type: KvCacheType::default(),
+            limits,
+        };
+        config.load_persisted(db);
+        config
+    }
+
+    // Each field falls back to its computed default when missing or not of the expected type
+    fn load_persisted(&mut self, db: &Database) {
+        if let Some(batch_size) = persisted(db, "batch_size") {
+            self.batch_size = batch_size;
+        }
+        if let Some(max_context_length) = persisted(db, "max_context_length") {
+            self.max_context_length = max_context_length;
+        }
+        if let Some(max_output_length) = persisted(db, "max_output_length") {
+            self.max_output_length = max_output_length;
+        }
+        if let Some(embedding_model) = persisted(db, "embedding_model") {
+            self.embedding_model = embedding_model;
+        }
+        if let Some(system_prompt) = persisted(db, "system_prompt") {
+            self.system_prompt = system_prompt;
+        }
+        if let Some(temperature) = persisted::<f32>(db, "temperature")
+            && temperature.is_finite()
+            && temperature >= 0.0
+        {
+            self.temperature = temperature;
+        }
+        if let Some(stop_sequences) = persisted(db, "stop_sequences") {
+            self.stop_sequences = stop_sequences;
+        }
+        if let Some(chat_templates) = persisted(db, "chat_templates") {
+            self.chat_templates = chat_templates;
+        }
+        if let Some(n_threads) = persisted::<u32>(db, "n_threads")
+            && n_threads > 0
+        {
+            self.n_threads = n_threads;
+        }
+        if let Some(n_threads_batch) = persisted::<u32>(db, "n_threads_batch")
+            && n_threads_batch > 0
+        {
+            self.n_threads_batch = n_threads_batch;
+        }
+        if let Some(use_mmap) = persisted(db, "use_mmap") {
+            self.use_mmap = use_mmap;
+        }
+        if let Some(use_mlock) = persisted(db, "use_mlock") {
+            self.use_mlock = use_mlock;
         }
+        if let Some(n_gpu_layers) = persisted(db, "n_gpu_layers") {
+            self.n_gpu_layers = n_gpu_layers;
+        }
+        if let Some(flash_attention) = persisted(db, "flash_attention") {
+            self.flash_attention = flash_attention;
+        }
+        if let Some(kv_cache_type) = persisted(db, "kv_cache_type") {
+            self.kv_cache_type = kv_cache_type;
+        }
+        self.clamp_to_limits();
+    }
+
+    pub fn reset_to_defaults(&mut self) {
+        self.batch_size = self.limits.batch_size;
+        self.max_context_length = self.limits.max_context_length;
+        self.max_output_length = self.limits.max_output_length;
+        self.system_prompt = consts::DEFAULT_SYSTEM_PROMPT.to_string();
+        self.temperature = consts::DEFAULT_TEMPERATURE;
+        self.stop_sequences.clear();
+        self.chat_templates.clear();
+        self.n_threads = device::cpu_threads();
+        self.n_threads_batch = device::cpu_threads();
+        self.use_mmap = consts::DEFAULT_USE_MMAP;
+        self.use_mlock = consts::DEFAULT_USE_MLOCK;
+        self.n_gpu_layers = consts::DEFAULT_N_GPU_LAYERS;
+        self.flash_attention = consts::DEFAULT_FLASH_ATTENTION;
+        self.kv_cache_type = KvCacheType::default();
+    }
+
+    // Sizes that still match the previous defaults follow the new ones, customized sizes are clamped
+    pub fn apply_limits(&mut self, limits: ContextLimits) {
+        let previous = self.limits;
+        if self.batch_size == previous.batch_size {
+            self.batch_size = limits.batch_size;
+        }
+        if self.max_output_length == previous.max_output_length {
+            self.max_output_length = limits.max_output_length;
+        }
+        if self.max_context_length == previous.max_context_length {
+            self.max_context_length = limits.max_context_length;
+        }
+        self.limits = limits;
+        self.clamp_to_limits();
     }
 
-    pub fn get_model_path(&self) -> String {
-        path_resolver::paths()
+    pub fn clamp_to_limits(&mut self) {
+        self.n_threads = self.n_threads.max(1);
+        self.n_threads_batch = self.n_threads_batch.max(1);
+        self.batch_size = self.batch_size.clamp(1, self.limits.batch_size.max(1));
+        self.max_output_length = self.max_output_length.clamp(1, self.batch_size / 2 + 1);
+        self.max_context_length = self.max_context_length.clamp(
+            1,
+            self.batch_size
+                .saturating_sub(self.max_output_length)
+                .max(1),
+        );
+    }
+
+    // Key and value cache types, llama.cpp can only quantize the V cache when flash attention is on
+    pub fn kv_cache_types(&self) -> (KvCacheType, KvCacheType) {
+        let type_v = if self.flash_attention {
+            self.kv_cache_type
+        } else {
+            KvCacheType::F16
+        };
+        (self.kv_cache_type, type_v)
+    }
+
+    pub fn change_from(&self, previous: &Config) -> ConfigChange {
+        if self.default_model != previous.default_model
+            || self.use_mmap != previous.use_mmap
+            || self.use_mlock != previous.use_mlock
+            || self.n_gpu_layers != previous.n_gpu_layers
+        {
+            ConfigChange::ModelReload
+        } else if self.batch_size != previous.batch_size
+            || self.max_context_length != previous.max_context_length
+            || self.n_threads != previous.n_threads
+            || self.n_threads_batch != previous.n_threads_batch
+            || self.flash_attention != previous.flash_attention
+            || self.kv_cache_type != previous.kv_cache_type
+        {
+            ConfigChange::ContextRebuild
+        } else if self.temperature != previous.temperature
+            || self.max_output_length != previous.max_output_length
+            || self.system_prompt != previous.system_prompt
+            || self.stop_sequences != previous.stop_sequences
+            || self.chat_templates != previous.chat_templates
+        {
+            ConfigChange::Sampler
+        } else {
+            ConfigChange::None
+        }
+    }
+
+    pub fn get_model_path(&self) -> AppResult<String> {
+        let path = path_resolver::paths()?
             .app_local_data(&self.default_model)
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string()
+            .map_err(AppError::Internal)?;
+        Ok(path.to_string_lossy().to_string())
     }
 
-    pub fn get_available_models(&self) -> &'static HashMap<String, Model> {
+    pub fn get_available_models(&self) -> &HashMap<String, Model> {
         &self.models
     }
 }
+
+// Values are stored as JSON by `set_model_config`
+fn persisted<T: DeserializeOwned>(db: &Database, key: &str) -> Option<T> {
+    let raw = repository::get_model_config(db, key.to_string())
+        .ok()
+        .flatten()?;
+    match serde_json::from_str(&raw) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            eprintln!("Ignoring invalid model config {}: {}", key, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    fn memory(available_bytes: u64) -> DeviceMemory {
+        DeviceMemory {
+            total_bytes: available_bytes.max(16 * GIB),
+            available_bytes,
+        }
+    }
+
+    fn footprint() -> ModelFootprint {
+        ModelFootprint {
+            model_bytes: 2 * GIB,
+            bytes_per_token: 100 * 1024,
+            n_ctx_train: 32768,
+        }
+    }
+
+    #[test]
+    fn context_fits_the_memory_left_after_the_model() {
+        // 1000 MiB after the weights hold 10240 tokens of KV cache
+        let limits = ContextLimits::compute(memory(2 * GIB + 1000 * 1024 * 1024), &footprint());
+        assert_eq!(limits.batch_size, 10240);
+        assert_eq!(limits.max_output_length, consts::DEFAULT_MAX_OUTPUT_LENGTH);
+        assert_eq!(
+            limits.max_context_length + limits.max_output_length,
+            limits.batch_size
+        );
+    }
+
+    #[test]
+    fn context_is_capped_by_training_length() {
+        let limits = ContextLimits::compute(memory(64 * GIB), &footprint());
+        assert_eq!(limits.batch_size, 32768);
+    }
+
+    #[test]
+    fn tiny_ram_still_gets_the_minimum_context() {
+        // Less memory than the weights need leaves nothing for the cache
+        let limits = ContextLimits::compute(memory(GIB), &footprint());
+        assert_eq!(limits.batch_size, consts::MIN_BATCH_SIZE);
+        assert_eq!(limits.max_output_length, consts::MIN_BATCH_SIZE / 2);
+        assert_eq!(limits.max_context_length, consts::MIN_BATCH_SIZE / 2);
+
+        let limits = ContextLimits::compute(memory(0), &ModelFootprint::default());
+        assert_eq!(limits.batch_size, consts::MIN_BATCH_SIZE);
+    }
+}
@@ -1,48 +1,62 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 use tauri::{State, Window, async_runtime::Mutex};
 
 use crate::{
-    conversation::{models::Conversation, service},
-    infrastructure::context::Context,
+    conversation::{
+        models::{Conversation, Message},
+        service,
+    },
+    infrastructure::{context::Context, database::Database, error::AppResult},
+    search,
 };
 
 #[tauri::command]
-pub async fn start_conversation(title: String) -> Result<String, String> {
-    service::start_new_conversation(&title).map_err(|e| e.to_string())
+pub async fn start_conversation(title: String, db: State<'_, Database>) -> AppResult<String> {
+    service::start_new_conversation(&db, &title)
 }
 
 #[tauri::command]
 pub async fn continue_conversation(
     conv_id: String,
     user_input: String,
+    attachments: Option<Vec<PathBuf>>,
     window: Window,
     app_state: State<'_, Arc<Mutex<Context>>>,
-) -> Result<Option<String>, String> {
+) -> AppResult<Message> {
     let app_state_handle: Arc<Mutex<Context>> = app_state.inner().clone();
 
-    let result = tauri::async_runtime::spawn_blocking(move || {
+    let id = conv_id.clone();
+    let reply = tauri::async_runtime::spawn_blocking(move || {
         let mut ctx = tauri::async_runtime::block_on(app_state_handle.lock());
-        service::continue_conversation(&conv_id, &user_input, window, &mut ctx)
+        service::continue_conversation(
+            &id,
+            &user_input,
+            &attachments.unwrap_or_default(),
+            &window,
+            &mut ctx,
+        )
     })
-    .await
-    .map_err(|e| e.to_string())?;
+    .await??;
 
-    result.map_err(|e| format!("Inference Failed: {:?}", e))?;
-    Ok(None)
+    search::service::index_in_background(app_state.inner().clone(), Some(conv_id));
+    Ok(reply)
 }
 
 #[tauri::command]
-pub async fn get_conversation_ids() -> Result<Vec<String>, String> {
-    Ok(service::get_conversation_ids())
+pub async fn get_conversation_ids(db: State<'_, Database>) -> AppResult<Vec<String>> {
+    service::get_conversation_ids(&db)
 }
 
 #[tauri::command]
-pub async fn get_conversation(conv_id: String) -> Result<Option<Conversation>, String> {
-    service::get_conversation(&conv_id).map_err(|e| e.to_string())
+pub async fn get_conversation(
+    conv_id: String,
+    db: State<'_, Database>,
+) -> AppResult<Option<Conversation>> {
+    service::get_conversation(&db, &conv_id)
 }
 
 #[tauri::command]
-pub async fn delete_conversation(conv_id: String) -> Result<String, String> {
-    service::delete_conversation(&conv_id).map_err(|e| e.to_string())
+pub async fn delete_conversation(conv_id: String, db: State<'_, Database>) -> AppResult<String> {
+    service::delete_conversation(&db, &conv_id)
 }
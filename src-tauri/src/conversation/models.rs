@@ -1,9 +1,69 @@
 use serde::{Deserialize, Serialize};
 
+use crate::adapters::models::ActiveAdapter;
+use crate::attachments::models::Attachment;
+use crate::inference::models::GenerationStats;
+use crate::knowledge::models::Citation;
+use crate::tools::models::ToolCall;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Message {
     pub role: String,
     pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<GenerationStats>,
+    // Knowledge base passages the reply cites
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub citations: Vec<Citation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+    // Tools an assistant message asked for, their results follow as `tool` messages
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    // Tool a `tool` message answers for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    // LoRA adapters a reply was generated with
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub adapters: Vec<ActiveAdapter>,
+}
+
+impl Message {
+    // Content as the model sees it, attached files follow as delimited blocks
+    pub fn prompt_content(&self) -> String {
+        if self.role == "tool" {
+            let response = serde_json::json!({
+                "name": self.name.as_deref().unwrap_or_default(),
+                "content": self.content,
+            });
+            return format!("<tool_response>\n{}\n</tool_response>", response);
+        }
+
+        let mut content = self.content.clone();
+        for attachment in &self.attachments {
+            let note = if attachment.truncated {
+                "\n[truncated]"
+            } else {
+                ""
+            };
+            content.push_str(&format!(
+                "\n\n<attachment name=\"{}\">\n{}{}\n</attachment>",
+                escape_attribute(&attachment.name),
+                attachment.excerpt,
+                note
+            ));
+        }
+        content
+    }
+}
+
+// File names are user text, quotes and angle brackets would end the tag early
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -25,12 +85,65 @@ impl Conversation {
 
     // Add a message to the conversation
     pub fn add_message(&mut self, role: &str, content: &str) {
+        self.add_message_with_attachments(role, content, Vec::new());
+    }
+
+    // Add a message carrying attached files
+    pub fn add_message_with_attachments(
+        &mut self,
+        role: &str,
+        content: &str,
+        attachments: Vec<Attachment>,
+    ) {
         self.body.push(Message {
             role: role.to_string(),
             content: content.to_string(),
+            stats: None,
+            citations: Vec::new(),
+            attachments,
+            tool_calls: Vec::new(),
+            name: None,
+            adapters: Vec::new(),
         });
     }
 
+    // Add a generated reply along with its performance metrics and sources
+    pub fn add_reply(&mut self, content: &str, stats: GenerationStats, citations: Vec<Citation>) {
+        self.body.push(Message {
+            role: "assistant".to_string(),
+            content: content.to_string(),
+            stats: Some(stats),
+            citations,
+            attachments: Vec::new(),
+            tool_calls: Vec::new(),
+            name: None,
+            adapters: Vec::new(),
+        });
+    }
+
+    // Add a reply that asked for tools instead of answering
+    pub fn add_tool_calls(&mut self, content: &str, stats: GenerationStats, calls: Vec<ToolCall>) {
+        self.add_reply(content, stats, Vec::new());
+        if let Some(msg) = self.body.last_mut() {
+            msg.tool_calls = calls;
+        }
+    }
+
+    // Record the adapters the last message was generated with
+    pub fn record_adapters(&mut self, adapters: &[ActiveAdapter]) {
+        if let Some(msg) = self.body.last_mut() {
+            msg.adapters = adapters.to_vec();
+        }
+    }
+
+    // Add the result of a tool call
+    pub fn add_tool_result(&mut self, name: &str, result: &str) {
+        self.add_message("tool", result);
+        if let Some(msg) = self.body.last_mut() {
+            msg.name = Some(name.to_string());
+        }
+    }
+
     // Get the last message (if any)
     pub fn get_last_message(&self) -> Option<&Message> {
         self.body.last()
@@ -1,14 +1,15 @@
-use std::fmt::Error;
-
 use crate::{
     conversation::models::{Conversation, Message},
-    infrastructure::database::Database,
+    infrastructure::{
+        database::Database,
+        error::{AppError, AppResult},
+    },
 };
 
-use rusqlite::{Result, params};
+use rusqlite::params;
 
-pub fn add_conversation(conv: &Conversation) -> Result<()> {
-    let conn = Database::get_db().get_conn();
+pub fn add_conversation(db: &Database, conv: &Conversation) -> AppResult<()> {
+    let conn = db.get_conn()?;
     let body_json = serde_json::to_string(&conv.body)
         .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
     conn.execute(
@@ -18,8 +19,8 @@ pub fn add_conversation(conv: &Conversation) -> Result<()> {
     Ok(())
 }
 
-pub fn update_conversation(conv: &Conversation) -> Result<()> {
-    let conn = Database::get_db().get_conn();
+pub fn update_conversation(db: &Database, conv: &Conversation) -> AppResult<()> {
+    let conn = db.get_conn()?;
     let body_json = serde_json::to_string(&conv.body)
         .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
     conn.execute(
@@ -29,8 +30,8 @@ pub fn update_conversation(conv: &Conversation) -> Result<()> {
     Ok(())
 }
 
-pub fn get_conversation(id: &str) -> Result<Option<Conversation>> {
-    let conn = Database::get_db().get_conn();
+pub fn get_conversation(db: &Database, id: &str) -> AppResult<Option<Conversation>> {
+    let conn = db.get_conn()?;
     let mut stmt = conn.prepare("SELECT id, title, body FROM conversations WHERE id = ?1")?;
     let mut rows = stmt.query(params![id])?;
 
@@ -49,8 +50,8 @@ pub fn get_conversation(id: &str) -> Result<Option<Conversation>> {
     }
 }
 
-pub fn get_conversation_ids() -> Result<Vec<String>> {
-    let conn = Database::get_db().get_conn();
+pub fn get_conversation_ids(db: &Database) -> AppResult<Vec<String>> {
+    let conn = db.get_conn()?;
     let mut stmt =
         conn.prepare("SELECT id, lastUpdated FROM conversations order by lastUpdated DESC")?;
     let ids = stmt
@@ -59,15 +60,11 @@ pub fn get_conversation_ids() -> Result<Vec<String>> {
     Ok(ids)
 }
 
-pub fn delete_conversation(id: &str) -> Result<String, Error> {
-    let conn = Database::get_db().get_conn();
-    if let Ok(deleted) = conn.execute("DELETE FROM conversations where id = ?1", params![id]) {
-        if deleted <= 0 {
-            return Err(Error);
-        } else {
-        }
-    } else {
-        return Err(Error);
+pub fn delete_conversation(db: &Database, id: &str) -> AppResult<String> {
+    let conn = db.get_conn()?;
+    let deleted = conn.execute("DELETE FROM conversations where id = ?1", params![id])?;
+    if deleted == 0 {
+        return Err(AppError::NotFound(format!("Conversation {} not found", id)));
     }
-    return Ok("delete successful".to_string());
+    Ok("delete successful".to_string())
 }
@@ -1,64 +1,212 @@
-use std::fmt::Error;
-use std::vec;
+use std::path::PathBuf;
 
-use crate::conversation::models::Conversation;
+use crate::adapters;
+use crate::attachments;
+use crate::conversation::models::{Conversation, Message};
 use crate::conversation::repository as dao;
+use crate::inference::{
+    self,
+    models::{ChatPrompt, Prompt, StreamingContent},
+};
+use crate::infrastructure::consts;
 use crate::infrastructure::context::Context;
+use crate::infrastructure::database::Database;
+use crate::infrastructure::error::{AppError, AppResult};
+use crate::infrastructure::events::{Event, EventSink};
+use crate::knowledge;
+use crate::memory::{self, models::FactSource};
+use crate::search;
+use crate::tools;
 
-use rusqlite::Result;
-use tauri::Window;
 use uuid::Uuid;
 
-pub fn get_conversation_ids() -> Vec<String> {
-    if let Ok(conversation_ids) = dao::get_conversation_ids() {
-        return conversation_ids;
-    } else {
-        return vec![];
-    }
+pub fn get_conversation_ids(db: &Database) -> AppResult<Vec<String>> {
+    dao::get_conversation_ids(db)
 }
 
-pub fn start_new_conversation(title: &str) -> Result<String> {
+pub fn start_new_conversation(db: &Database, title: &str) -> AppResult<String> {
     let id = Uuid::new_v4().to_string();
     let conversation = Conversation::new(id.clone(), title.to_string());
-    dao::add_conversation(&conversation)?;
+    dao::add_conversation(db, &conversation)?;
     Ok(id)
 }
 
 pub fn continue_conversation(
     conv_id: &str,
     user_input: &str,
-    window: Window,
+    attachment_paths: &[PathBuf],
+    sink: &dyn EventSink,
     ctx: &mut Context,
-) -> Result<Option<String>> {
-    if let Some(mut conversation) = dao::get_conversation(conv_id)? {
-        conversation.add_message("user", user_input);
-
-        // Ensure inference is available
-        let inference = match ctx.inference.as_mut() {
-            Some(i) => i,
-            None => return Ok(None),
+) -> AppResult<Message> {
+    let mut conversation = dao::get_conversation(&ctx.db, conv_id)?
+        .ok_or_else(|| AppError::NotFound(format!("Conversation {} not found", conv_id)))?;
+
+    // Ensure inference is available, an API request may have left another model loaded
+    inference::service::ensure_default_model(ctx)?;
+    if ctx.inference.is_none() {
+        return Err(AppError::ModelLoadFailed("No model is loaded".into()));
+    }
+
+    let token_budget = attachments::service::token_budget(&ctx.config, attachment_paths.len());
+    let attached = attachment_paths
+        .iter()
+        .map(|path| attachments::service::attach(path, token_budget))
+        .collect::<AppResult<Vec<_>>>()?;
+    conversation.add_message_with_attachments(
+        "user",
+        user_input,
+        attached
+            .iter()
+            .map(|(attachment, _)| attachment.clone())
+            .collect(),
+    );
+
+    // The knowledge base only grounds a reply, failing to search it must not block one
+    let passages = knowledge::service::retrieve(user_input, ctx).unwrap_or_else(|e| {
+        eprintln!("Knowledge base search failed: {}", e);
+        Vec::new()
+    });
+
+    let facts = if memory::service::is_enabled(&ctx.db) {
+        Some(
+            memory::service::relevant_facts(user_input, ctx).unwrap_or_else(|e| {
+                eprintln!("Memory lookup failed: {}", e);
+                Vec::new()
+            }),
+        )
+    } else {
+        None
+    };
+
+    // Adapters are switched per conversation, the base weights stay loaded
+    let active_adapters = adapters::service::get_conversation_adapters(&ctx.db, conv_id)?;
+    let lora = adapters::service::lora_adapters(&active_adapters)?;
+
+    let tools = ctx.tools.clone();
+    let definitions = if tools::service::is_enabled(&ctx.db) {
+        tools.definitions()
+    } else {
+        Vec::new()
+    };
+
+    // Each round either answers or asks for tools whose results feed the next one
+    let mut round = 0;
+    let (mut ai_reply, stats) = loop {
+        let inference = ctx
+            .inference
+            .as_mut()
+            .ok_or_else(|| AppError::ModelLoadFailed("No model is loaded".into()))?;
+        inference.set_adapters(&lora)?;
+        let mut prompt = ChatPrompt::new(&conversation).with_passages(&passages);
+        if let Some(facts) = &facts {
+            prompt = prompt.with_memory(facts);
+        }
+        // The last round has no tools so the model has to answer
+        if round < consts::MAX_TOOL_ROUNDS {
+            prompt = prompt.with_tools(&definitions);
+        }
+        let mut stream = ReplyStream {
+            sink,
+            id: conversation.id.clone(),
+            calls: (!prompt.tools.is_empty()).then(tools::parser::CallFilter::default),
+            proposals: facts.is_some().then(memory::service::ProposalFilter::new),
         };
+        let (reply, stats) =
+            inference.generate(Prompt::Chat(prompt), None, |piece| stream.push(piece))?;
+        inference::service::record_generation(&ctx.db, &inference.config.default_model, &stats);
 
-        match inference.generate_text(&conversation, window) {
-            Ok(ai_reply) => {
-                conversation.add_message("assistant", &ai_reply);
-                dao::update_conversation(&conversation)?;
-                Ok(Some(ai_reply))
-            }
-            Err(e) => {
-                eprintln!("AI generation error: {}", e);
-                Ok(None)
+        let calls = tools::parser::parse_tool_calls(&reply, prompt.tools);
+        if calls.is_empty() {
+            stream.finish();
+            break (reply, stats);
+        }
+
+        conversation.add_tool_calls(&reply, stats, calls.clone());
+        conversation.record_adapters(&active_adapters);
+        for call in &calls {
+            let result = tools::service::run(&tools, call, ctx);
+            conversation.add_tool_result(&call.name, &result);
+        }
+        round += 1;
+    };
+
+    if facts.is_some() {
+        let (cleaned, proposals) = memory::service::take_proposals(&ai_reply);
+        ai_reply = cleaned;
+        for proposal in proposals {
+            if let Err(e) = memory::service::add_fact(&ctx.db, &proposal, FactSource::Model) {
+                eprintln!("Failed to save proposed fact: {}", e);
             }
         }
-    } else {
-        Ok(None)
+    }
+    let citations = knowledge::service::cited(&ai_reply, &passages);
+    conversation.add_reply(&ai_reply, stats, citations);
+    conversation.record_adapters(&active_adapters);
+    // Originals are only kept for a turn that completed
+    for (attachment, bytes) in &attached {
+        attachments::service::save_original(&ctx.db, attachment, bytes)?;
+    }
+    dao::update_conversation(&ctx.db, &conversation)?;
+
+    conversation
+        .get_last_message()
+        .cloned()
+        .ok_or_else(|| AppError::Internal("Reply was not stored".into()))
+}
+
+// What the user sees of a reply as it is generated. A round that may call tools only shows
+// what cannot be part of a call, and memory proposals are left out as they are when stored.
+struct ReplyStream<'a> {
+    sink: &'a dyn EventSink,
+    id: String,
+    calls: Option<tools::parser::CallFilter>,
+    proposals: Option<memory::service::ProposalFilter>,
+}
+
+impl ReplyStream<'_> {
+    fn push(&mut self, piece: &str) {
+        let text = match self.calls.as_mut() {
+            Some(calls) => calls.push(piece),
+            None => piece.to_string(),
+        };
+        self.show(&text);
+    }
+
+    // The reply turned out to be an answer, shows what was held back
+    fn finish(mut self) {
+        if let Some(calls) = self.calls.take() {
+            self.show(&calls.finish());
+        }
+        if let Some(proposals) = self.proposals.take() {
+            self.emit(proposals.finish());
+        }
+    }
+
+    fn show(&mut self, text: &str) {
+        let text = match self.proposals.as_mut() {
+            Some(proposals) => proposals.push(text),
+            None => text.to_string(),
+        };
+        self.emit(text);
+    }
+
+    fn emit(&self, content: String) {
+        if !content.is_empty() {
+            self.sink.emit(Event::Stream(StreamingContent {
+                id: self.id.clone(),
+                content,
+            }));
+        }
     }
 }
 
-pub fn get_conversation(id: &str) -> Result<Option<Conversation>> {
-    dao::get_conversation(id)
+pub fn get_conversation(db: &Database, id: &str) -> AppResult<Option<Conversation>> {
+    dao::get_conversation(db, id)
 }
 
-pub fn delete_conversation(id: &str) -> Result<String, Error> {
-    dao::delete_conversation(id)
+pub fn delete_conversation(db: &Database, id: &str) -> AppResult<String> {
+    let deleted = dao::delete_conversation(db, id)?;
+    search::repository::delete_conversation_embeddings(db, id)?;
+    adapters::service::delete_conversation_adapters(db, id)?;
+    Ok(deleted)
 }
@@ -0,0 +1,100 @@
+use std::{collections::HashMap, sync::Arc};
+
+use serde_json::Value;
+use tauri::{State, async_runtime::Mutex};
+
+use crate::{
+    configuration::models::ConfigChange,
+    inference::{
+        models::{BenchmarkResult, ModelStats},
+        service,
+        template::ChatTemplate,
+    },
+    infrastructure::{context::Context, database::Database, error::AppResult},
+};
+
+#[tauri::command]
+pub async fn get_inference_stats(db: State<'_, Database>) -> AppResult<Vec<ModelStats>> {
+    service::get_inference_stats(&db)
+}
+
+#[tauri::command]
+pub async fn benchmark_model(
+    model_name: String,
+    app_state: State<'_, Arc<Mutex<Context>>>,
+) -> AppResult<BenchmarkResult> {
+    let app_state_handle: Arc<Mutex<Context>> = app_state.inner().clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut ctx = tauri::async_runtime::block_on(app_state_handle.lock());
+        service::benchmark_model(model_name, &mut ctx)
+    })
+    .await?
+}
+
+#[tauri::command]
+pub async fn embed_texts(
+    texts: Vec<String>,
+    app_state: State<'_, Arc<Mutex<Context>>>,
+) -> AppResult<Vec<Vec<f32>>> {
+    let app_state_handle: Arc<Mutex<Context>> = app_state.inner().clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut ctx = tauri::async_runtime::block_on(app_state_handle.lock());
+        service::embed(&texts, &mut ctx)
+    })
+    .await?
+}
+
+#[tauri::command]
+pub async fn generate_structured(
+    prompt: String,
+    schema: Value,
+    app_state: State<'_, Arc<Mutex<Context>>>,
+) -> AppResult<Value> {
+    let app_state_handle: Arc<Mutex<Context>> = app_state.inner().clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut ctx = tauri::async_runtime::block_on(app_state_handle.lock());
+        service::generate_structured(&prompt, &schema, &mut ctx)
+    })
+    .await?
+}
+
+// Like `generate_structured`, with a GBNF grammar whose `root` rule accepts the reply
+#[tauri::command]
+pub async fn generate_constrained(
+    prompt: String,
+    grammar: String,
+    app_state: State<'_, Arc<Mutex<Context>>>,
+) -> AppResult<String> {
+    let app_state_handle: Arc<Mutex<Context>> = app_state.inner().clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut ctx = tauri::async_runtime::block_on(app_state_handle.lock());
+        service::generate_constrained(&prompt, &grammar, &mut ctx)
+    })
+    .await?
+}
+
+#[tauri::command]
+pub async fn get_chat_templates() -> AppResult<Vec<ChatTemplate>> {
+    Ok(ChatTemplate::ALL.to_vec())
+}
+
+#[tauri::command]
+pub async fn set_chat_template(
+    model_name: String,
+    template: Option<ChatTemplate>,
+    app_state: State<'_, Arc<Mutex<Context>>>,
+) -> AppResult<ConfigChange> {
+    let mut ctx = app_state.lock().await;
+    service::set_chat_template(&model_name, template, &mut ctx)
+}
+
+#[tauri::command]
+pub async fn get_model_benchmarks(
+    db: State<'_, Database>,
+) -> AppResult<HashMap<String, BenchmarkResult>> {
+    service::get_benchmarks(&db)
+}
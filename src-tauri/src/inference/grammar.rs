@@ -0,0 +1,363 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::{Map, Value};
+
+use crate::infrastructure::error::{AppError, AppResult};
+
+// Shared rules, following the ones llama.cpp's json-schema-to-grammar emits
+const PRIMITIVES: &[(&str, &str)] = &[
+    ("space", r#"| " " | "\n" [ \t]{0,20}"#),
+    ("boolean", r#"("true" | "false") space"#),
+    ("null", r#""null" space"#),
+    ("integral-part", r#"[0] | [1-9] [0-9]{0,15}"#),
+    ("decimal-part", r#"[0-9]{1,16}"#),
+    ("integer", r#"("-"? integral-part) space"#),
+    (
+        "number",
+        r#"("-"? integral-part) ("." decimal-part)? ([eE] [-+]? integral-part)? space"#,
+    ),
+    (
+        "char",
+        r#"[^"\\\x7F\x00-\x1F] | [\\] (["\\bfnrt] | "u" [0-9a-fA-F]{4})"#,
+    ),
+    ("string", r#""\"" char* "\"" space"#),
+    (
+        "object",
+        r#""{" space ( string ":" space value ("," space string ":" space value)* )? "}" space"#,
+    ),
+    (
+        "array",
+        r#""[" space ( value ("," space value)* )? "]" space"#,
+    ),
+    ("value", "object | array | string | number | boolean | null"),
+];
+
+// GBNF grammar whose `root` rule only accepts JSON documents valid against `schema`.
+// Covers types, enum, const, properties, required, items, min/max items and lengths,
+// anyOf/oneOf/allOf of one schema and local `$ref`s. Properties come out in key order,
+// string patterns and formats are not enforced.
+pub fn from_json_schema(schema: &Value) -> AppResult<String> {
+    let mut converter = Converter {
+        root: schema,
+        rules: BTreeMap::new(),
+        refs: HashMap::new(),
+    };
+    let root = converter.visit(schema, "root")?;
+    if root != "root" {
+        converter.rules.insert("root".into(), root);
+    }
+
+    let mut grammar = format!("root ::= {}\n", converter.rules["root"]);
+    for (name, body) in &converter.rules {
+        if name != "root" {
+            grammar.push_str(&format!("{} ::= {}\n", name, body));
+        }
+    }
+    Ok(grammar)
+}
+
+struct Converter<'a> {
+    root: &'a Value,
+    rules: BTreeMap<String, String>,
+    // Rule made for each `$ref` pointer already seen
+    refs: HashMap<String, String>,
+}
+
+impl Converter<'_> {
+    // Returns an expression matching `schema`, adding the rules it needs under `name`
+    fn visit(&mut self, schema: &Value, name: &str) -> AppResult<String> {
+        let schema = match schema {
+            Value::Bool(true) => return Ok(self.primitive("value")),
+            Value::Object(schema) => schema,
+            _ => return Err(invalid("a schema must be an object")),
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            return self.reference(reference);
+        }
+        if let Some(value) = schema.get("const") {
+            return self.add_rule(name, format!("{} space", literal(value)));
+        }
+        if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+            let alternatives: Vec<String> = values.iter().map(literal).collect();
+            return self.add_rule(name, format!("({}) space", alternatives.join(" | ")));
+        }
+        for key in ["anyOf", "oneOf"] {
+            if let Some(options) = schema.get(key).and_then(Value::as_array) {
+                let alternatives = options
+                    .iter()
+                    .enumerate()
+                    .map(|(i, option)| self.visit(option, &format!("{}-{}", name, i)))
+                    .collect::<AppResult<Vec<_>>>()?;
+                return self.add_rule(name, alternatives.join(" | "));
+            }
+        }
+        if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+            return match all.as_slice() {
+                [only] => self.visit(only, name),
+                _ => Err(invalid("allOf is only supported with a single schema")),
+            };
+        }
+
+        match schema.get("type") {
+            Some(Value::Array(types)) => {
+                let alternatives = types
+                    .iter()
+                    .map(|kind| {
+                        let mut single = schema.clone();
+                        single.insert("type".into(), kind.clone());
+                        let kind = kind.as_str().unwrap_or_default();
+                        self.visit(&Value::Object(single), &format!("{}-{}", name, kind))
+                    })
+                    .collect::<AppResult<Vec<_>>>()?;
+                self.add_rule(name, alternatives.join(" | "))
+            }
+            Some(Value::String(kind)) => match kind.as_str() {
+                "object" => self.object(schema, name),
+                "array" => self.array(schema, name),
+                "string" => self.string(schema, name),
+                "integer" | "number" | "boolean" | "null" => Ok(self.primitive(kind)),
+                _ => Err(invalid(&format!("unknown type {}", kind))),
+            },
+            Some(_) => Err(invalid("type must be a string or an array")),
+            None if schema.contains_key("properties") => self.object(schema, name),
+            None if schema.contains_key("items") => self.array(schema, name),
+            None => Ok(self.primitive("value")),
+        }
+    }
+
+    fn object(&mut self, schema: &Map<String, Value>, name: &str) -> AppResult<String> {
+        let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+            return Ok(self.primitive("object"));
+        };
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|keys| keys.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+
+        let mut required_pairs = Vec::new();
+        let mut optional_pairs = Vec::new();
+        for (key, property) in properties {
+            let value = self.visit(property, &format!("{}-{}", name, rule_name(key)))?;
+            let pair = format!(
+                "{} space \":\" space {}",
+                literal(&Value::String(key.clone())),
+                value
+            );
+            if required.contains(&key.as_str()) {
+                required_pairs.push(pair);
+            } else {
+                optional_pairs.push(pair);
+            }
+        }
+
+        let mut body = String::from("\"{\" space ");
+        body.push_str(&required_pairs.join(" \",\" space "));
+        if !optional_pairs.is_empty() {
+            let tail = optional_tail(&optional_pairs);
+            if required_pairs.is_empty() {
+                body.push_str(&format!("({})?", tail));
+            } else {
+                // Every optional pair after a required one is preceded by a comma
+                for pair in &optional_pairs {
+                    body.push_str(&format!(" (\",\" space {})?", pair));
+                }
+            }
+        }
+        body.push_str(" \"}\" space");
+        self.add_rule(name, body)
+    }
+
+    fn array(&mut self, schema: &Map<String, Value>, name: &str) -> AppResult<String> {
+        let item = match schema.get("items") {
+            Some(items) => self.visit(items, &format!("{}-item", name))?,
+            None => self.primitive("value"),
+        };
+        let min = schema.get("minItems").and_then(Value::as_u64).unwrap_or(0);
+        let max = schema.get("maxItems").and_then(Value::as_u64);
+        if max.is_some_and(|max| max < min) {
+            return Err(invalid("maxItems is less than minItems"));
+        }
+
+        let items = match (min, max) {
+            (_, Some(0)) => String::new(),
+            (0, max) => format!("({} {})?", item, repeat_rest(&item, 0, max)),
+            (min, max) => format!("{} {}", item, repeat_rest(&item, min - 1, max)),
+        };
+        self.add_rule(name, format!("\"[\" space {} \"]\" space", items))
+    }
+
+    fn string(&mut self, schema: &Map<String, Value>, name: &str) -> AppResult<String> {
+        let min = schema.get("minLength").and_then(Value::as_u64);
+        let max = schema.get("maxLength").and_then(Value::as_u64);
+        if min.is_none() && max.is_none() {
+            return Ok(self.primitive("string"));
+        }
+        self.primitive("char");
+        let repeat = match max {
+            Some(max) => format!("{{{},{}}}", min.unwrap_or(0), max),
+            None => format!("{{{},}}", min.unwrap_or(0)),
+        };
+        self.add_rule(name, format!("\"\\\"\" char{} \"\\\"\" space", repeat))
+    }
+
+    // Only references into the same document, as in `#/$defs/address`
+    fn reference(&mut self, reference: &str) -> AppResult<String> {
+        let path = reference
+            .strip_prefix("#/")
+            .ok_or_else(|| invalid(&format!("unsupported $ref {}", reference)))?;
+        if let Some(name) = self.refs.get(reference) {
+            return Ok(name.clone());
+        }
+
+        let target = path
+            .split('/')
+            .try_fold(self.root, |schema, key| schema.get(key))
+            .ok_or_else(|| invalid(&format!("$ref {} does not resolve", reference)))?;
+        // Named after the whole pointer, numbered when two pointers sanitize alike
+        let segments: Vec<String> = path
+            .split('/')
+            .map(|segment| rule_name(segment).trim_matches('-').to_string())
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        let base = format!("ref-{}", segments.join("-"));
+        let mut name = base.clone();
+        let mut i = 1;
+        while self.rules.contains_key(&name) {
+            name = format!("{}{}", base, i);
+            i += 1;
+        }
+
+        // Reserved first so recursive schemas refer back to the rule instead of looping
+        self.refs.insert(reference.to_string(), name.clone());
+        self.rules.insert(name.clone(), String::new());
+        let body = self.visit(target, &format!("{}-value", name))?;
+        self.rules.insert(name.clone(), body);
+        Ok(name)
+    }
+
+    // Adds a built-in rule together with the rules it refers to
+    fn primitive(&mut self, name: &str) -> String {
+        if !self.rules.contains_key(name)
+            && let Some((_, body)) = PRIMITIVES.iter().find(|(rule, _)| *rule == name)
+        {
+            self.rules.insert(name.to_string(), body.to_string());
+            for (dependency, _) in PRIMITIVES {
+                if *dependency != name && refers_to(body, dependency) {
+                    self.primitive(dependency);
+                }
+            }
+        }
+        name.to_string()
+    }
+
+    fn add_rule(&mut self, name: &str, body: String) -> AppResult<String> {
+        // Shared rules come from the bodies' references to them
+        for (primitive, _) in PRIMITIVES {
+            if refers_to(&body, primitive) {
+                self.primitive(primitive);
+            }
+        }
+
+        let mut unique = name.to_string();
+        let mut i = 1;
+        while self
+            .rules
+            .get(&unique)
+            .is_some_and(|existing| *existing != body)
+        {
+            unique = format!("{}{}", name, i);
+            i += 1;
+        }
+        self.rules.insert(unique.clone(), body);
+        Ok(unique)
+    }
+}
+
+// Alternatives for optional pairs with no required one before them, so the first present
+// pair has no comma: `a ("," b)? ("," c)? | b ("," c)? | c`
+fn optional_tail(pairs: &[String]) -> String {
+    (0..pairs.len())
+        .map(|first| {
+            let mut alternative = pairs[first].clone();
+            for pair in &pairs[first + 1..] {
+                alternative.push_str(&format!(" (\",\" space {})?", pair));
+            }
+            alternative
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+// Further items after the first, between `min` and `max` of them
+fn repeat_rest(item: &str, min: u64, max: Option<u64>) -> String {
+    let count = match max {
+        Some(max) => format!("{{{},{}}}", min, max.saturating_sub(1)),
+        None => format!("{{{},}}", min),
+    };
+    format!("(\",\" space {}){}", item, count)
+}
+
+// JSON text of `value` as a GBNF string literal
+fn literal(value: &Value) -> String {
+    let json = value.to_string();
+    let mut escaped = String::with_capacity(json.len() + 2);
+    escaped.push('"');
+    for c in json.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+// GBNF rule names only allow letters, digits and dashes
+fn rule_name(key: &str) -> String {
+    key.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+// Whether `body` uses the rule `name` outside of literals and character classes
+fn refers_to(body: &str, name: &str) -> bool {
+    // The character closing the literal or class being skipped
+    let mut closing: Option<char> = None;
+    let mut escaped = false;
+    let mut word = String::new();
+    for c in body.chars().chain([' ']) {
+        if let Some(end) = closing {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                _ if c == end => closing = None,
+                _ => {}
+            }
+            continue;
+        }
+        if c.is_ascii_alphanumeric() || c == '-' {
+            word.push(c);
+            continue;
+        }
+        if word == name {
+            return true;
+        }
+        word.clear();
+        closing = match c {
+            '"' => Some('"'),
+            '[' => Some(']'),
+            _ => None,
+        };
+    }
+    false
+}
+
+fn invalid(reason: &str) -> AppError {
+    AppError::Validation(format!("Unsupported JSON schema: {}", reason))
+}
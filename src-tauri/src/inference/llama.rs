@@ -0,0 +1,668 @@
+use llama_cpp_2::context::LlamaContext;
+use llama_cpp_2::context::params::{self as ctx_params, LlamaContextParams, LlamaPoolingType};
+use llama_cpp_2::llama_backend::LlamaBackend;
+use llama_cpp_2::llama_batch::LlamaBatch;
+use llama_cpp_2::model::params::LlamaModelParams;
+use llama_cpp_2::model::{LlamaChatMessage, LlamaChatTemplate, LlamaLoraAdapter, LlamaModel};
+use llama_cpp_2::sampling::LlamaSampler;
+use llama_cpp_2::token::LlamaToken;
+use self_cell::self_cell;
+use std::collections::HashMap;
+use std::num::NonZero;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use crate::configuration::models::{Config, ContextLimits, KvCacheType, ModelFootprint};
+use crate::inference::models::{
+    ChatPrompt, GenerationStats, LoraAdapter, Prompt, TextGenerator, elapsed_ms, normalize,
+};
+use crate::inference::stop::StopMatcher;
+use crate::inference::template::ChatTemplate;
+use crate::infrastructure::error::{AppError, AppResult};
+use crate::infrastructure::{consts, device};
+
+// llama.cpp allows a single backend initialization per process
+static BACKEND: OnceLock<AppResult<LlamaBackend>> = OnceLock::new();
+
+pub fn backend() -> AppResult<&'static LlamaBackend> {
+    BACKEND
+        .get_or_init(|| {
+            LlamaBackend::init()
+                .map_err(|e| AppError::ModelLoadFailed(format!("Backend init failed: {:?}", e)))
+        })
+        .as_ref()
+        .map_err(|e| e.clone())
+}
+
+// Contexts borrowing the weights, each created only when it is needed
+struct ModelContext<'a> {
+    // None when the model was loaded to embed only
+    generation: Option<LlamaContext<'a>>,
+    // Created by the first embedding and reused by later ones
+    embedding: Option<LlamaContext<'a>>,
+}
+
+impl<'a> ModelContext<'a> {
+    fn generation(&mut self) -> AppResult<&mut LlamaContext<'a>> {
+        self.generation
+            .as_mut()
+            .ok_or_else(|| AppError::Internal("Model is loaded for embeddings only".into()))
+    }
+}
+
+self_cell!(
+    // Weights together with the contexts borrowing them, dropped contexts first
+    struct LoadedModel {
+        owner: LlamaModel,
+
+        #[not_covariant]
+        dependent: ModelContext,
+    }
+);
+
+// SAFETY: the context is only reachable through `&mut LlamaEngine`, which the app keeps
+// behind a mutex, so llama.cpp never sees concurrent calls on it.
+unsafe impl Send for LoadedModel {}
+
+// How a chat is turned into prompt text
+enum PromptTemplate {
+    Builtin(ChatTemplate),
+    // Read from the GGUF, `fallback` is used when llama.cpp cannot apply it
+    Embedded {
+        template: LlamaChatTemplate,
+        fallback: ChatTemplate,
+    },
+}
+
+// LoRA adapters loaded for the model, by file
+struct LoadedAdapters(HashMap<PathBuf, LlamaLoraAdapter>);
+
+// SAFETY: as for `LoadedModel`, adapters are only used through `&mut LlamaEngine`
+unsafe impl Send for LoadedAdapters {}
+
+// Runs GGUF models through llama.cpp
+pub struct LlamaEngine {
+    loaded: LoadedModel,
+    adapters: LoadedAdapters,
+    // Adapters set on the current context
+    applied: Vec<LoraAdapter>,
+}
+
+impl LlamaEngine {
+    // Loads the configured model and fits the config's sizes to the memory left after it
+    pub fn load(config: &mut Config) -> AppResult<Self> {
+        // Sample memory before the weights are mapped so they are not counted twice
+        let memory = device::memory();
+        let model_path = config.get_model_path()?;
+        let model = Self::load_model(&model_path, config)?;
+
+        let footprint = Self::footprint(&model, &model_path, config.kv_cache_types());
+        config.apply_limits(ContextLimits::compute(memory, &footprint));
+
+        let loaded = LoadedModel::try_new(model, |model| Self::new_contexts(model, config))?;
+        Ok(Self::with_loaded(loaded))
+    }
+
+    // Loads the configured model without a generation context, its KV cache would go unused
+    pub fn load_for_embeddings(config: &Config) -> AppResult<Self> {
+        let model = Self::load_model(&config.get_model_path()?, config)?;
+        let loaded = LoadedModel::new(model, |_| ModelContext {
+            generation: None,
+            embedding: None,
+        });
+        Ok(Self::with_loaded(loaded))
+    }
+
+    fn load_model(model_path: &str, config: &Config) -> AppResult<LlamaModel> {
+        LlamaModel::load_from_file(backend()?, model_path, &Self::model_params(config)?)
+            .map_err(|e| AppError::ModelLoadFailed(format!("{:?}", e)))
+    }
+
+    fn with_loaded(loaded: LoadedModel) -> Self {
+        Self {
+            loaded,
+            adapters: LoadedAdapters(HashMap::new()),
+            applied: Vec::new(),
+        }
+    }
+
+    fn model_params(config: &Config) -> AppResult<LlamaModelParams> {
+        // Offloading is meaningless without a GPU backend compiled in
+        let n_gpu_layers = if backend()?.supports_gpu_offload() {
+            config.n_gpu_layers
+        } else {
+            0
+        };
+
+        Ok(LlamaModelParams::default()
+            .with_n_gpu_layers(n_gpu_layers)
+            .with_use_mmap(config.use_mmap)
+            .with_use_mlock(config.use_mlock))
+    }
+
+    fn new_contexts<'a>(model: &'a LlamaModel, config: &Config) -> AppResult<ModelContext<'a>> {
+        Ok(ModelContext {
+            generation: Some(Self::new_context(model, config)?),
+            embedding: None,
+        })
+    }
+
+    fn new_context<'a>(model: &'a LlamaModel, config: &Config) -> AppResult<LlamaContext<'a>> {
+        let flash_attention = if config.flash_attention {
+            llama_cpp_sys_2::LLAMA_FLASH_ATTN_TYPE_ENABLED
+        } else {
+            llama_cpp_sys_2::LLAMA_FLASH_ATTN_TYPE_DISABLED
+        };
+        let (type_k, type_v) = config.kv_cache_types();
+
+        let n_batch = u32::try_from(config.batch_size)
+            .map_err(|_| AppError::Validation("Batch size out of range".into()))?;
+        let n_ctx = u32::try_from(config.max_context_length)
+            .ok()
+            .and_then(NonZero::new)
+            .ok_or_else(|| AppError::Validation("Context length out of range".into()))?;
+
+        let ctx_params = LlamaContextParams::default()
+            .with_n_batch(n_batch)
+            .with_n_ctx(Some(n_ctx))
+            .with_n_threads(config.n_threads as i32)
+            .with_n_threads_batch(config.n_threads_batch as i32)
+            .with_flash_attention_policy(flash_attention)
+            .with_type_k(Self::kv_type(type_k))
+            .with_type_v(Self::kv_type(type_v));
+
+        model
+            .new_context(backend()?, ctx_params)
+            .map_err(|e| AppError::ModelLoadFailed(format!("Session creation error: {:?}", e)))
+    }
+
+    // Separate context with embeddings enabled, the generation context does not output them
+    fn embedding_context<'a>(
+        model: &'a LlamaModel,
+        config: &Config,
+        n_ctx: u32,
+    ) -> AppResult<LlamaContext<'a>> {
+        let ctx_params = LlamaContextParams::default()
+            .with_embeddings(true)
+            .with_pooling_type(LlamaPoolingType::Unspecified)
+            .with_n_ctx(NonZero::new(n_ctx))
+            .with_n_batch(n_ctx)
+            .with_n_ubatch(n_ctx)
+            .with_n_threads(config.n_threads as i32)
+            .with_n_threads_batch(config.n_threads_batch as i32);
+
+        model
+            .new_context(backend()?, ctx_params)
+            .map_err(|e| AppError::ModelLoadFailed(format!("Session creation error: {:?}", e)))
+    }
+
+    fn run_embed(&mut self, texts: &[String], config: &Config) -> AppResult<Vec<Vec<f32>>> {
+        self.loaded.with_dependent_mut(|model, contexts| {
+            let n_ctx = config
+                .batch_size
+                .min(model.n_ctx_train() as u64)
+                .clamp(1, consts::EMBEDDING_CONTEXT_TOKENS) as u32;
+            // The context is kept between calls, only a new batch size replaces it
+            let ctx = match &mut contexts.embedding {
+                Some(ctx) if ctx.n_ctx() == n_ctx => ctx,
+                slot => slot.insert(Self::embedding_context(model, config, n_ctx)?),
+            };
+            let mut batch = LlamaBatch::new(n_ctx as usize, 1);
+
+            let mut vectors = Vec::with_capacity(texts.len());
+            for text in texts {
+                let mut tokens = model
+                    .str_to_token(text, llama_cpp_2::model::AddBos::Always)
+                    .map_err(|e| AppError::Validation(format!("Tokenization error: {:?}", e)))?;
+                // Embedding models only see their window, the head of a text carries its topic
+                tokens.truncate(n_ctx as usize);
+                if tokens.is_empty() {
+                    return Err(AppError::Validation("Cannot embed an empty text".into()));
+                }
+
+                ctx.clear_kv_cache();
+                batch.clear();
+                // Every position outputs so the vector can be pooled here when the model has no pooling
+                batch
+                    .add_sequence(&tokens, 0, true)
+                    .map_err(|e| AppError::Internal(format!("Batch add failed: {:?}", e)))?;
+                ctx.decode(&mut batch)
+                    .map_err(|e| AppError::Internal(format!("Decode failed: {:?}", e)))?;
+
+                let vector = match ctx.embeddings_seq_ith(0) {
+                    Ok(pooled) => pooled.to_vec(),
+                    Err(_) => Self::mean_pool(ctx, tokens.len())?,
+                };
+                vectors.push(normalize(vector));
+            }
+            Ok(vectors)
+        })
+    }
+
+    fn mean_pool(ctx: &LlamaContext<'_>, n_tokens: usize) -> AppResult<Vec<f32>> {
+        let mut pooled: Vec<f32> = Vec::new();
+        for i in 0..n_tokens {
+            let embedding = ctx
+                .embeddings_ith(i as i32)
+                .map_err(|e| AppError::Internal(format!("Embedding failed: {:?}", e)))?;
+            if pooled.is_empty() {
+                pooled = vec![0.0; embedding.len()];
+            }
+            for (sum, value) in pooled.iter_mut().zip(embedding) {
+                *sum += value;
+            }
+        }
+        for sum in &mut pooled {
+            *sum /= n_tokens as f32;
+        }
+        Ok(pooled)
+    }
+
+    pub fn model(&self) -> &LlamaModel {
+        self.loaded.borrow_owner()
+    }
+
+    fn kv_type(kv_cache_type: KvCacheType) -> ctx_params::KvCacheType {
+        match kv_cache_type {
+            KvCacheType::F16 => ctx_params::KvCacheType::F16,
+            KvCacheType::Q8_0 => ctx_params::KvCacheType::Q8_0,
+            KvCacheType::Q4_0 => ctx_params::KvCacheType::Q4_0,
+        }
+    }
+
+    // KV cache holds one key and one value vector per layer for every token
+    fn footprint(
+        model: &LlamaModel,
+        path: &str,
+        (type_k, type_v): (KvCacheType, KvCacheType),
+    ) -> ModelFootprint {
+        let n_layer = model.n_layer() as u64;
+        let n_embd = model.n_embd().max(0) as u64;
+        let n_head = model.n_head().max(1) as u64;
+        let n_head_kv = model.n_head_kv() as u64;
+        let n_embd_kv = n_embd * n_head_kv / n_head;
+        let (k_bytes, k_elements) = type_k.element_size();
+        let (v_bytes, v_elements) = type_v.element_size();
+
+        ModelFootprint {
+            model_bytes: std::fs::metadata(path)
+                .map(|m| m.len())
+                .unwrap_or_else(|_| model.size()),
+            bytes_per_token: n_layer * n_embd_kv * k_bytes / k_elements
+                + n_layer * n_embd_kv * v_bytes / v_elements,
+            n_ctx_train: model.n_ctx_train() as u64,
+        }
+    }
+
+    pub fn tokenize(&self, text: &str, config: &Config) -> AppResult<Vec<LlamaToken>> {
+        let mut tokens = self
+            .model()
+            .str_to_token(text, llama_cpp_2::model::AddBos::Always)
+            .map_err(|e| AppError::Validation(format!("Tokenization error: {:?}", e)))?;
+        let limit = config
+            .max_context_length
+            .saturating_sub(config.max_output_length)
+            .max(1) as usize;
+        // Keep the end of an overlong prompt, it is what the continuation depends on
+        if tokens.len() > limit {
+            tokens.drain(..tokens.len() - limit);
+        }
+        Ok(tokens)
+    }
+
+    fn decode_loop(
+        model: &LlamaModel,
+        ctx: &mut LlamaContext<'_>,
+        config: &Config,
+        grammar: Option<&str>,
+        tokens_list: Vec<LlamaToken>,
+        mut on_piece: impl FnMut(&str),
+    ) -> AppResult<(String, GenerationStats)> {
+        let started = Instant::now();
+        let mut stats = GenerationStats {
+            prompt_tokens: tokens_list.len() as u64,
+            ..GenerationStats::default()
+        };
+        ctx.clear_kv_cache();
+
+        let mut batch = LlamaBatch::new(config.batch_size as usize, 8);
+
+        let last_index = tokens_list.len() as i32 - 1;
+
+        for (i, token) in (0_i32..).zip(tokens_list.into_iter()) {
+            batch
+                .add(token, i, &[0], i == last_index)
+                .map_err(|_| AppError::ContextOverflow("Prompt exceeds the batch size".into()))?;
+        }
+
+        ctx.decode(&mut batch)
+            .map_err(|e| AppError::Internal(format!("Decode failed: {:?}", e)))?;
+        stats.prompt_ms = elapsed_ms(started);
+        let generation_started = Instant::now();
+
+        let mut n_cur: u64 = batch.n_tokens() as u64;
+        let cur: u64 = batch.n_tokens() as u64;
+        let decoder: &mut encoding_rs::Decoder = &mut encoding_rs::UTF_8.new_decoder();
+
+        let mut samplers = Vec::new();
+        // First in the chain so the remaining samplers only see tokens the grammar allows
+        if let Some(grammar) = grammar {
+            samplers.push(
+                LlamaSampler::grammar(model, grammar, "root")
+                    .map_err(|e| AppError::Validation(format!("Invalid grammar: {:?}", e)))?,
+            );
+        }
+        samplers.push(LlamaSampler::temp(config.temperature));
+        samplers.push(LlamaSampler::dist(1));
+        let mut sampler = LlamaSampler::chain(samplers, false);
+        let mut message = String::new();
+        let mut stop = StopMatcher::new(&config.stop_sequences);
+
+        while n_cur < config.batch_size && n_cur - cur < config.max_output_length {
+            let token = sampler.sample(ctx, batch.n_tokens() - 1);
+            sampler.accept(token);
+            if stats.generated_tokens == 0 {
+                stats.time_to_first_token_ms = elapsed_ms(started);
+            }
+            // Covers every end of turn token, such as <|eot_id|>, <end_of_turn> and <|im_end|>
+            if model.is_eog_token(token) {
+                break;
+            }
+            stats.generated_tokens += 1;
+
+            let output_string = model
+                .token_to_piece(token, decoder, true, None)
+                .map_err(|e| AppError::Internal(format!("Detokenization failed: {:?}", e)))?;
+
+            let (settled, stopped) = stop.push(&output_string);
+            if !settled.is_empty() {
+                message += &settled;
+                on_piece(&settled);
+            }
+            if stopped {
+                break;
+            }
+            batch.clear();
+            if batch.add(token, n_cur as i32, &[0], true).is_err() {
+                break;
+            }
+            n_cur += 1;
+
+            if ctx.decode(&mut batch).is_err() {
+                break;
+            }
+        }
+
+        // Held back text turned out not to be a stop string
+        let rest = stop.finish();
+        if !rest.is_empty() {
+            message += &rest;
+            on_piece(&rest);
+        }
+
+        stats.generation_ms = elapsed_ms(generation_started);
+        if stats.generation_ms > 0.0 {
+            stats.tokens_per_second = stats.generated_tokens as f64 * 1000.0 / stats.generation_ms;
+        }
+        Ok((message, stats))
+    }
+
+    // Times prompt processing and token generation like llama-bench's pp and tg tests,
+    // returning the prompt size actually used with both durations in milliseconds
+    fn run_benchmark(
+        &mut self,
+        n_prompt: usize,
+        n_gen: usize,
+        config: &Config,
+    ) -> AppResult<(usize, f64, f64)> {
+        let batch_size = config.batch_size as usize;
+
+        self.loaded
+            .with_dependent_mut(|model, contexts| -> AppResult<(usize, f64, f64)> {
+                let ctx = contexts.generation()?;
+                let n_vocab = model.n_vocab().max(1) as u32;
+                let n_ctx = ctx.n_ctx() as usize;
+                let n_prompt = n_prompt
+                    .min(n_ctx.saturating_sub(n_gen))
+                    .min(batch_size)
+                    .max(1);
+
+                // Token content does not affect timing, a fixed LCG keeps runs comparable
+                let mut seed: u32 = 42;
+                let mut next_token = || {
+                    seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                    LlamaToken::new(((seed >> 8) % n_vocab) as i32)
+                };
+
+                ctx.clear_kv_cache();
+                let mut batch = LlamaBatch::new(n_prompt, 1);
+                for i in 0..n_prompt {
+                    batch
+                        .add(next_token(), i as i32, &[0], i == n_prompt - 1)
+                        .map_err(|e| AppError::Internal(format!("Batch add failed: {:?}", e)))?;
+                }
+
+                let started = Instant::now();
+                ctx.decode(&mut batch)
+                    .map_err(|e| AppError::Internal(format!("Decode failed: {:?}", e)))?;
+                let prompt_ms = elapsed_ms(started);
+
+                let started = Instant::now();
+                for i in 0..n_gen {
+                    batch.clear();
+                    batch
+                        .add(next_token(), (n_prompt + i) as i32, &[0], true)
+                        .map_err(|e| AppError::Internal(format!("Batch add failed: {:?}", e)))?;
+                    ctx.decode(&mut batch)
+                        .map_err(|e| AppError::Internal(format!("Decode failed: {:?}", e)))?;
+                }
+                let generation_ms = elapsed_ms(started);
+
+                ctx.clear_kv_cache();
+                Ok((n_prompt, prompt_ms, generation_ms))
+            })
+    }
+
+    // The override saved for this model, else the GGUF's own template, else a guess by name
+    fn template(&self, config: &Config) -> PromptTemplate {
+        if let Some(template) = config.chat_templates.get(&config.default_model) {
+            return PromptTemplate::Builtin(*template);
+        }
+        let fallback = ChatTemplate::guess(&config.default_model);
+        match self.model().chat_template(None) {
+            Ok(template) => PromptTemplate::Embedded { template, fallback },
+            Err(_) => PromptTemplate::Builtin(fallback),
+        }
+    }
+
+    fn apply_template(&self, template: &PromptTemplate, messages: &[(String, String)]) -> String {
+        let (embedded, fallback) = match template {
+            PromptTemplate::Builtin(builtin) => return builtin.render(messages),
+            PromptTemplate::Embedded { template, fallback } => (template, fallback),
+        };
+        let applied = messages
+            .iter()
+            .map(|(role, content)| LlamaChatMessage::new(role.clone(), content.clone()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("{:?}", e))
+            .and_then(|chat| {
+                self.model()
+                    .apply_chat_template(embedded, &chat, true)
+                    .map_err(|e| format!("{:?}", e))
+            });
+        // A template llama.cpp cannot apply is treated like a missing one
+        applied.unwrap_or_else(|e| {
+            eprintln!("Chat template failed, using {:?}: {}", fallback, e);
+            fallback.render(messages)
+        })
+    }
+
+    pub fn format_prompt(
+        &self,
+        prompt: &ChatPrompt<'_>,
+        config: &Config,
+    ) -> AppResult<Vec<LlamaToken>> {
+        // Copied so passages can be dropped when the prompt does not fit
+        let mut prompt = *prompt;
+
+        // 1. Start with all current messages
+        let mut body_messages: Vec<(String, String)> = prompt
+            .messages()
+            .map(|msg| {
+                // Not every template knows a tool role, results go back as a user turn
+                let role = if msg.role == "user" || msg.role == "tool" {
+                    "user"
+                } else {
+                    "assistant"
+                };
+                (role.to_string(), msg.prompt_content())
+            })
+            .collect();
+
+        let model = self.model();
+        let template = self.template(config);
+
+        let mut tokens: Vec<LlamaToken>;
+        let reserve_for_output = config.max_output_length as usize;
+
+        // 2. Sliding Window: Remove oldest messages until the prompt fits
+        // We loop, checking if (System + Body + New Output) <= Context Limit
+        loop {
+            let system_msg = ("system".to_string(), prompt.system_message(config));
+            let mut current_chat = vec![system_msg];
+            current_chat.extend(body_messages.clone());
+
+            let chat_str = self.apply_template(&template, &current_chat);
+
+            tokens = model
+                .str_to_token(&chat_str, llama_cpp_2::model::AddBos::Never)
+                .map_err(|e| AppError::Validation(format!("Tokenization error: {:?}", e)))?;
+
+            // Check if we are within bounds
+            if tokens.len() + reserve_for_output <= config.max_context_length as usize {
+                break;
+            }
+
+            // If too long, remove the oldest message (index 0). The latest one is kept until
+            // the passages retrieved for it are gone, least relevant first
+            if body_messages.len() > 1 {
+                body_messages.remove(0);
+            } else if let Some((_, kept)) = prompt.passages.split_last() {
+                prompt.passages = kept;
+            } else if !body_messages.is_empty() {
+                body_messages.remove(0);
+            } else {
+                // If even the system prompt alone is too long, we must truncate the tokens directly,
+                // keeping the end so the opening of the assistant's turn survives
+                let limit = (config.max_context_length as usize).saturating_sub(reserve_for_output);
+                if limit == 0 {
+                    return Err(AppError::ContextOverflow(
+                        "No room left for the prompt after reserving output tokens".into(),
+                    ));
+                }
+                tokens.drain(..tokens.len() - limit);
+                break;
+            }
+        }
+
+        Ok(tokens)
+    }
+}
+
+impl TextGenerator for LlamaEngine {
+    fn generate(
+        &mut self,
+        prompt: Prompt<'_>,
+        config: &Config,
+        grammar: Option<&str>,
+        on_piece: &mut dyn FnMut(&str),
+    ) -> AppResult<(String, GenerationStats)> {
+        let tokens_list = match prompt {
+            Prompt::Chat(chat) => self.format_prompt(&chat, config)?,
+            Prompt::Text(text) => self.tokenize(text, config)?,
+        };
+
+        self.loaded.with_dependent_mut(|model, contexts| {
+            Self::decode_loop(
+                model,
+                contexts.generation()?,
+                config,
+                grammar,
+                tokens_list,
+                on_piece,
+            )
+        })
+    }
+
+    // Recreates the context for new size settings while keeping the loaded weights
+    fn rebuild_context(self: Box<Self>, config: &Config) -> AppResult<Box<dyn TextGenerator>> {
+        let Self {
+            loaded, adapters, ..
+        } = *self;
+        let model = loaded.into_owner();
+        let loaded = LoadedModel::try_new(model, |model| Self::new_contexts(model, config))?;
+
+        // Loaded adapters are kept, the new context starts without any set
+        Ok(Box::new(Self {
+            loaded,
+            adapters,
+            applied: Vec::new(),
+        }))
+    }
+
+    fn benchmark(
+        &mut self,
+        n_prompt: usize,
+        n_gen: usize,
+        config: &Config,
+    ) -> AppResult<(usize, f64, f64)> {
+        self.run_benchmark(n_prompt, n_gen, config)
+    }
+
+    fn embed(&mut self, texts: &[String], config: &Config) -> AppResult<Vec<Vec<f32>>> {
+        self.run_embed(texts, config)
+    }
+
+    fn set_adapters(&mut self, adapters: &[LoraAdapter]) -> AppResult<()> {
+        if self.applied == adapters {
+            return Ok(());
+        }
+        // Each file is loaded once and stays available until the model is unloaded
+        for adapter in adapters {
+            if !self.adapters.0.contains_key(&adapter.path) {
+                let lora = self.model().lora_adapter_init(&adapter.path).map_err(|e| {
+                    AppError::Validation(format!(
+                        "Adapter {} does not fit this model: {:?}",
+                        adapter.path.display(),
+                        e
+                    ))
+                })?;
+                self.adapters.0.insert(adapter.path.clone(), lora);
+            }
+        }
+
+        let previous = std::mem::take(&mut self.applied);
+        let loras = &mut self.adapters.0;
+        self.loaded.with_dependent_mut(|_, contexts| {
+            let ctx = contexts.generation()?;
+            for adapter in &previous {
+                if let Some(lora) = loras.get_mut(&adapter.path) {
+                    ctx.lora_adapter_remove(lora).map_err(|e| {
+                        AppError::Internal(format!("Failed to remove adapter: {:?}", e))
+                    })?;
+                }
+            }
+            for adapter in adapters {
+                if let Some(lora) = loras.get_mut(&adapter.path) {
+                    ctx.lora_adapter_set(lora, adapter.scale).map_err(|e| {
+                        AppError::Internal(format!("Failed to set adapter: {:?}", e))
+                    })?;
+                }
+            }
+            Ok::<_, AppError>(())
+        })?;
+        self.applied = adapters.to_vec();
+        Ok(())
+    }
+}
@@ -1,2 +1,9 @@
+pub mod controller;
+pub mod grammar;
+pub mod llama;
 pub mod models;
+pub mod repository;
+pub mod scripted;
 pub mod service;
+pub mod stop;
+pub mod template;
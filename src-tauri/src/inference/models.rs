@@ -1,192 +1,351 @@
-use llama_cpp_2::context::LlamaContext;
-use llama_cpp_2::context::params::LlamaContextParams;
-use llama_cpp_2::llama_backend::LlamaBackend;
-use llama_cpp_2::llama_batch::LlamaBatch;
-use llama_cpp_2::model::params::LlamaModelParams;
-use llama_cpp_2::model::{LlamaChatMessage, LlamaModel};
-use llama_cpp_2::sampling::LlamaSampler;
-use std::num::NonZero;
-use std::sync::Arc;
-use tauri::{Emitter, Window};
+use std::path::PathBuf;
+use std::time::Instant;
 
 use crate::configuration::models::Config;
-use crate::conversation::models::Conversation;
+use crate::conversation::models::{Conversation, Message};
+use crate::inference::llama::LlamaEngine;
+use crate::infrastructure::consts;
+use crate::infrastructure::error::{AppError, AppResult};
+use crate::infrastructure::events::{Event, EventSink};
+use crate::knowledge::models::Passage;
+use crate::memory::models::Fact;
 use crate::models::models::Model;
+use crate::tools::models::ToolDefinition;
 
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
 pub struct StreamingContent {
     pub id: String,
     pub content: String,
 }
 
-pub struct Inference {
-    model: Arc<LlamaModel>,
-    pub ctx: LlamaContext<'static>,
-    pub model_attrs: Model,
-    pub config: Config,
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct GenerationStats {
+    pub prompt_tokens: u64,
+    pub prompt_ms: f64,
+    pub generated_tokens: u64,
+    pub generation_ms: f64,
+    pub time_to_first_token_ms: f64,
+    pub tokens_per_second: f64,
 }
 
-impl Inference {
-    pub fn init(config: &Config) -> Result<Self, String> {
-        let backend =
-            Arc::new(LlamaBackend::init().map_err(|e| format!("Backend init failed: {:?}", e))?);
-        let model = Arc::new(
-            LlamaModel::load_from_file(
-                &backend,
-                config.get_model_path(),
-                &LlamaModelParams::default(),
-            )
-            .map_err(|e| format!("Model load failed: {:?}", e))?,
-        );
-
-        let ctx_params = LlamaContextParams::default()
-            .with_n_batch(config.batch_size.try_into().unwrap())
-            .with_n_ctx(Some(
-                NonZero::try_from(config.max_context_length as u32).unwrap(),
-            ));
-
-        let ctx = unsafe {
-            let internal_ctx = model
-                .new_context(&backend, ctx_params)
-                .map_err(|e| format!("Session creation error: {:?}", e))?;
-            std::mem::transmute::<LlamaContext<'_>, LlamaContext<'static>>(internal_ctx)
-        };
+// Rolling averages over the most recent generations of one model
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ModelStats {
+    pub model_name: String,
+    pub samples: u64,
+    pub prompt_tokens_per_second: f64,
+    pub tokens_per_second: f64,
+    pub time_to_first_token_ms: f64,
+    pub generated_tokens: f64,
+}
 
-        Ok(Self {
-            model,
-            ctx,
-
-            model_attrs: config
-                .get_available_models()
-                .get(&config.default_model)
-                .cloned()
-                .unwrap(),
-            config: config.clone(),
-        })
+// Throughput of a downloaded model measured with synthetic pp/tg workloads
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BenchmarkResult {
+    pub model_name: String,
+    pub load_ms: f64,
+    pub prompt_tokens: u64,
+    pub prompt_tokens_per_second: f64,
+    pub generated_tokens: u64,
+    pub tokens_per_second: f64,
+    // Highest resident size of the process during load and run, above what it held before
+    pub peak_memory_bytes: u64,
+}
+
+// A LoRA adapter file applied on top of the loaded weights
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoraAdapter {
+    pub path: PathBuf,
+    pub scale: f32,
+}
+
+pub enum Prompt<'a> {
+    // Rendered with the model's chat template
+    Chat(ChatPrompt<'a>),
+    // Continued as is
+    Text(&'a str),
+}
+
+// A conversation together with the context added to its system message
+#[derive(Clone, Copy)]
+pub struct ChatPrompt<'a> {
+    pub conversation: &'a Conversation,
+    // Knowledge base passages, cited by their 1-based position
+    pub passages: &'a [Passage],
+    // Remembered facts, `None` when memory is off
+    pub memory: Option<&'a [Fact]>,
+    // Tools the model may call
+    pub tools: &'a [ToolDefinition],
+}
+
+impl<'a> ChatPrompt<'a> {
+    pub fn new(conversation: &'a Conversation) -> Self {
+        ChatPrompt {
+            conversation,
+            passages: &[],
+            memory: None,
+            tools: &[],
+        }
     }
 
-    pub fn generate_text(&mut self, conv: &Conversation, window: Window) -> Result<String, String> {
-        let model = &self.model;
-        self.ctx.clear_kv_cache();
+    pub fn with_passages(self, passages: &'a [Passage]) -> Self {
+        ChatPrompt { passages, ..self }
+    }
+
+    pub fn with_memory(self, facts: &'a [Fact]) -> Self {
+        ChatPrompt {
+            memory: Some(facts),
+            ..self
+        }
+    }
 
-        let mut batch = LlamaBatch::new(self.config.batch_size as usize, 8);
+    pub fn with_tools(self, tools: &'a [ToolDefinition]) -> Self {
+        ChatPrompt { tools, ..self }
+    }
 
-        let tokens_list = self.format_prompt(conv)?;
-        let last_index = tokens_list.len() as i32 - 1;
+    // A leading system message, as sent by API clients, replaces the configured prompt
+    pub fn system_message(&self, config: &Config) -> String {
+        let mut system = self
+            .conversation
+            .body
+            .iter()
+            .find(|msg| msg.role == "system")
+            .map(|msg| msg.content.clone())
+            .unwrap_or_else(|| config.system_prompt.clone());
 
-        for (i, token) in (0_i32..).zip(tokens_list.into_iter()) {
-            batch.add(token, i, &[0], i == last_index).unwrap();
+        if let Some(facts) = self.memory {
+            system.push_str("\n\n");
+            system.push_str(consts::MEMORY_INSTRUCTION);
+            if !facts.is_empty() {
+                system.push_str("\n\n");
+                system.push_str(consts::MEMORY_FACTS_HEADER);
+                for fact in facts {
+                    system.push_str(&format!("\n- {}", fact.content));
+                }
+            }
         }
 
-        self.ctx
-            .decode(&mut batch)
-            .map_err(|e| format!("Decode failed: {:?}", e))?;
-
-        let mut n_cur: u64 = batch.n_tokens() as u64;
-        let cur: u64 = batch.n_tokens() as u64;
-        let decoder: &mut encoding_rs::Decoder = &mut encoding_rs::UTF_8.new_decoder();
-
-        let mut sampler = LlamaSampler::chain(
-            vec![
-                LlamaSampler::temp(self.config.temperature),
-                LlamaSampler::dist(1),
-            ],
-            false,
-        );
-        let mut message = String::new();
-
-        while n_cur < self.config.batch_size && n_cur - cur < self.config.max_output_length {
-            let token = sampler.sample(&self.ctx, batch.n_tokens() - 1);
-            sampler.accept(token);
-            if token == model.token_eos() {
-                break;
+        if !self.tools.is_empty() {
+            system.push_str("\n\n");
+            system.push_str(consts::TOOLS_INSTRUCTION);
+            system.push_str("\n<tools>");
+            for tool in self.tools {
+                let definition = serde_json::json!({
+                    "type": "function",
+                    "function": tool,
+                });
+                system.push_str(&format!("\n{}", definition));
             }
+            system.push_str("\n</tools>");
+        }
 
-            let output_string = model.token_to_piece(token, decoder, true, None).unwrap();
-
-            message += &output_string;
-            let _ = window.emit(
-                "llm-stream",
-                StreamingContent {
-                    id: conv.id.clone(),
-                    content: output_string.clone(),
-                },
-            );
-            batch.clear();
-            batch.add(token, n_cur as i32, &[0], true).unwrap();
-            n_cur += 1;
-
-            if let Err(_) = self.ctx.decode(&mut batch) {
-                break;
+        if !self.passages.is_empty() {
+            system.push_str("\n\n");
+            system.push_str(consts::KB_SOURCES_INSTRUCTION);
+            for (i, passage) in self.passages.iter().enumerate() {
+                system.push_str(&format!(
+                    "\n\n[{}] {}\n{}",
+                    i + 1,
+                    passage.document_name,
+                    passage.text
+                ));
             }
         }
-        Ok(message)
+        system
     }
 
-    pub fn format_prompt(
-        &self,
-        conv: &Conversation,
-    ) -> Result<Vec<llama_cpp_2::token::LlamaToken>, String> {
-        let system_msg =
-            LlamaChatMessage::new("system".to_string(), self.config.system_prompt.clone())
-                .map_err(|e| format!("Invalid system prompt: {:?}", e))?;
-
-        // 1. Start with all current messages
-        let mut body_messages: Vec<LlamaChatMessage> = conv
+    pub fn messages(&self) -> impl Iterator<Item = &'a Message> {
+        self.conversation
             .body
             .iter()
-            .map(|msg| {
-                let role = if msg.role == "user" {
-                    "user"
-                } else {
-                    "assistant"
-                };
-                LlamaChatMessage::new(role.to_string(), msg.content.clone()).unwrap()
+            .filter(|msg| msg.role != "system")
+    }
+}
+
+// Turns prompts into text, implemented by llama.cpp and by the scripted engine used in tests
+pub trait TextGenerator: Send {
+    // `grammar` is GBNF with a `root` rule, only tokens it allows are ever sampled
+    fn generate(
+        &mut self,
+        prompt: Prompt<'_>,
+        config: &Config,
+        grammar: Option<&str>,
+        on_piece: &mut dyn FnMut(&str),
+    ) -> AppResult<(String, GenerationStats)>;
+
+    // Applies new sizes, threads and cache types without reloading the weights
+    fn rebuild_context(self: Box<Self>, config: &Config) -> AppResult<Box<dyn TextGenerator>>;
+
+    // Times prompt processing and token generation like llama-bench's pp and tg tests,
+    // returning the prompt size actually used with both durations in milliseconds
+    fn benchmark(
+        &mut self,
+        n_prompt: usize,
+        n_gen: usize,
+        config: &Config,
+    ) -> AppResult<(usize, f64, f64)>;
+
+    // One pooled, unit length vector per text
+    fn embed(&mut self, texts: &[String], config: &Config) -> AppResult<Vec<Vec<f32>>>;
+
+    // Replaces the adapters used by later generations, the base weights stay loaded
+    fn set_adapters(&mut self, adapters: &[LoraAdapter]) -> AppResult<()>;
+}
+
+pub struct Inference {
+    engine: Box<dyn TextGenerator>,
+    pub model_attrs: Model,
+    pub config: Config,
+}
+
+impl Inference {
+    pub fn init(config: &mut Config) -> AppResult<Self> {
+        let model_attrs = Self::model_attrs(config)?;
+        let engine = LlamaEngine::load(config)?;
+
+        Ok(Self::with_engine(
+            Box::new(engine),
+            model_attrs,
+            config.clone(),
+        ))
+    }
+
+    // Loads the model to embed only, it gets no generation context
+    pub fn init_embedder(config: &Config) -> AppResult<Self> {
+        let model_attrs = Self::model_attrs(config)?;
+        let engine = LlamaEngine::load_for_embeddings(config)?;
+
+        Ok(Self::with_engine(
+            Box::new(engine),
+            model_attrs,
+            config.clone(),
+        ))
+    }
+
+    fn model_attrs(config: &Config) -> AppResult<Model> {
+        config
+            .get_available_models()
+            .get(&config.default_model)
+            .cloned()
+            .ok_or_else(|| {
+                AppError::NotFound(format!("Model {} not available", config.default_model))
             })
-            .collect();
-
-        let template = self
-            .model
-            .chat_template(None)
-            .map_err(|e| format!("Failed to get chat template: {:?}", e))?;
-
-        let mut tokens: Vec<llama_cpp_2::token::LlamaToken>;
-        let reserve_for_output = self.config.max_output_length as usize;
-
-        // 2. Sliding Window: Remove oldest messages until the prompt fits
-        // We loop, checking if (System + Body + New Output) <= Context Limit
-        loop {
-            let mut current_chat = vec![system_msg.clone()];
-            current_chat.extend(body_messages.clone());
-
-            let chat_str = self
-                .model
-                .apply_chat_template(&template, &current_chat, true)
-                .map_err(|e| format!("Template error: {:?}", e))?;
-
-            tokens = self
-                .model
-                .str_to_token(&chat_str, llama_cpp_2::model::AddBos::Never)
-                .map_err(|e| format!("Tokenization error: {:?}", e))?;
-
-            // Check if we are within bounds
-            if tokens.len() + reserve_for_output <= self.config.max_context_length as usize {
-                break;
-            }
+    }
 
-            // If too long and we have messages to remove, remove the oldest one (index 0)
-            if !body_messages.is_empty() {
-                body_messages.remove(0);
-            } else {
-                // If even the system prompt alone is too long, we must truncate the tokens directly
-                tokens.truncate(self.config.max_context_length as usize - reserve_for_output);
-                break;
-            }
+    pub fn with_engine(engine: Box<dyn TextGenerator>, model_attrs: Model, config: Config) -> Self {
+        Self {
+            engine,
+            model_attrs,
+            config,
         }
+    }
+
+    pub fn rebuild_context(self, config: &Config) -> AppResult<Self> {
+        Ok(Self {
+            engine: self.engine.rebuild_context(config)?,
+            model_attrs: self.model_attrs,
+            config: config.clone(),
+        })
+    }
+
+    pub fn generate_text(
+        &mut self,
+        prompt: ChatPrompt<'_>,
+        sink: &dyn EventSink,
+    ) -> AppResult<(String, GenerationStats)> {
+        let conv = prompt.conversation;
+        self.generate(Prompt::Chat(prompt), None, |piece| {
+            sink.emit(Event::Stream(StreamingContent {
+                id: conv.id.clone(),
+                content: piece.to_string(),
+            }))
+        })
+    }
+
+    // `overrides` replaces the sampling settings for this generation only
+    pub fn generate(
+        &mut self,
+        prompt: Prompt<'_>,
+        overrides: Option<&Config>,
+        mut on_piece: impl FnMut(&str),
+    ) -> AppResult<(String, GenerationStats)> {
+        let config = overrides.unwrap_or(&self.config);
+        self.engine.generate(prompt, config, None, &mut on_piece)
+    }
+
+    // Output is constrained by `grammar` while it is sampled
+    pub fn generate_with_grammar(
+        &mut self,
+        prompt: Prompt<'_>,
+        grammar: &str,
+        mut on_piece: impl FnMut(&str),
+    ) -> AppResult<(String, GenerationStats)> {
+        self.engine
+            .generate(prompt, &self.config, Some(grammar), &mut on_piece)
+    }
 
-        Ok(tokens)
+    pub fn benchmark(&mut self, n_prompt: usize, n_gen: usize) -> AppResult<(usize, f64, f64)> {
+        self.engine.benchmark(n_prompt, n_gen, &self.config)
     }
+
+    pub fn embed(&mut self, texts: &[String]) -> AppResult<Vec<Vec<f32>>> {
+        self.engine.embed(texts, &self.config)
+    }
+
+    pub fn set_adapters(&mut self, adapters: &[LoraAdapter]) -> AppResult<()> {
+        self.engine.set_adapters(adapters)
+    }
+}
+
+pub fn elapsed_ms(since: Instant) -> f64 {
+    since.elapsed().as_secs_f64() * 1000.0
+}
+
+// Scales to unit length so cosine similarity becomes a dot product
+pub fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for v in &mut vector {
+            *v /= norm;
+        }
+    }
+    vector
+}
+
+// Cosine similarity of two unit length vectors
+pub fn similarity(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+// Vectors are stored in SQLite as little endian f32 blobs
+pub fn vector_to_bytes(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
 }
 
-unsafe impl Send for Inference {}
-unsafe impl Sync for Inference {}
+pub fn vector_from_bytes(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_scales_to_unit_length() {
+        let vector = normalize(vec![3.0, 4.0]);
+        assert_eq!(vector, vec![0.6, 0.8]);
+        assert!((similarity(&vector, &vector) - 1.0).abs() < 1e-6);
+
+        let negative = normalize(vec![-2.0, 0.0, 0.0]);
+        assert_eq!(negative, vec![-1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn normalize_leaves_a_zero_vector_alone() {
+        assert_eq!(normalize(vec![0.0; 3]), vec![0.0; 3]);
+        assert!(normalize(Vec::new()).is_empty());
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use rusqlite::params;
+
+use crate::{
+    inference::models::{BenchmarkResult, GenerationStats, ModelStats},
+    infrastructure::{database::Database, error::AppResult},
+};
+
+pub fn add_generation_stats(
+    db: &Database,
+    model_name: &str,
+    stats: &GenerationStats,
+) -> AppResult<()> {
+    let conn = db.get_conn()?;
+    conn.execute(
+        "INSERT INTO inference_stats (model_name, prompt_tokens, prompt_ms, generated_tokens,
+            generation_ms, time_to_first_token_ms, tokens_per_second, created)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, DATETIME('now'))",
+        params![
+            model_name,
+            stats.prompt_tokens,
+            stats.prompt_ms,
+            stats.generated_tokens,
+            stats.generation_ms,
+            stats.time_to_first_token_ms,
+            stats.tokens_per_second
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn get_model_stats(db: &Database, window: u64) -> AppResult<Vec<ModelStats>> {
+    let conn = db.get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT model_name,
+            COUNT(*),
+            IFNULL(AVG(prompt_tokens * 1000.0 / NULLIF(prompt_ms, 0)), 0),
+            AVG(tokens_per_second),
+            AVG(time_to_first_token_ms),
+            AVG(generated_tokens)
+        FROM (
+            SELECT *, ROW_NUMBER() OVER (PARTITION BY model_name ORDER BY id DESC) AS recency
+            FROM inference_stats
+        )
+        WHERE recency <= ?1
+        GROUP BY model_name
+        ORDER BY model_name",
+    )?;
+    let stats = stmt
+        .query_map(params![window], |row| {
+            Ok(ModelStats {
+                model_name: row.get(0)?,
+                samples: row.get(1)?,
+                prompt_tokens_per_second: row.get(2)?,
+                tokens_per_second: row.get(3)?,
+                time_to_first_token_ms: row.get(4)?,
+                generated_tokens: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<ModelStats>, _>>()?;
+    Ok(stats)
+}
+
+pub fn save_benchmark(db: &Database, result: &BenchmarkResult) -> AppResult<()> {
+    let conn = db.get_conn()?;
+    let body_json = serde_json::to_string(result)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    conn.execute(
+        "INSERT INTO model_benchmarks (model_name, body, created) VALUES (?1, ?2, DATETIME('now'))
+            ON CONFLICT(model_name) DO UPDATE SET body = excluded.body, created = excluded.created",
+        params![result.model_name, body_json],
+    )?;
+    Ok(())
+}
+
+pub fn get_benchmarks(db: &Database) -> AppResult<HashMap<String, BenchmarkResult>> {
+    let conn = db.get_conn()?;
+    let mut stmt = conn.prepare("SELECT model_name, body FROM model_benchmarks")?;
+    let rows = stmt.query_map([], |row| {
+        let model_name: String = row.get(0)?;
+        let body_json: String = row.get(1)?;
+        let result: BenchmarkResult = serde_json::from_str(&body_json).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e))
+        })?;
+        Ok((model_name, result))
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
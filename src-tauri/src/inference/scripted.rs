@@ -0,0 +1,208 @@
+use std::collections::VecDeque;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::configuration::models::Config;
+use crate::inference::models::{
+    GenerationStats, LoraAdapter, Prompt, TextGenerator, elapsed_ms, normalize,
+};
+use crate::inference::stop::StopMatcher;
+use crate::infrastructure::error::{AppError, AppResult};
+
+const EMBEDDING_DIMENSIONS: usize = 64;
+
+// One canned answer: the pieces streamed in order, optionally followed by a failure
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedReply {
+    pub pieces: Vec<String>,
+    pub error: Option<AppError>,
+}
+
+impl ScriptedReply {
+    pub fn text(pieces: &[&str]) -> Self {
+        ScriptedReply {
+            pieces: pieces.iter().map(|piece| piece.to_string()).collect(),
+            error: None,
+        }
+    }
+
+    // Streams `pieces` and then fails, like a decode error halfway through a reply
+    pub fn failing(pieces: &[&str], error: AppError) -> Self {
+        ScriptedReply {
+            error: Some(error),
+            ..Self::text(pieces)
+        }
+    }
+}
+
+// Deterministic stand-in for a model, plays back its replies one generation at a time
+#[derive(Debug, Default)]
+pub struct ScriptedEngine {
+    replies: VecDeque<ScriptedReply>,
+    token_delay: Duration,
+    prompts: Arc<Mutex<Vec<String>>>,
+    grammars: Arc<Mutex<Vec<String>>>,
+    adapters: Arc<Mutex<Vec<LoraAdapter>>>,
+}
+
+impl ScriptedEngine {
+    pub fn new(replies: impl IntoIterator<Item = ScriptedReply>) -> Self {
+        ScriptedEngine {
+            replies: replies.into_iter().collect(),
+            ..Self::default()
+        }
+    }
+
+    // Sleeps before every piece to simulate a slow device
+    pub fn with_token_delay(mut self, delay: Duration) -> Self {
+        self.token_delay = delay;
+        self
+    }
+
+    // Prompts as rendered for each generation, still readable once the engine is boxed
+    pub fn prompts(&self) -> Arc<Mutex<Vec<String>>> {
+        self.prompts.clone()
+    }
+
+    // Grammars generations were constrained with, the replies are played back regardless
+    pub fn grammars(&self) -> Arc<Mutex<Vec<String>>> {
+        self.grammars.clone()
+    }
+
+    // Adapters currently set, as the last `set_adapters` left them
+    pub fn adapters(&self) -> Arc<Mutex<Vec<LoraAdapter>>> {
+        self.adapters.clone()
+    }
+
+    fn render(prompt: Prompt<'_>, config: &Config) -> String {
+        match prompt {
+            Prompt::Chat(chat) => {
+                let mut rendered = format!("system: {}\n", chat.system_message(config));
+                for msg in chat.messages() {
+                    rendered.push_str(&format!("{}: {}\n", msg.role, msg.prompt_content()));
+                }
+                rendered
+            }
+            Prompt::Text(text) => text.to_string(),
+        }
+    }
+}
+
+impl TextGenerator for ScriptedEngine {
+    fn generate(
+        &mut self,
+        prompt: Prompt<'_>,
+        config: &Config,
+        grammar: Option<&str>,
+        on_piece: &mut dyn FnMut(&str),
+    ) -> AppResult<(String, GenerationStats)> {
+        let started = Instant::now();
+        let rendered = Self::render(prompt, config);
+        if let Some(grammar) = grammar
+            && let Ok(mut grammars) = self.grammars.lock()
+        {
+            grammars.push(grammar.to_string());
+        }
+        // Whitespace separated words stand in for tokens
+        let mut stats = GenerationStats {
+            prompt_tokens: rendered.split_whitespace().count() as u64,
+            ..GenerationStats::default()
+        };
+        if let Ok(mut prompts) = self.prompts.lock() {
+            prompts.push(rendered);
+        }
+
+        let reply = self
+            .replies
+            .pop_front()
+            .ok_or_else(|| AppError::Internal("Scripted engine has no replies left".into()))?;
+        stats.prompt_ms = elapsed_ms(started);
+        let generation_started = Instant::now();
+
+        let mut message = String::new();
+        let mut stop = StopMatcher::new(&config.stop_sequences);
+        let mut stopped = false;
+        for piece in reply.pieces.iter().take(config.max_output_length as usize) {
+            if !self.token_delay.is_zero() {
+                thread::sleep(self.token_delay);
+            }
+            if stats.generated_tokens == 0 {
+                stats.time_to_first_token_ms = elapsed_ms(started);
+            }
+            stats.generated_tokens += 1;
+            let (settled, reached) = stop.push(piece);
+            stopped = reached;
+            if !settled.is_empty() {
+                message.push_str(&settled);
+                on_piece(&settled);
+            }
+            if stopped {
+                break;
+            }
+        }
+
+        // A stop string ends the reply before a scripted failure is reached
+        if !stopped && let Some(error) = reply.error {
+            return Err(error);
+        }
+        let rest = stop.finish();
+        if !rest.is_empty() {
+            message.push_str(&rest);
+            on_piece(&rest);
+        }
+
+        stats.generation_ms = elapsed_ms(generation_started);
+        if stats.generation_ms > 0.0 {
+            stats.tokens_per_second = stats.generated_tokens as f64 * 1000.0 / stats.generation_ms;
+        }
+        Ok((message, stats))
+    }
+
+    fn rebuild_context(self: Box<Self>, _config: &Config) -> AppResult<Box<dyn TextGenerator>> {
+        Ok(self)
+    }
+
+    fn benchmark(
+        &mut self,
+        n_prompt: usize,
+        n_gen: usize,
+        _config: &Config,
+    ) -> AppResult<(usize, f64, f64)> {
+        let per_token_ms = self.token_delay.as_secs_f64() * 1000.0;
+        Ok((n_prompt, 0.0, per_token_ms * n_gen as f64))
+    }
+
+    // Bag of hashed words, texts sharing words end up close to each other
+    fn embed(&mut self, texts: &[String], _config: &Config) -> AppResult<Vec<Vec<f32>>> {
+        texts
+            .iter()
+            .map(|text| {
+                let mut vector = vec![0.0; EMBEDDING_DIMENSIONS];
+                for word in text.split_whitespace() {
+                    let word = word
+                        .trim_matches(|c: char| !c.is_alphanumeric())
+                        .to_lowercase();
+                    if word.is_empty() {
+                        continue;
+                    }
+                    let mut hasher = DefaultHasher::new();
+                    word.hash(&mut hasher);
+                    vector[hasher.finish() as usize % EMBEDDING_DIMENSIONS] += 1.0;
+                }
+                if vector.iter().all(|v| *v == 0.0) {
+                    return Err(AppError::Validation("Cannot embed an empty text".into()));
+                }
+                Ok(normalize(vector))
+            })
+            .collect()
+    }
+
+    fn set_adapters(&mut self, adapters: &[LoraAdapter]) -> AppResult<()> {
+        if let Ok(mut applied) = self.adapters.lock() {
+            *applied = adapters.to_vec();
+        }
+        Ok(())
+    }
+}
@@ -1,42 +1,375 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+    time::Instant,
+};
+
+use serde_json::Value;
+
 use crate::{
-    configuration::models::Config,
-    inference::models::Inference,
-    infrastructure::{self, context::Context, path_resolver},
+    configuration::{
+        self,
+        models::{Config, ConfigChange},
+    },
+    conversation::models::Conversation,
+    inference::{
+        grammar,
+        models::{BenchmarkResult, ChatPrompt, GenerationStats, Inference, ModelStats, Prompt},
+        repository as dao,
+        template::ChatTemplate,
+    },
+    infrastructure::{
+        self, consts,
+        context::Context,
+        database::Database,
+        device,
+        error::{AppError, AppResult},
+        path_resolver,
+    },
 };
 
-fn validate_model(config: &Config, model_name: &str) -> Result<(), String> {
+pub fn validate_model(config: &Config, model_name: &str) -> AppResult<()> {
     if !config.get_available_models().contains_key(model_name) {
-        return Err("Model not available".into());
+        return Err(AppError::NotFound(format!(
+            "Model {} not available",
+            model_name
+        )));
     }
 
-    let path = path_resolver::paths().app_local_data(model_name).unwrap();
+    let path = path_resolver::paths()?
+        .app_local_data(model_name)
+        .map_err(AppError::Internal)?;
 
     if !path.exists() {
-        return Err("Model not downloaded".into());
+        return Err(AppError::ModelNotDownloaded(model_name.to_string()));
     }
 
-
     Ok(())
 }
 
-pub fn activate_model(model_name: String, ctx: &mut Context) -> Result<(), String> {
+pub fn activate_model(model_name: String, ctx: &mut Context) -> AppResult<()> {
+    set_default_model(model_name, ctx)?;
+
+    initialize_inference(ctx)
+}
+
+// Persists the default model without loading it
+pub fn set_default_model(model_name: String, ctx: &mut Context) -> AppResult<()> {
     validate_model(&ctx.config, &model_name)?;
     // persist
-    infrastructure::service::set_config("model_name".into(), model_name.clone())
-        .map_err(|e| e.to_string())?;
+    infrastructure::service::set_config(&ctx.db, "model_name".into(), model_name.clone())?;
+
+    ctx.config.default_model = model_name;
+
+    Ok(())
+}
+
+// Loads the model saved as default, if any
+pub fn activate_saved_model(ctx: &mut Context) {
+    let saved_model =
+        infrastructure::service::get_config(&ctx.db, "model_name".to_string()).unwrap_or_default();
+
+    if !saved_model.is_empty()
+        && let Err(e) = activate_model(saved_model, ctx)
+    {
+        eprintln!("Failed to activate saved model: {}", e);
+    }
+}
 
-    ctx.config.default_model = model_name.clone();
+// Loads `model_name` for a single request, the saved default and the settings stay as they are
+pub fn load_model(model_name: &str, ctx: &mut Context) -> AppResult<()> {
+    validate_model(&ctx.config, model_name)?;
+    let mut config = ctx.config.clone();
+    config.default_model = model_name.to_string();
+    ctx.inference = None;
+    ctx.inference = Some(Inference::init(&mut config)?);
+    Ok(())
+}
 
-    initialize_inference(ctx);
+// Switches back to the default model after a request ran on another one
+pub fn ensure_default_model(ctx: &mut Context) -> AppResult<()> {
+    let loaded = ctx
+        .inference
+        .as_ref()
+        .map(|inference| inference.config.default_model.as_str());
+    if ctx.config.default_model.is_empty() || loaded == Some(ctx.config.default_model.as_str()) {
+        return Ok(());
+    }
+    initialize_inference(ctx)
+}
 
+fn initialize_inference(ctx: &mut Context) -> AppResult<()> {
+    // Release the previous model first so its memory counts as available
+    ctx.inference = None;
+    ctx.inference = Some(Inference::init(&mut ctx.config)?);
     Ok(())
 }
 
-fn initialize_inference(ctx: &mut Context) {
-    match Inference::init(&ctx.config) {
-        Ok(inference) => {
+pub fn apply_config(change: ConfigChange, ctx: &mut Context) -> AppResult<ConfigChange> {
+    if change == ConfigChange::None || ctx.config.default_model.is_empty() {
+        return Ok(ConfigChange::None);
+    }
+
+    let Some(mut inference) = ctx.inference.take() else {
+        reload_model(ctx, None)?;
+        return Ok(ConfigChange::ModelReload);
+    };
+    // Nothing to adjust in place when the engine runs another model
+    if inference.config.default_model != ctx.config.default_model {
+        let previous = inference.config.clone();
+        drop(inference);
+        reload_model(ctx, Some(previous))?;
+        return Ok(ConfigChange::ModelReload);
+    }
+
+    match change {
+        ConfigChange::Sampler => {
+            inference.config = ctx.config.clone();
             ctx.inference = Some(inference);
         }
-        Err(e) => eprintln!("Inference init failed: {}", e),
+        ConfigChange::ContextRebuild => {
+            // The rebuild consumes the engine, on failure the model is reloaded as it ran before
+            let mut previous = inference.config.clone();
+            match inference.rebuild_context(&ctx.config) {
+                Ok(rebuilt) => ctx.inference = Some(rebuilt),
+                Err(e) => {
+                    ctx.inference = Inference::init(&mut previous).ok();
+                    return Err(e);
+                }
+            }
+        }
+        ConfigChange::ModelReload | ConfigChange::None => {
+            let previous = inference.config.clone();
+            drop(inference);
+            reload_model(ctx, Some(previous))?;
+        }
+    }
+    Ok(change)
+}
+
+// Loads the default model with the new settings, on failure the model that ran before is
+// loaded again as it was so the app is not left without one
+fn reload_model(ctx: &mut Context, previous: Option<Config>) -> AppResult<()> {
+    let result = activate_model(ctx.config.default_model.clone(), ctx);
+    if result.is_err()
+        && let Some(mut previous) = previous
+    {
+        ctx.inference = Inference::init(&mut previous).ok();
+    }
+    result
+}
+
+pub fn embed(texts: &[String], ctx: &mut Context) -> AppResult<Vec<Vec<f32>>> {
+    if texts.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !embeds_with_chat_model(&ctx.config) {
+        return embed_separately(&ctx.embedder, &ctx.config, texts);
+    }
+
+    // A dedicated embedder left from a previous setting is no longer needed
+    *lock_embedder(&ctx.embedder) = None;
+    ensure_default_model(ctx)?;
+    ctx.inference
+        .as_mut()
+        .ok_or_else(|| AppError::ModelLoadFailed("No model is loaded".into()))?
+        .embed(texts)
+}
+
+// Name stored next to vectors, those from another model are not comparable
+pub fn embedding_model(config: &Config) -> &str {
+    if config.embedding_model.is_empty() {
+        &config.default_model
+    } else {
+        &config.embedding_model
+    }
+}
+
+pub fn embeds_with_chat_model(config: &Config) -> bool {
+    embedding_model(config) == config.default_model
+}
+
+// Embeds with the dedicated embedding model, loading it first when the setting changed.
+// Only the embedder is locked, so callers need not hold the context.
+pub fn embed_separately(
+    embedder: &Mutex<Option<Inference>>,
+    config: &Config,
+    texts: &[String],
+) -> AppResult<Vec<Vec<f32>>> {
+    let name = embedding_model(config);
+    let mut embedder = lock_embedder(embedder);
+    if embedder
+        .as_ref()
+        .is_none_or(|embedder| embedder.config.default_model != name)
+    {
+        validate_model(config, name)?;
+        *embedder = None;
+        let mut config = config.clone();
+        config.default_model = name.to_string();
+        *embedder = Some(Inference::init_embedder(&config)?);
+    }
+    embedder
+        .as_mut()
+        .ok_or_else(|| AppError::Internal("Embedding model was not loaded".into()))?
+        .embed(texts)
+}
+
+// An embedding that panicked leaves nothing inconsistent behind, so poisoning is ignored
+pub fn lock_embedder(embedder: &Mutex<Option<Inference>>) -> MutexGuard<'_, Option<Inference>> {
+    embedder.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Saves the template `model_name` is prompted with, `None` goes back to the GGUF's own
+pub fn set_chat_template(
+    model_name: &str,
+    template: Option<ChatTemplate>,
+    ctx: &mut Context,
+) -> AppResult<ConfigChange> {
+    // An override can still be removed once its model is gone
+    if template.is_some() {
+        validate_model(&ctx.config, model_name)?;
+    }
+    let previous = ctx.config.clone();
+    match template {
+        Some(template) => ctx
+            .config
+            .chat_templates
+            .insert(model_name.to_string(), template),
+        None => ctx.config.chat_templates.remove(model_name),
+    };
+
+    let change = match apply_config(ctx.config.change_from(&previous), ctx) {
+        Ok(change) => change,
+        Err(e) => {
+            ctx.config = previous;
+            return Err(e);
+        }
+    };
+
+    // Saved once applied, so a template the model cannot run with is not kept
+    let raw = serde_json::to_string(&ctx.config.chat_templates)
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    configuration::repository::set_model_config(&ctx.db, "chat_templates".into(), raw)?;
+    Ok(change)
+}
+
+// Answers `prompt` as a single user turn, sampling only what `gbnf` accepts
+pub fn generate_constrained(prompt: &str, gbnf: &str, ctx: &mut Context) -> AppResult<String> {
+    let (reply, _) = run_constrained(prompt, gbnf, ctx)?;
+    Ok(reply)
+}
+
+// JSON valid against `schema`, enforced by a grammar during sampling
+pub fn generate_structured(prompt: &str, schema: &Value, ctx: &mut Context) -> AppResult<Value> {
+    let gbnf = grammar::from_json_schema(schema)?;
+    let (reply, truncated) = run_constrained(prompt, &gbnf, ctx)?;
+    serde_json::from_str(&reply).map_err(|e| {
+        // The grammar cannot close a document cut off by the output limit
+        if truncated {
+            AppError::OutputTruncated(
+                "Structured reply was cut off, raise the output length".into(),
+            )
+        } else {
+            AppError::Internal(format!("Structured reply is not valid JSON: {}", e))
+        }
+    })
+}
+
+// The reply and whether it stopped at the output length limit
+fn run_constrained(prompt: &str, gbnf: &str, ctx: &mut Context) -> AppResult<(String, bool)> {
+    ensure_default_model(ctx)?;
+    let inference = ctx
+        .inference
+        .as_mut()
+        .ok_or_else(|| AppError::ModelLoadFailed("No model is loaded".into()))?;
+    let mut conversation = Conversation::new(String::new(), String::new());
+    conversation.add_message("user", prompt);
+    inference.set_adapters(&[])?;
+
+    let (reply, stats) = inference.generate_with_grammar(
+        Prompt::Chat(ChatPrompt::new(&conversation)),
+        gbnf,
+        |_| {},
+    )?;
+    record_generation(&ctx.db, &inference.config.default_model, &stats);
+    let truncated = stats.generated_tokens >= inference.config.max_output_length;
+    Ok((reply, truncated))
+}
+
+pub fn record_generation(db: &Database, model_name: &str, stats: &GenerationStats) {
+    if let Err(e) = dao::add_generation_stats(db, model_name, stats) {
+        eprintln!("Failed to record generation stats: {}", e);
+    }
+}
+
+pub fn get_inference_stats(db: &Database) -> AppResult<Vec<ModelStats>> {
+    dao::get_model_stats(db, consts::STATS_WINDOW)
+}
+
+// Temporarily swaps the active model out so the benchmark has the device to itself
+pub fn benchmark_model(model_name: String, ctx: &mut Context) -> AppResult<BenchmarkResult> {
+    validate_model(&ctx.config, &model_name)?;
+
+    let loaded = ctx
+        .inference
+        .take()
+        .map(|inference| inference.config.default_model.clone());
+    let result = run_benchmark(&model_name, &ctx.config);
+
+    // Put back whatever the outcome, a failed run must not leave the app without its model
+    let restored = match loaded {
+        Some(loaded) if loaded != ctx.config.default_model => load_model(&loaded, ctx),
+        Some(_) => initialize_inference(ctx),
+        None => Ok(()),
+    };
+    if let (Err(_), Err(reload)) = (&result, &restored) {
+        eprintln!("Failed to reload model after benchmark: {}", reload);
     }
+    let result = result?;
+    restored?;
+
+    dao::save_benchmark(&ctx.db, &result)?;
+    Ok(result)
+}
+
+fn run_benchmark(model_name: &str, config: &Config) -> AppResult<BenchmarkResult> {
+    let mut config = config.clone();
+    config.default_model = model_name.to_string();
+
+    // Resident size is sampled throughout, it counts mapped weights once they are read
+    let resident_before = device::resident_bytes().unwrap_or(0);
+    let (measured, peak_resident) = device::peak_resident_bytes(|| -> AppResult<_> {
+        let started = Instant::now();
+        let mut inference = Inference::init(&mut config)?;
+        let load_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        let (prompt_tokens, prompt_ms, generation_ms) = inference.benchmark(
+            consts::BENCHMARK_PROMPT_TOKENS,
+            consts::BENCHMARK_GENERATED_TOKENS,
+        )?;
+        Ok((load_ms, prompt_tokens, prompt_ms, generation_ms))
+    });
+    let (load_ms, prompt_tokens, prompt_ms, generation_ms) = measured?;
+
+    let per_second = |tokens: usize, ms: f64| {
+        if ms > 0.0 {
+            tokens as f64 * 1000.0 / ms
+        } else {
+            0.0
+        }
+    };
+
+    Ok(BenchmarkResult {
+        model_name: model_name.to_string(),
+        load_ms,
+        prompt_tokens: prompt_tokens as u64,
+        prompt_tokens_per_second: per_second(prompt_tokens, prompt_ms),
+        generated_tokens: consts::BENCHMARK_GENERATED_TOKENS as u64,
+        tokens_per_second: per_second(consts::BENCHMARK_GENERATED_TOKENS, generation_ms),
+        peak_memory_bytes: peak_resident.saturating_sub(resident_before),
+    })
+}
+
+pub fn get_benchmarks(db: &Database) -> AppResult<HashMap<String, BenchmarkResult>> {
+    dao::get_benchmarks(db)
 }
@@ -0,0 +1,66 @@
+// Cuts a streamed reply at the first stop string. Text that could still turn into one is held
+// back until the next pieces settle it, so a stop string never reaches the stream.
+pub struct StopMatcher {
+    stops: Vec<String>,
+    pending: String,
+}
+
+impl StopMatcher {
+    pub fn new(stops: &[String]) -> Self {
+        StopMatcher {
+            stops: stops
+                .iter()
+                .filter(|stop| !stop.is_empty())
+                .cloned()
+                .collect(),
+            pending: String::new(),
+        }
+    }
+
+    // Text safe to emit after `piece`, and whether a stop string was reached
+    pub fn push(&mut self, piece: &str) -> (String, bool) {
+        self.pending.push_str(piece);
+
+        if let Some(end) = self
+            .stops
+            .iter()
+            .filter_map(|stop| self.pending.find(stop.as_str()))
+            .min()
+        {
+            let emit = self.pending[..end].to_string();
+            self.pending.clear();
+            return (emit, true);
+        }
+
+        let held = self.partial_match_start();
+        let emit = self.pending[..held].to_string();
+        self.pending.drain(..held);
+        (emit, false)
+    }
+
+    // Text still held back once generation ended without a stop string
+    pub fn finish(self) -> String {
+        self.pending
+    }
+
+    // Start of the longest suffix of `pending` that begins some stop string
+    fn partial_match_start(&self) -> usize {
+        self.pending
+            .char_indices()
+            .map(|(i, _)| i)
+            .find(|&i| {
+                let suffix = &self.pending[i..];
+                self.stops.iter().any(|stop| stop.starts_with(suffix))
+            })
+            .unwrap_or(self.pending.len())
+    }
+}
+
+// Start of the longest suffix of `text` that begins `pattern`, the part to hold back while
+// the next pieces could still complete it
+pub fn partial_match_start(text: &str, pattern: &str) -> usize {
+    text.char_indices()
+        .map(|(i, _)| i)
+        .find(|&i| pattern.starts_with(&text[i..]))
+        .unwrap_or(text.len())
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+
+// Prompt formats built into the app, for models whose GGUF has no usable chat template
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatTemplate {
+    ChatMl,
+    Llama3,
+    Gemma,
+    Mistral,
+    Phi,
+    DeepSeek,
+}
+
+impl ChatTemplate {
+    pub const ALL: [ChatTemplate; 6] = [
+        ChatTemplate::ChatMl,
+        ChatTemplate::Llama3,
+        ChatTemplate::Gemma,
+        ChatTemplate::Mistral,
+        ChatTemplate::Phi,
+        ChatTemplate::DeepSeek,
+    ];
+
+    // Best match for a model file name, ChatML being the most widely understood format
+    pub fn guess(model_name: &str) -> ChatTemplate {
+        let name = model_name.to_lowercase();
+        // Checked first, DeepSeek distills carry the name of the model they were based on
+        if name.contains("deepseek") {
+            ChatTemplate::DeepSeek
+        } else if name.contains("llama-3") || name.contains("llama3") {
+            ChatTemplate::Llama3
+        } else if name.contains("gemma") {
+            ChatTemplate::Gemma
+        } else if name.contains("mistral") || name.contains("mixtral") {
+            ChatTemplate::Mistral
+        } else if name
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .any(|part| part.starts_with("phi"))
+        {
+            ChatTemplate::Phi
+        } else {
+            ChatTemplate::ChatMl
+        }
+    }
+
+    // Gemma and Mistral instruct models were never trained with a system turn
+    pub fn has_system_role(self) -> bool {
+        !matches!(self, ChatTemplate::Gemma | ChatTemplate::Mistral)
+    }
+
+    // `messages` are (role, content) pairs with roles system, user or assistant, the
+    // rendered prompt ends with the opening of the assistant's turn
+    pub fn render(self, messages: &[(String, String)]) -> String {
+        let folded;
+        let messages = if self.has_system_role() {
+            messages
+        } else {
+            folded = fold_system(messages);
+            &folded
+        };
+
+        let mut prompt = String::from(match self {
+            ChatTemplate::Llama3 => "<|begin_of_text|>",
+            ChatTemplate::Gemma => "<bos>",
+            ChatTemplate::Mistral => "<s>",
+            ChatTemplate::DeepSeek => "<｜begin▁of▁sentence｜>",
+            ChatTemplate::ChatMl | ChatTemplate::Phi => "",
+        });
+        for (role, content) in messages {
+            let role = role.as_str();
+            match self {
+                ChatTemplate::ChatMl => {
+                    prompt.push_str(&format!("<|im_start|>{}\n{}<|im_end|>\n", role, content))
+                }
+                ChatTemplate::Llama3 => prompt.push_str(&format!(
+                    "<|start_header_id|>{}<|end_header_id|>\n\n{}<|eot_id|>",
+                    role, content
+                )),
+                ChatTemplate::Gemma => {
+                    let role = if role == "assistant" { "model" } else { role };
+                    prompt.push_str(&format!(
+                        "<start_of_turn>{}\n{}<end_of_turn>\n",
+                        role, content
+                    ))
+                }
+                ChatTemplate::Mistral => match role {
+                    "assistant" => prompt.push_str(&format!(" {}</s>", content)),
+                    _ => prompt.push_str(&format!("[INST] {} [/INST]", content)),
+                },
+                ChatTemplate::Phi => {
+                    prompt.push_str(&format!("<|{}|>\n{}<|end|>\n", role, content))
+                }
+                ChatTemplate::DeepSeek => match role {
+                    "system" => prompt.push_str(content),
+                    "assistant" => {
+                        prompt.push_str(&format!("<｜Assistant｜>{}<｜end▁of▁sentence｜>", content))
+                    }
+                    _ => prompt.push_str(&format!("<｜User｜>{}", content)),
+                },
+            }
+        }
+
+        prompt.push_str(match self {
+            ChatTemplate::ChatMl => "<|im_start|>assistant\n",
+            ChatTemplate::Llama3 => "<|start_header_id|>assistant<|end_header_id|>\n\n",
+            ChatTemplate::Gemma => "<start_of_turn>model\n",
+            ChatTemplate::Mistral => "",
+            ChatTemplate::Phi => "<|assistant|>\n",
+            ChatTemplate::DeepSeek => "<｜Assistant｜>",
+        });
+        prompt
+    }
+}
+
+// Moves system messages to the start of the first user turn
+fn fold_system(messages: &[(String, String)]) -> Vec<(String, String)> {
+    let system: Vec<&str> = messages
+        .iter()
+        .filter(|(role, content)| role == "system" && !content.is_empty())
+        .map(|(_, content)| content.as_str())
+        .collect();
+    let mut folded: Vec<(String, String)> = messages
+        .iter()
+        .filter(|(role, _)| role != "system")
+        .cloned()
+        .collect();
+    if system.is_empty() {
+        return folded;
+    }
+
+    let system = system.join("\n\n");
+    match folded.iter_mut().find(|(role, _)| role == "user") {
+        Some((_, content)) => *content = format!("{}\n\n{}", system, content),
+        None => folded.insert(0, ("user".to_string(), system)),
+    }
+    folded
+}
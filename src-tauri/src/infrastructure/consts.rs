@@ -1,8 +1,17 @@
-use crate::{infrastructure::path_resolver, models::models::Model};
-use std::{collections::HashMap, sync::OnceLock};
+use crate::{
+    infrastructure::{
+        error::{AppError, AppResult},
+        path_resolver,
+    },
+    models::models::Model,
+};
+use std::{collections::HashMap, path::PathBuf, sync::OnceLock, time::Duration};
 
 pub static DB_NAME: &str = "data_store.sqlite";
 
+// Must match `identifier` in tauri.conf.json
+pub static APP_IDENTIFIER: &str = "com.breve.ai";
+
 pub static DEFAULT_SYSTEM_PROMPT: &str = "You are a friendly AI assistant named Breve.
 You are designed to respond to user queries in a friendly and empathetic manner.
 Answer without making up facts or hallucinating.";
@@ -11,7 +20,63 @@ pub const DEFAULT_GLOBAL_MEM_BYTES: u64 = 4 * 1024 * 1024 * 1024;
 pub const DEFAULT_MODEL_SIZE_BYTES: u64 = 1024 * 1024 * 1024;
 pub const DEFAULT_BYTES_PER_TOKEN: u64 = 8 * 1024;
 pub const DEFAULT_MAX_OUTPUT_LENGTH: u64 = 4096;
+pub const DEFAULT_N_CTX_TRAIN: u64 = 32768;
+pub const MIN_BATCH_SIZE: u64 = 4096;
+pub const MAX_BATCH_SIZE: u64 = 32768;
 pub const DEFAULT_TEMPERATURE: f32 = 0.6;
+pub const DEFAULT_USE_MMAP: bool = true;
+pub const DEFAULT_USE_MLOCK: bool = false;
+// More layers than any catalog model has, llama.cpp caps it at the model's layer count
+pub const DEFAULT_N_GPU_LAYERS: u32 = 999;
+pub const DEFAULT_FLASH_ATTENTION: bool = false;
+// Number of recent generations averaged per model by `get_inference_stats`
+pub const STATS_WINDOW: u64 = 20;
+// Workload sizes of llama-bench's default pp512 and tg128 tests
+pub const BENCHMARK_PROMPT_TOKENS: usize = 512;
+pub const BENCHMARK_GENERATED_TOKENS: usize = 128;
+// How often memory is sampled while a benchmark runs
+pub const MEMORY_SAMPLE_INTERVAL: Duration = Duration::from_millis(20);
+// Embedding inputs are short chunks and questions, a small context keeps the extra KV cache cheap
+pub const EMBEDDING_CONTEXT_TOKENS: u64 = 2048;
+// Knowledge base chunks, sized to stay well under the embedding context
+pub const KB_CHUNK_CHARS: usize = 1200;
+pub const KB_CHUNK_OVERLAP_CHARS: usize = 200;
+pub const KB_EMBED_BATCH: usize = 16;
+pub const KB_TOP_K: usize = 4;
+// Passages take at most one part in this many of the context window
+pub const KB_CONTEXT_SHARE: usize = 4;
+// Passages less similar than this to the question are left out of the prompt
+pub const KB_MIN_SCORE: f32 = 0.3;
+pub static KB_SOURCES_INSTRUCTION: &str = "Answer from the numbered sources below when they are relevant \
+and cite every source you use with its marker, like [1]. If they do not contain the answer, say so.";
+// Settings key turning long-term memory on, off unless set to "true"
+pub static MEMORY_ENABLED_KEY: &str = "memory_enabled";
+pub const MEMORY_TOKEN_BUDGET: usize = 256;
+pub static MEMORY_INSTRUCTION: &str = "When the user shares a lasting fact about themselves, \
+such as their work, tools or preferences, repeat it on its own line as <remember>fact</remember>.";
+pub static MEMORY_FACTS_HEADER: &str = "What you know about the user:";
+pub const SEARCH_DEFAULT_LIMIT: usize = 20;
+// Settings key letting the model call local tools, off unless set to "true"
+pub static TOOLS_ENABLED_KEY: &str = "tools_enabled";
+// Tool calls answered before the model must reply in plain text
+pub const MAX_TOOL_ROUNDS: usize = 4;
+pub const TOOL_SEARCH_LIMIT: usize = 5;
+pub const CALCULATOR_MAX_DEPTH: usize = 64;
+pub static TOOLS_INSTRUCTION: &str = "You may call the tools described inside <tools></tools> \
+when they help. To call one, reply with nothing but <tool_call>{\"name\": <tool name>, \
+\"arguments\": <arguments object>}</tool_call>. Its result comes back inside <tool_response></tool_response>.";
+// Attachments are cut by an estimate, the prompt is fitted exactly once tokenized
+pub const CHARS_PER_TOKEN: usize = 4;
+pub const ATTACHMENT_MAX_TOKENS: usize = 4096;
+// Originals are stored in the database, larger files are refused
+pub const ATTACHMENT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+pub const BINARY_SNIFF_BYTES: usize = 8000;
+// Imported LoRA adapters live here inside the app's data directory
+pub static ADAPTERS_DIR: &str = "adapters";
+pub const MAX_ADAPTER_SCALE: f32 = 4.0;
+pub static API_SERVER_CONFIG_KEY: &str = "api_server";
+pub static DEFAULT_API_HOST: &str = "127.0.0.1";
+pub const DEFAULT_API_PORT: u16 = 8080;
 
 static DEFAULT_MODELS: OnceLock<HashMap<String, Model>> = OnceLock::new();
 
@@ -27,6 +92,7 @@ pub fn default_models() -> &'static HashMap<String, Model> {
                     is_thinking: false,
                     params: "1B".to_string(),
                     is_premium: false,
+                    quant: "Q4_K_M".to_string(),
                 },
             ),
             (
@@ -38,6 +104,7 @@ pub fn default_models() -> &'static HashMap<String, Model> {
                     is_thinking: false,
                     params: "1B".to_string(),
                     is_premium: false,
+                    quant: "Q4_K_M".to_string(),
                 },
             ),
             (
@@ -49,6 +116,7 @@ pub fn default_models() -> &'static HashMap<String, Model> {
                     is_thinking: false,
                     params: "3B".to_string(),
                     is_premium: true,
+                    quant: "Q4_K_M".to_string(),
                 },
             ),
             (
@@ -60,6 +128,7 @@ pub fn default_models() -> &'static HashMap<String, Model> {
                     is_thinking: false,
                     params: "360M".to_string(),
                     is_premium: false,
+                    quant: "Q4_K_M".to_string(),
                 },
             ),
             (
@@ -71,6 +140,7 @@ pub fn default_models() -> &'static HashMap<String, Model> {
                     is_thinking: true,
                     params: "3B".to_string(),
                     is_premium: true,
+                    quant: "Q4_K_M".to_string(),
                 },
             ),
             (
@@ -82,6 +152,7 @@ pub fn default_models() -> &'static HashMap<String, Model> {
                     is_thinking: false,
                     params: "1.7B".to_string(),
                     is_premium: false,
+                    quant: "Q4_K_M".to_string(),
                 },
             ),
             (
@@ -93,6 +164,7 @@ pub fn default_models() -> &'static HashMap<String, Model> {
                     is_thinking: false,
                     params: "1.5B".to_string(),
                     is_premium: false,
+                    quant: "Q4_K_M".to_string(),
                 },
             ),
             (
@@ -104,6 +176,7 @@ pub fn default_models() -> &'static HashMap<String, Model> {
                     is_thinking: false,
                     params: "1.7B".to_string(),
                     is_premium: false,
+                    quant: "Q4_K_M".to_string(),
                 },
             ),
             (
@@ -115,17 +188,15 @@ pub fn default_models() -> &'static HashMap<String, Model> {
                     is_thinking: true,
                     params: "1.5B".to_string(),
                     is_premium: false,
+                    quant: "Q4_K_M".to_string(),
                 },
             ),
         ])
     })
 }
 
-pub fn get_db_path() -> String {
-    path_resolver::paths()
+pub fn get_db_path() -> AppResult<PathBuf> {
+    path_resolver::paths()?
         .app_local_data(DB_NAME)
-        .unwrap()
-        .to_str()
-        .unwrap()
-        .to_string()
+        .map_err(AppError::Database)
 }
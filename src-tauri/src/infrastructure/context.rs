@@ -1,17 +1,33 @@
-use crate::{configuration::models::Config, inference::models::Inference};
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    configuration::models::Config,
+    inference::models::Inference,
+    infrastructure::{database::Database, error::AppResult},
+    tools::registry::ToolRegistry,
+};
 
 pub struct Context {
+    pub db: Database,
     pub config: Config,
     pub inference: Option<Inference>,
+    // Dedicated embedding model, loaded on first use. Locked on its own so indexing can
+    // embed without holding the context
+    pub embedder: Arc<Mutex<Option<Inference>>>,
+    // Shared so a running tool can borrow the context mutably
+    pub tools: Arc<ToolRegistry>,
 }
 
 impl Context {
-    pub fn init() -> Result<Context, String> {
-        let config = Config::init();
+    pub fn init(db: Database) -> AppResult<Context> {
+        let config = Config::init(&db);
 
         Ok(Context {
+            db,
             config,
             inference: None,
+            embedder: Arc::new(Mutex::new(None)),
+            tools: Arc::new(ToolRegistry::builtin()),
         })
     }
 }
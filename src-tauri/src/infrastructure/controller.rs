@@ -1,11 +1,23 @@
-use crate::infrastructure::service;
+use tauri::State;
+
+use crate::infrastructure::{
+    database::Database,
+    device::{self, DeviceMemory},
+    error::AppResult,
+    service,
+};
+
+#[tauri::command]
+pub async fn get_config(key: String, db: State<'_, Database>) -> AppResult<String> {
+    service::get_config(&db, key)
+}
 
 #[tauri::command]
-pub async fn get_config(key: String) -> Result<String, String> {
-    service::get_config(key).map_err(|e| e.to_string())
+pub async fn set_config(key: String, value: String, db: State<'_, Database>) -> AppResult<()> {
+    service::set_config(&db, key, value)
 }
 
 #[tauri::command]
-pub async fn set_config(key: String, value: String) -> Result<(), String> {
-    service::set_config(key, value).map_err(|e| e.to_string())
+pub async fn get_device_memory() -> AppResult<DeviceMemory> {
+    Ok(device::memory())
 }
@@ -1,36 +1,67 @@
-use std::sync::OnceLock;
+use std::path::Path;
 
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::Result;
+use uuid::Uuid;
 
-use crate::infrastructure::consts;
+use crate::infrastructure::error::{AppError, AppResult};
 
 type Pool = r2d2::Pool<SqliteConnectionManager>;
 
-#[derive(Debug)]
+pub const IN_MEMORY: &str = ":memory:";
+
+// Cheap to clone, every clone shares the same connection pool
+#[derive(Debug, Clone)]
 pub struct Database {
     pub pool: Pool,
 }
 
-static DB_INSTANCE: OnceLock<Database> = OnceLock::new();
-
 impl Database {
-    pub fn get_db() -> &'static Database {
-        DB_INSTANCE.get_or_init(|| {
-            let manager = SqliteConnectionManager::file(consts::get_db_path());
-            let pool = r2d2::Pool::new(manager).expect("Failed to create pool");
-            let db = Database { pool };
+    // Opens the database file at `path`, or a private in-memory one for `:memory:`
+    pub fn open<P: AsRef<Path>>(path: P) -> AppResult<Database> {
+        if path.as_ref() == Path::new(IN_MEMORY) {
+            return Self::in_memory();
+        }
+        let pool = r2d2::Pool::builder().build(SqliteConnectionManager::file(path))?;
+        Self::with_pool(pool)
+    }
 
-            _ = db.init_conversation_dao();
-            _ = db.init_settings_dao();
-            _ = db.init_model_config_dao();
+    pub fn in_memory() -> AppResult<Database> {
+        // Each plain `:memory:` connection is its own database, a named shared cache lets the
+        // pooled connections see the same data. It lives as long as one of them stays open.
+        let uri = format!(
+            "file:breve-{}?mode=memory&cache=shared",
+            Uuid::new_v4().simple()
+        );
+        let pool = r2d2::Pool::builder()
+            .max_lifetime(None)
+            .idle_timeout(None)
+            .build(SqliteConnectionManager::file(uri))?;
+        Self::with_pool(pool)
+    }
 
-            db
-        })
+    fn with_pool(pool: Pool) -> AppResult<Database> {
+        let db = Database { pool };
+        db.init_tables()?;
+        Ok(db)
     }
 
-    pub fn init_conversation_dao(&self) -> Result<()> {
-        let conn = self.pool.get().expect("Failed to get connection from pool");
+    fn init_tables(&self) -> AppResult<()> {
+        self.init_conversation_dao()?;
+        self.init_settings_dao()?;
+        self.init_model_config_dao()?;
+        self.init_model_catalog_dao()?;
+        self.init_inference_stats_dao()?;
+        self.init_model_benchmarks_dao()?;
+        self.init_knowledge_dao()?;
+        self.init_attachments_dao()?;
+        self.init_message_embeddings_dao()?;
+        self.init_memory_dao()?;
+        self.init_adapters_dao()?;
+        Ok(())
+    }
+
+    pub fn init_conversation_dao(&self) -> AppResult<()> {
+        let conn = self.get_conn()?;
         conn.execute(
             "CREATE TABLE IF NOT EXISTS conversations (
                 id TEXT PRIMARY KEY,
@@ -43,8 +74,8 @@ impl Database {
         Ok(())
     }
 
-    pub fn init_settings_dao(&self) -> Result<()> {
-        let conn = self.pool.get().expect("Failed to get connection from pool");
+    pub fn init_settings_dao(&self) -> AppResult<()> {
+        let conn = self.get_conn()?;
         conn.execute(
             "CREATE TABLE IF NOT EXISTS settings (
                 key TEXT PRIMARY KEY,
@@ -55,8 +86,8 @@ impl Database {
         Ok(())
     }
 
-        pub fn init_model_config_dao(&self) -> Result<()> {
-        let conn = self.pool.get().expect("Failed to get connection from pool");
+    pub fn init_model_config_dao(&self) -> AppResult<()> {
+        let conn = self.get_conn()?;
         conn.execute(
             "CREATE TABLE IF NOT EXISTS model_config (
                 key TEXT PRIMARY KEY,
@@ -67,8 +98,150 @@ impl Database {
         Ok(())
     }
 
+    pub fn init_model_catalog_dao(&self) -> AppResult<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS model_catalog (
+                file_name TEXT PRIMARY KEY,
+                body TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    pub fn init_inference_stats_dao(&self) -> AppResult<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS inference_stats (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                model_name TEXT NOT NULL,
+                prompt_tokens INTEGER NOT NULL,
+                prompt_ms REAL NOT NULL,
+                generated_tokens INTEGER NOT NULL,
+                generation_ms REAL NOT NULL,
+                time_to_first_token_ms REAL NOT NULL,
+                tokens_per_second REAL NOT NULL,
+                created TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    pub fn init_model_benchmarks_dao(&self) -> AppResult<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS model_benchmarks (
+                model_name TEXT PRIMARY KEY,
+                body TEXT NOT NULL,
+                created TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    pub fn init_knowledge_dao(&self) -> AppResult<()> {
+        let conn = self.get_conn()?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS knowledge_documents (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                path TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                chunk_count INTEGER NOT NULL,
+                embedding_model TEXT NOT NULL,
+                created TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS knowledge_chunks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                document_id TEXT NOT NULL,
+                chunk_index INTEGER NOT NULL,
+                start_offset INTEGER NOT NULL,
+                end_offset INTEGER NOT NULL,
+                text TEXT NOT NULL,
+                embedding BLOB NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS knowledge_chunks_document
+                ON knowledge_chunks (document_id);",
+        )?;
+        Ok(())
+    }
+
+    pub fn init_attachments_dao(&self) -> AppResult<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS attachments (
+                hash TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                content BLOB NOT NULL,
+                created TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    pub fn init_message_embeddings_dao(&self) -> AppResult<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS message_embeddings (
+                conversation_id TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                embedding_model TEXT NOT NULL,
+                embedding BLOB NOT NULL,
+                PRIMARY KEY (conversation_id, position)
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    pub fn init_memory_dao(&self) -> AppResult<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS memory_facts (
+                id TEXT PRIMARY KEY,
+                content TEXT NOT NULL,
+                source TEXT NOT NULL,
+                accepted INTEGER NOT NULL,
+                embedding BLOB,
+                embedding_model TEXT,
+                created TEXT NOT NULL,
+                updated TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    pub fn init_adapters_dao(&self) -> AppResult<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS lora_adapters (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                created TEXT NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS conversation_adapters (
+                conversation_id TEXT NOT NULL,
+                adapter_id TEXT NOT NULL,
+                scale REAL NOT NULL,
+                position INTEGER NOT NULL,
+                PRIMARY KEY (conversation_id, adapter_id)
+            )",
+            [],
+        )?;
+        Ok(())
+    }
 
-    pub fn get_conn(&self) -> r2d2::PooledConnection<SqliteConnectionManager> {
-        self.pool.get().expect("Database pool exhausted")
+    pub fn get_conn(&self) -> AppResult<r2d2::PooledConnection<SqliteConnectionManager>> {
+        self.pool.get().map_err(AppError::from)
     }
 }
@@ -0,0 +1,163 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::infrastructure::consts;
+
+#[derive(serde::Serialize, Clone, Copy, Debug)]
+pub struct DeviceMemory {
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+}
+
+// Falls back to the default budget when the platform query fails
+pub fn memory() -> DeviceMemory {
+    read_memory().unwrap_or(DeviceMemory {
+        total_bytes: consts::DEFAULT_GLOBAL_MEM_BYTES,
+        available_bytes: consts::DEFAULT_GLOBAL_MEM_BYTES,
+    })
+}
+
+pub fn cpu_threads() -> u32 {
+    std::thread::available_parallelism()
+        .map(|n| n.get() as u32)
+        .unwrap_or(4)
+}
+
+// Highest resident size of this process while `run` executes, sampled from another thread
+pub fn peak_resident_bytes<T>(run: impl FnOnce() -> T) -> (T, u64) {
+    let done = AtomicBool::new(false);
+    std::thread::scope(|scope| {
+        let sampler = scope.spawn(|| {
+            let mut peak = 0;
+            loop {
+                peak = peak.max(resident_bytes().unwrap_or(0));
+                if done.load(Ordering::Relaxed) {
+                    return peak;
+                }
+                std::thread::sleep(consts::MEMORY_SAMPLE_INTERVAL);
+            }
+        });
+        let value = run();
+        done.store(true, Ordering::Relaxed);
+        (value, sampler.join().unwrap_or(0))
+    })
+}
+
+// Memory of this process held in RAM, mapped model pages included once they are read
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn resident_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    parse_resident(&status)
+}
+
+// `VmRSS:    123456 kB` in /proc/self/status
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn parse_resident(status: &str) -> Option<u64> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|kib| kib.parse::<u64>().ok())
+        .map(|kib| kib * 1024)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn resident_bytes() -> Option<u64> {
+    use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System};
+
+    let pid = sysinfo::get_current_pid().ok()?;
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        false,
+        ProcessRefreshKind::nothing().with_memory(),
+    );
+    system.process(pid).map(|process| process.memory())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn read_memory() -> Option<DeviceMemory> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    parse_meminfo(&meminfo)
+}
+
+// Values in /proc/meminfo are reported in KiB, e.g. `MemAvailable:    8123456 kB`
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn parse_meminfo(meminfo: &str) -> Option<DeviceMemory> {
+    let field = |name: &str| -> Option<u64> {
+        meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .and_then(|rest| rest.split_whitespace().next())
+            .and_then(|kib| kib.parse::<u64>().ok())
+            .map(|kib| kib * 1024)
+    };
+
+    let total_bytes = field("MemTotal")?;
+    // Kernels older than 3.14 do not report MemAvailable
+    let available_bytes = field("MemAvailable")
+        .or_else(|| Some(field("MemFree")? + field("Cached").unwrap_or(0)))
+        .unwrap_or(total_bytes);
+
+    Some(DeviceMemory {
+        total_bytes,
+        available_bytes,
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn read_memory() -> Option<DeviceMemory> {
+    let mut system = sysinfo::System::new();
+    system.refresh_memory();
+
+    let total_bytes = system.total_memory();
+    if total_bytes == 0 {
+        return None;
+    }
+
+    Some(DeviceMemory {
+        total_bytes,
+        available_bytes: system.available_memory(),
+    })
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android")))]
+mod tests {
+    use super::*;
+
+    const GIB_IN_KIB: u64 = 1024 * 1024;
+
+    #[test]
+    fn meminfo_reports_total_and_available() {
+        let meminfo = "MemTotal:       16777216 kB\n\
+                       MemFree:         1048576 kB\n\
+                       MemAvailable:    8388608 kB\n\
+                       Cached:          4194304 kB\n";
+        let memory = parse_meminfo(meminfo).unwrap();
+        assert_eq!(memory.total_bytes, 16 * GIB_IN_KIB * 1024);
+        assert_eq!(memory.available_bytes, 8 * GIB_IN_KIB * 1024);
+    }
+
+    #[test]
+    fn missing_meminfo_lines_fall_back() {
+        // Without MemAvailable, free and cached memory are counted
+        let old_kernel = "MemTotal: 4194304 kB\nMemFree: 1048576 kB\nCached: 1048576 kB\n";
+        let memory = parse_meminfo(old_kernel).unwrap();
+        assert_eq!(memory.available_bytes, 2 * GIB_IN_KIB * 1024);
+
+        // Nothing about free memory leaves all of it available
+        let memory = parse_meminfo("MemTotal: 4194304 kB\n").unwrap();
+        assert_eq!(memory.available_bytes, memory.total_bytes);
+
+        assert!(parse_meminfo("MemAvailable: 4194304 kB\n").is_none());
+        assert!(parse_meminfo("MemTotal: unknown kB\n").is_none());
+        // A field is matched by its whole name
+        assert!(parse_meminfo("MemTotalHuge: 4194304 kB\n").is_none());
+    }
+
+    #[test]
+    fn resident_size_is_read_from_status() {
+        let status = "Name:\tbreve\nVmPeak:\t  900000 kB\nVmRSS:\t  204800 kB\n";
+        assert_eq!(parse_resident(status), Some(200 * 1024 * 1024));
+        assert_eq!(parse_resident("Name:\tbreve\n"), None);
+    }
+}
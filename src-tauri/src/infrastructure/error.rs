@@ -0,0 +1,90 @@
+use std::fmt;
+
+use serde::Serialize;
+
+// Serialized as `{"code": "NOT_FOUND", "message": "..."}` so the frontend can match on the code
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "code", content = "message", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AppError {
+    NotFound(String),
+    ModelNotDownloaded(String),
+    ModelLoadFailed(String),
+    ContextOverflow(String),
+    // Generation hit the output length limit before the reply was complete
+    OutputTruncated(String),
+    Database(String),
+    Network(String),
+    Validation(String),
+    // Failures that are neither the user's nor the environment's doing, e.g. a decode error
+    Internal(String),
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::NotFound(_) => "NOT_FOUND",
+            AppError::ModelNotDownloaded(_) => "MODEL_NOT_DOWNLOADED",
+            AppError::ModelLoadFailed(_) => "MODEL_LOAD_FAILED",
+            AppError::ContextOverflow(_) => "CONTEXT_OVERFLOW",
+            AppError::OutputTruncated(_) => "OUTPUT_TRUNCATED",
+            AppError::Database(_) => "DATABASE",
+            AppError::Network(_) => "NETWORK",
+            AppError::Validation(_) => "VALIDATION",
+            AppError::Internal(_) => "INTERNAL",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::NotFound(message)
+            | AppError::ModelNotDownloaded(message)
+            | AppError::ModelLoadFailed(message)
+            | AppError::ContextOverflow(message)
+            | AppError::OutputTruncated(message)
+            | AppError::Database(message)
+            | AppError::Network(message)
+            | AppError::Validation(message)
+            | AppError::Internal(message) => message,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code(), self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        AppError::Database(e.to_string())
+    }
+}
+
+impl From<r2d2::Error> for AppError {
+    fn from(e: r2d2::Error) -> Self {
+        AppError::Database(format!("Connection unavailable: {}", e))
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        AppError::Network(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::Internal(format!("I/O error: {}", e))
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(e: tauri::Error) -> Self {
+        AppError::Internal(e.to_string())
+    }
+}
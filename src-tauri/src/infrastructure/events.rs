@@ -0,0 +1,80 @@
+use std::sync::{Mutex, mpsc::Sender};
+
+use tauri::{Emitter, Window};
+
+use crate::inference::models::StreamingContent;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    // A generated piece of a conversation reply
+    Stream(StreamingContent),
+    // Model download progress in percent
+    DownloadProgress(f64),
+}
+
+// Receives stream and progress events from the services
+pub trait EventSink: Send + Sync {
+    fn emit(&self, event: Event);
+}
+
+impl EventSink for Window {
+    fn emit(&self, event: Event) {
+        let _ = match event {
+            Event::Stream(content) => Emitter::emit(self, "llm-stream", content),
+            Event::DownloadProgress(pct) => Emitter::emit(self, "download-progress", pct),
+        };
+    }
+}
+
+// Forwards events to another thread, a dropped receiver is ignored
+pub struct ChannelSink(Mutex<Sender<Event>>);
+
+impl ChannelSink {
+    pub fn new(sender: Sender<Event>) -> Self {
+        ChannelSink(Mutex::new(sender))
+    }
+}
+
+impl EventSink for ChannelSink {
+    fn emit(&self, event: Event) {
+        if let Ok(sender) = self.0.lock() {
+            let _ = sender.send(event);
+        }
+    }
+}
+
+// Keeps every event in memory, for tests
+#[derive(Default)]
+pub struct RecordingSink(Mutex<Vec<Event>>);
+
+impl RecordingSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn events(&self) -> Vec<Event> {
+        self.0
+            .lock()
+            .map(|events| events.clone())
+            .unwrap_or_default()
+    }
+
+    // Concatenated stream pieces, i.e. the reply as the frontend would see it
+    pub fn streamed_text(&self) -> String {
+        self.events()
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Stream(content) => Some(content.content),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl EventSink for RecordingSink {
+    fn emit(&self, event: Event) {
+        if let Ok(mut events) = self.0.lock() {
+            events.push(event);
+        }
+    }
+}
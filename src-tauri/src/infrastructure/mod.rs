@@ -1,7 +1,11 @@
+pub mod clock;
 pub mod consts;
 pub mod context;
 pub mod controller;
 pub mod database;
+pub mod device;
+pub mod error;
+pub mod events;
 pub mod path_resolver;
 pub mod repository;
 pub mod service;
@@ -3,32 +3,51 @@ use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 use tauri::{AppHandle, Manager, path::BaseDirectory};
 
+use crate::infrastructure::{
+    consts,
+    error::{AppError, AppResult},
+};
+
+enum Base {
+    App(AppHandle),
+    // Used by the CLI, which has no app handle to resolve through
+    Directory(PathBuf),
+}
+
 // Global path provider
 pub struct AppPaths {
-    app_handle: AppHandle,
+    base: Base,
 }
 
 impl AppPaths {
     pub fn resource<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, String> {
-        self.app_handle
-            .path()
-            .resolve(path, BaseDirectory::Resource)
-            .map_err(|e| format!("Failed to resolve resource path: {}", e))
+        match &self.base {
+            Base::App(app_handle) => app_handle
+                .path()
+                .resolve(path, BaseDirectory::Resource)
+                .map_err(|e| format!("Failed to resolve resource path: {}", e)),
+            Base::Directory(dir) => Ok(dir.join(path)),
+        }
     }
 
     pub fn config<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, String> {
-        self.app_handle
-            .path()
-            .resolve(path, BaseDirectory::Config)
-            .map_err(|e| format!("Failed to resolve config path: {}", e))
+        match &self.base {
+            Base::App(app_handle) => app_handle
+                .path()
+                .resolve(path, BaseDirectory::Config)
+                .map_err(|e| format!("Failed to resolve config path: {}", e)),
+            Base::Directory(dir) => Ok(dir.join(path)),
+        }
     }
 
     pub fn app_local_data<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, String> {
-        let path = self
-            .app_handle
-            .path()
-            .resolve(path, BaseDirectory::AppLocalData)
-            .map_err(|e| format!("Failed to resolve app local data path: {}", e))?;
+        let path = match &self.base {
+            Base::App(app_handle) => app_handle
+                .path()
+                .resolve(path, BaseDirectory::AppLocalData)
+                .map_err(|e| format!("Failed to resolve app local data path: {}", e))?,
+            Base::Directory(dir) => dir.join(path),
+        };
 
         // Ensure parent directory exists
         if let Some(parent) = path.parent() {
@@ -45,9 +64,24 @@ impl AppPaths {
 static APP_PATHS: OnceLock<AppPaths> = OnceLock::new();
 
 pub fn init_app_paths(app_handle: AppHandle) {
-    let _ = APP_PATHS.set(AppPaths { app_handle });
+    let _ = APP_PATHS.set(AppPaths {
+        base: Base::App(app_handle),
+    });
+}
+
+pub fn init_dir_paths(dir: PathBuf) {
+    let _ = APP_PATHS.set(AppPaths {
+        base: Base::Directory(dir),
+    });
+}
+
+// Where Tauri places AppLocalData, so tools without an app handle share the app's data
+pub fn default_data_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join(consts::APP_IDENTIFIER))
 }
 
-pub fn paths() -> &'static AppPaths {
-    APP_PATHS.get().expect("App paths not initialized")
+pub fn paths() -> AppResult<&'static AppPaths> {
+    APP_PATHS
+        .get()
+        .ok_or_else(|| AppError::Internal("App paths not initialized".into()))
 }
@@ -1,9 +1,7 @@
-use rusqlite::Result;
+use crate::infrastructure::{database::Database, error::AppResult};
 
-use crate::infrastructure::database::Database;
-
-pub fn get_config(name: String) -> Result<Option<String>> {
-    let conn = Database::get_db().get_conn();
+pub fn get_config(db: &Database, name: String) -> AppResult<Option<String>> {
+    let conn = db.get_conn()?;
     let mut stmt = conn.prepare("SELECT value FROM settings WHERE key = ?1")?;
     let mut rows = stmt.query(rusqlite::params![name])?;
 
@@ -15,8 +13,8 @@ pub fn get_config(name: String) -> Result<Option<String>> {
     }
 }
 
-pub fn set_config(name: String, value: String) -> Result<()> {
-    let conn = Database::get_db().get_conn();
+pub fn set_config(db: &Database, name: String, value: String) -> AppResult<()> {
+    let conn = db.get_conn()?;
     conn.execute(
         "INSERT INTO settings (key, value) VALUES (?1, ?2)
             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
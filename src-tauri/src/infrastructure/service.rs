@@ -1,13 +1,14 @@
-use rusqlite::Result;
+use crate::infrastructure::{
+    database::Database,
+    error::{AppError, AppResult},
+    repository as dao,
+};
 
-use crate::infrastructure::repository as dao;
-
-pub fn get_config(key: String) -> Result<String, String> {
-    dao::get_config(key)
-        .map_err(|e| e.to_string())?
-        .ok_or_else(|| "Config not found".to_string())
+pub fn get_config(db: &Database, key: String) -> AppResult<String> {
+    dao::get_config(db, key.clone())?
+        .ok_or_else(|| AppError::NotFound(format!("Config {} not found", key)))
 }
 
-pub fn set_config(key: String, value: String) -> Result<(), String> {
-    dao::set_config(key, value).map_err(|e| e.to_string())
+pub fn set_config(db: &Database, key: String, value: String) -> AppResult<()> {
+    dao::set_config(db, key, value)
 }
@@ -0,0 +1,32 @@
+use std::{path::PathBuf, sync::Arc};
+
+use tauri::{State, async_runtime::Mutex};
+
+use crate::{
+    infrastructure::{context::Context, database::Database, error::AppResult},
+    knowledge::{models::Document, service},
+};
+
+#[tauri::command]
+pub async fn add_knowledge_document(
+    path: String,
+    app_state: State<'_, Arc<Mutex<Context>>>,
+) -> AppResult<Document> {
+    let app_state_handle: Arc<Mutex<Context>> = app_state.inner().clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut ctx = tauri::async_runtime::block_on(app_state_handle.lock());
+        service::add_document(&PathBuf::from(path), &mut ctx)
+    })
+    .await?
+}
+
+#[tauri::command]
+pub async fn get_knowledge_documents(db: State<'_, Database>) -> AppResult<Vec<Document>> {
+    service::get_documents(&db)
+}
+
+#[tauri::command]
+pub async fn delete_knowledge_document(id: String, db: State<'_, Database>) -> AppResult<()> {
+    service::delete_document(&db, &id)
+}
@@ -0,0 +1,22 @@
+use std::fs;
+use std::path::Path;
+
+use crate::attachments;
+use crate::infrastructure::error::{AppError, AppResult};
+use crate::knowledge::models::DocumentKind;
+
+// Wide enough that html2text never wraps, chunking decides where lines end
+const HTML_TEXT_WIDTH: usize = 100_000;
+
+// Plain text of a document, markdown is kept as is since models read it natively
+pub fn extract_text(path: &Path, kind: DocumentKind) -> AppResult<String> {
+    let bytes = fs::read(path)?;
+    let text = match kind {
+        DocumentKind::Text | DocumentKind::Markdown => attachments::service::decode(&bytes).0,
+        DocumentKind::Html => html2text::from_read(bytes.as_slice(), HTML_TEXT_WIDTH)
+            .map_err(|e| AppError::Validation(format!("Unreadable HTML: {}", e)))?,
+        DocumentKind::Pdf => pdf_extract::extract_text_from_mem(&bytes)
+            .map_err(|e| AppError::Validation(format!("Unreadable PDF: {}", e)))?,
+    };
+    Ok(text.replace("\r\n", "\n"))
+}
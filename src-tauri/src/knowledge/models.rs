@@ -0,0 +1,89 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentKind {
+    Text,
+    Markdown,
+    Html,
+    Pdf,
+}
+
+impl DocumentKind {
+    pub fn from_path(path: &Path) -> Option<DocumentKind> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "txt" | "text" | "log" | "csv" => Some(DocumentKind::Text),
+            "md" | "markdown" => Some(DocumentKind::Markdown),
+            "html" | "htm" => Some(DocumentKind::Html),
+            "pdf" => Some(DocumentKind::Pdf),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DocumentKind::Text => "text",
+            DocumentKind::Markdown => "markdown",
+            DocumentKind::Html => "html",
+            DocumentKind::Pdf => "pdf",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<DocumentKind> {
+        match value {
+            "text" => Some(DocumentKind::Text),
+            "markdown" => Some(DocumentKind::Markdown),
+            "html" => Some(DocumentKind::Html),
+            "pdf" => Some(DocumentKind::Pdf),
+            _ => None,
+        }
+    }
+}
+
+// A file added to the knowledge base
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Document {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub kind: DocumentKind,
+    pub size: u64,
+    pub chunk_count: u64,
+    // Model the chunks were embedded with, only questions embedded by it can search them
+    pub embedding_model: String,
+}
+
+// Slice of a document's extracted text, `start` and `end` are byte offsets into it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub index: u64,
+    pub start: u64,
+    pub end: u64,
+    pub text: String,
+}
+
+// Chunk retrieved for a question, with its similarity to it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Passage {
+    pub document_id: String,
+    pub document_name: String,
+    pub chunk_index: u64,
+    pub start: u64,
+    pub end: u64,
+    pub text: String,
+    pub score: f32,
+}
+
+// Passage a reply referred to through its `[marker]`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Citation {
+    pub marker: u64,
+    pub document_id: String,
+    pub document_name: String,
+    pub start: u64,
+    pub end: u64,
+    pub text: String,
+}
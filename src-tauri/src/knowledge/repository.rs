@@ -0,0 +1,190 @@
+use rusqlite::params;
+
+use crate::{
+    inference::models::{vector_from_bytes, vector_to_bytes},
+    infrastructure::{
+        database::Database,
+        error::{AppError, AppResult},
+    },
+    knowledge::models::{Chunk, Document, DocumentKind, Passage},
+};
+
+// Stores a document with its embedded chunks, replacing any earlier copy of the same file
+pub fn add_document(
+    db: &Database,
+    document: &Document,
+    chunks: &[Chunk],
+    vectors: &[Vec<f32>],
+) -> AppResult<()> {
+    let mut conn = db.get_conn()?;
+    let tx = conn.transaction()?;
+    tx.execute(
+        "DELETE FROM knowledge_chunks WHERE document_id IN
+            (SELECT id FROM knowledge_documents WHERE path = ?1)",
+        params![document.path],
+    )?;
+    tx.execute(
+        "DELETE FROM knowledge_documents WHERE path = ?1",
+        params![document.path],
+    )?;
+    tx.execute(
+        "INSERT INTO knowledge_documents (id, name, path, kind, size, chunk_count, embedding_model, created)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, DATETIME('now'))",
+        params![
+            document.id,
+            document.name,
+            document.path,
+            document.kind.as_str(),
+            document.size,
+            document.chunk_count,
+            document.embedding_model
+        ],
+    )?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO knowledge_chunks (document_id, chunk_index, start_offset, end_offset, text, embedding)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        for (chunk, vector) in chunks.iter().zip(vectors) {
+            stmt.execute(params![
+                document.id,
+                chunk.index,
+                chunk.start,
+                chunk.end,
+                chunk.text,
+                vector_to_bytes(vector)
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn get_documents(db: &Database) -> AppResult<Vec<Document>> {
+    let conn = db.get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, name, path, kind, size, chunk_count, embedding_model
+            FROM knowledge_documents ORDER BY created DESC, name",
+    )?;
+    let documents = stmt
+        .query_map([], |row| {
+            let kind: String = row.get(3)?;
+            Ok(Document {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                path: row.get(2)?,
+                kind: DocumentKind::parse(&kind).unwrap_or(DocumentKind::Text),
+                size: row.get(4)?,
+                chunk_count: row.get(5)?,
+                embedding_model: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(documents)
+}
+
+pub fn delete_document(db: &Database, id: &str) -> AppResult<()> {
+    let mut conn = db.get_conn()?;
+    let tx = conn.transaction()?;
+    tx.execute(
+        "DELETE FROM knowledge_chunks WHERE document_id = ?1",
+        params![id],
+    )?;
+    let deleted = tx.execute("DELETE FROM knowledge_documents WHERE id = ?1", params![id])?;
+    if deleted == 0 {
+        return Err(AppError::NotFound(format!("Document {} not found", id)));
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn count_chunks(db: &Database, embedding_model: &str) -> AppResult<u64> {
+    let conn = db.get_conn()?;
+    let count = conn.query_row(
+        "SELECT COUNT(*) FROM knowledge_chunks c
+            JOIN knowledge_documents d ON d.id = c.document_id
+            WHERE d.embedding_model = ?1",
+        params![embedding_model],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+// Every chunk embedded by `embedding_model`, unscored
+pub fn get_chunks(db: &Database, embedding_model: &str) -> AppResult<Vec<(Passage, Vec<f32>)>> {
+    let conn = db.get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT c.document_id, d.name, c.chunk_index, c.start_offset, c.end_offset, c.text, c.embedding
+            FROM knowledge_chunks c
+            JOIN knowledge_documents d ON d.id = c.document_id
+            WHERE d.embedding_model = ?1",
+    )?;
+    let chunks = stmt
+        .query_map(params![embedding_model], |row| {
+            let embedding: Vec<u8> = row.get(6)?;
+            Ok((
+                Passage {
+                    document_id: row.get(0)?,
+                    document_name: row.get(1)?,
+                    chunk_index: row.get(2)?,
+                    start: row.get(3)?,
+                    end: row.get(4)?,
+                    text: row.get(5)?,
+                    score: 0.0,
+                },
+                vector_from_bytes(&embedding),
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(chunks)
+}
+
+// Ids of documents embedded by a model other than `embedding_model`
+pub fn get_stale_documents(db: &Database, embedding_model: &str) -> AppResult<Vec<String>> {
+    let conn = db.get_conn()?;
+    let mut stmt =
+        conn.prepare("SELECT id FROM knowledge_documents WHERE embedding_model != ?1")?;
+    let ids = stmt
+        .query_map(params![embedding_model], |row| row.get(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+    Ok(ids)
+}
+
+// (chunk index, text) of every chunk of a document
+pub fn get_chunk_texts(db: &Database, document_id: &str) -> AppResult<Vec<(u64, String)>> {
+    let conn = db.get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT chunk_index, text FROM knowledge_chunks
+            WHERE document_id = ?1 ORDER BY chunk_index",
+    )?;
+    let chunks = stmt
+        .query_map(params![document_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(chunks)
+}
+
+// Replaces the vectors of a document's chunks with ones made by `embedding_model`
+pub fn set_embeddings(
+    db: &Database,
+    document_id: &str,
+    embedding_model: &str,
+    embeddings: &[(u64, Vec<f32>)],
+) -> AppResult<()> {
+    let mut conn = db.get_conn()?;
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare(
+            "UPDATE knowledge_chunks SET embedding = ?1
+                WHERE document_id = ?2 AND chunk_index = ?3",
+        )?;
+        for (index, vector) in embeddings {
+            stmt.execute(params![vector_to_bytes(vector), document_id, index])?;
+        }
+    }
+    tx.execute(
+        "UPDATE knowledge_documents SET embedding_model = ?1 WHERE id = ?2",
+        params![embedding_model, document_id],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
@@ -0,0 +1,240 @@
+use std::fs;
+use std::path::Path;
+
+use uuid::Uuid;
+
+use crate::{
+    configuration::models::Config,
+    inference::{self, models::similarity},
+    infrastructure::{
+        consts,
+        context::Context,
+        database::Database,
+        error::{AppError, AppResult},
+    },
+    knowledge::{
+        extract,
+        models::{Chunk, Citation, Document, DocumentKind, Passage},
+        repository as dao,
+    },
+};
+
+// Extracts, chunks and embeds a file, adding it again replaces the stored copy
+pub fn add_document(path: &Path, ctx: &mut Context) -> AppResult<Document> {
+    let kind = DocumentKind::from_path(path).ok_or_else(|| {
+        AppError::Validation(format!("Unsupported file type: {}", path.display()))
+    })?;
+    let size = fs::metadata(path)?.len();
+    let text = extract::extract_text(path, kind)?;
+
+    let chunks = split_into_chunks(
+        &text,
+        consts::KB_CHUNK_CHARS,
+        consts::KB_CHUNK_OVERLAP_CHARS,
+    );
+    if chunks.is_empty() {
+        return Err(AppError::Validation(format!(
+            "No text found in {}",
+            path.display()
+        )));
+    }
+
+    let mut vectors = Vec::with_capacity(chunks.len());
+    for batch in chunks.chunks(consts::KB_EMBED_BATCH) {
+        let texts: Vec<String> = batch.iter().map(|chunk| chunk.text.clone()).collect();
+        vectors.extend(inference::service::embed(&texts, ctx)?);
+    }
+
+    let document = Document {
+        id: Uuid::new_v4().to_string(),
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string()),
+        path: path.to_string_lossy().to_string(),
+        kind,
+        size,
+        chunk_count: chunks.len() as u64,
+        embedding_model: inference::service::embedding_model(&ctx.config).to_string(),
+    };
+    dao::add_document(&ctx.db, &document, &chunks, &vectors)?;
+    Ok(document)
+}
+
+pub fn get_documents(db: &Database) -> AppResult<Vec<Document>> {
+    dao::get_documents(db)
+}
+
+pub fn delete_document(db: &Database, id: &str) -> AppResult<()> {
+    dao::delete_document(db, id)
+}
+
+// Chunks most similar to `question`, none when nothing stored is relevant enough
+pub fn retrieve(question: &str, ctx: &mut Context) -> AppResult<Vec<Passage>> {
+    let model = inference::service::embedding_model(&ctx.config).to_string();
+    if model.is_empty() {
+        return Ok(Vec::new());
+    }
+    // However the embedding model changed, documents from the previous one are caught up first
+    let db = ctx.db.clone();
+    reembed_documents(&db, &model, |texts| inference::service::embed(texts, ctx))?;
+    if dao::count_chunks(&ctx.db, &model)? == 0 {
+        return Ok(Vec::new());
+    }
+
+    let query = inference::service::embed(&[question.to_string()], ctx)?
+        .pop()
+        .ok_or_else(|| AppError::Internal("Question was not embedded".into()))?;
+
+    let mut passages: Vec<Passage> = dao::get_chunks(&ctx.db, &model)?
+        .into_iter()
+        .map(|(passage, vector)| Passage {
+            score: similarity(&query, &vector),
+            ..passage
+        })
+        .filter(|passage| passage.score >= consts::KB_MIN_SCORE)
+        .collect();
+    passages.sort_by(|a, b| b.score.total_cmp(&a.score));
+    passages.truncate(consts::KB_TOP_K);
+
+    // Passages get a fixed share of the context so the conversation keeps room
+    let mut budget = passage_budget(&ctx.config);
+    Ok(passages
+        .into_iter()
+        .filter(|passage| {
+            let fits = passage.text.len() <= budget;
+            if fits {
+                budget -= passage.text.len();
+            }
+            fits
+        })
+        .collect())
+}
+
+// Characters of passages a prompt can take
+pub fn passage_budget(config: &Config) -> usize {
+    config.max_context_length as usize / consts::KB_CONTEXT_SHARE * consts::CHARS_PER_TOKEN
+}
+
+// Embeds again the documents a previous embedding model indexed, their vectors cannot be
+// compared with the current model's. Returns how many documents were updated.
+pub fn reembed_documents(
+    db: &Database,
+    model: &str,
+    mut embed: impl FnMut(&[String]) -> AppResult<Vec<Vec<f32>>>,
+) -> AppResult<usize> {
+    if model.is_empty() {
+        return Ok(0);
+    }
+    let stale = dao::get_stale_documents(db, model)?;
+    for document_id in &stale {
+        let chunks = dao::get_chunk_texts(db, document_id)?;
+        let mut embeddings = Vec::with_capacity(chunks.len());
+        for batch in chunks.chunks(consts::KB_EMBED_BATCH) {
+            let texts: Vec<String> = batch.iter().map(|(_, text)| text.clone()).collect();
+            let vectors = embed(&texts)?;
+            embeddings.extend(batch.iter().map(|(index, _)| *index).zip(vectors));
+        }
+        dao::set_embeddings(db, document_id, model, &embeddings)?;
+    }
+    Ok(stale.len())
+}
+
+// Passages the reply refers to, through markers like `[2]` or `[1, 3]`
+pub fn cited(reply: &str, passages: &[Passage]) -> Vec<Citation> {
+    let mut markers: Vec<usize> = Vec::new();
+    let mut rest = reply;
+    while let Some(open) = rest.find('[') {
+        rest = &rest[open + 1..];
+        let Some(close) = rest.find(']') else {
+            break;
+        };
+        let numbers: Option<Vec<usize>> = rest[..close]
+            .split(',')
+            .map(|part| part.trim().parse().ok())
+            .collect();
+        for marker in numbers.unwrap_or_default() {
+            if (1..=passages.len()).contains(&marker) && !markers.contains(&marker) {
+                markers.push(marker);
+            }
+        }
+    }
+    markers.sort_unstable();
+
+    markers
+        .into_iter()
+        .map(|marker| {
+            let passage = &passages[marker - 1];
+            Citation {
+                marker: marker as u64,
+                document_id: passage.document_id.clone(),
+                document_name: passage.document_name.clone(),
+                start: passage.start,
+                end: passage.end,
+                text: passage.text.clone(),
+            }
+        })
+        .collect()
+}
+
+// Windows of up to `max_chars` characters ending at a paragraph, line, sentence or word
+// break when one falls in their second half, consecutive windows share about `overlap` characters
+pub fn split_into_chunks(text: &str, max_chars: usize, overlap: usize) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < text.len() {
+        let window_end = text[start..]
+            .char_indices()
+            .nth(max_chars)
+            .map(|(i, _)| start + i)
+            .unwrap_or(text.len());
+        let end = if window_end == text.len() {
+            window_end
+        } else {
+            break_point(text, start, window_end)
+        };
+
+        let raw = &text[start..end];
+        let piece = raw.trim();
+        if !piece.is_empty() {
+            let chunk_start = start + (raw.len() - raw.trim_start().len());
+            chunks.push(Chunk {
+                index: chunks.len() as u64,
+                start: chunk_start as u64,
+                end: (chunk_start + piece.len()) as u64,
+                text: piece.to_string(),
+            });
+        }
+        if end == text.len() {
+            break;
+        }
+
+        // Step back for the overlap, then forward to the next word so no chunk starts mid-word
+        let back = text[start..end]
+            .char_indices()
+            .rev()
+            .nth(overlap)
+            .map(|(i, _)| start + i)
+            .unwrap_or(start);
+        let next = text[back..end]
+            .char_indices()
+            .find(|(_, c)| c.is_whitespace())
+            .map(|(i, c)| back + i + c.len_utf8())
+            .unwrap_or(end);
+        start = if next > start { next } else { end };
+    }
+    chunks
+}
+
+fn break_point(text: &str, start: usize, window_end: usize) -> usize {
+    let window = &text[start..window_end];
+    for separator in ["\n\n", "\n", ". ", " "] {
+        if let Some(pos) = window.rfind(separator)
+            && pos >= window.len() / 2
+        {
+            return start + pos + separator.len();
+        }
+    }
+    window_end
+}
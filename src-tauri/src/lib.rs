@@ -1,14 +1,21 @@
 use std::sync::Arc;
 
+pub mod adapters;
+pub mod attachments;
 pub mod configuration;
 pub mod conversation;
 pub mod inference;
 pub mod infrastructure;
+pub mod knowledge;
+pub mod memory;
 pub mod models;
+pub mod search;
+pub mod server;
+pub mod tools;
 
 use tauri::{Manager, async_runtime::Mutex};
 
-use crate::infrastructure::context::Context;
+use crate::infrastructure::{context::Context, database::Database};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -18,16 +25,23 @@ pub fn run() {
         .setup(|app| {
             infrastructure::path_resolver::init_app_paths(app.handle().clone());
 
-            let mut ctx = Context::init()?;
+            let db = Database::open(infrastructure::consts::get_db_path()?)?;
+            let mut ctx = Context::init(db.clone())?;
 
-            let saved_model =
-                infrastructure::service::get_config("model_name".to_string()).unwrap_or_default();
+            inference::service::activate_saved_model(&mut ctx);
 
-            if !saved_model.is_empty() {
-                let _ = inference::service::activate_model(saved_model, &mut ctx);
+            let shared_ctx = Arc::new(Mutex::new(ctx));
+
+            let server_config = server::service::load_config(&db);
+            if server_config.enabled
+                && let Err(e) = server::service::start(server_config, shared_ctx.clone())
+            {
+                eprintln!("API server failed to start: {}", e);
             }
 
-            let shared_ctx = Arc::new(Mutex::new(ctx));
+            app.manage(db);
+            // Catches up on messages never indexed or embedded by a previous embedding model
+            search::service::index_in_background(shared_ctx.clone(), None);
 
             app.manage(shared_ctx);
 
@@ -39,17 +53,47 @@ pub fn run() {
             conversation::controller::get_conversation_ids,
             conversation::controller::get_conversation,
             conversation::controller::delete_conversation,
+            attachments::controller::open_attachment,
             models::controller::get_model_status,
             models::controller::get_available_models,
             models::controller::list_downloaded_models,
             models::controller::download_model,
+            models::controller::list_model_variants,
+            models::controller::download_model_variant,
             models::controller::delete_model,
             models::controller::set_default_model,
             models::controller::get_default_model,
             infrastructure::controller::get_config,
             infrastructure::controller::set_config,
+            infrastructure::controller::get_device_memory,
             configuration::controller::get_model_config,
             configuration::controller::set_model_config,
+            configuration::controller::reset_model_config,
+            inference::controller::get_inference_stats,
+            inference::controller::benchmark_model,
+            inference::controller::get_model_benchmarks,
+            inference::controller::embed_texts,
+            inference::controller::generate_structured,
+            inference::controller::generate_constrained,
+            inference::controller::get_chat_templates,
+            inference::controller::set_chat_template,
+            knowledge::controller::add_knowledge_document,
+            knowledge::controller::get_knowledge_documents,
+            knowledge::controller::delete_knowledge_document,
+            search::controller::semantic_search,
+            memory::controller::get_memory_facts,
+            memory::controller::add_memory_fact,
+            memory::controller::update_memory_fact,
+            memory::controller::accept_memory_fact,
+            memory::controller::delete_memory_fact,
+            tools::controller::get_tools,
+            adapters::controller::import_lora_adapter,
+            adapters::controller::get_lora_adapters,
+            adapters::controller::delete_lora_adapter,
+            adapters::controller::get_conversation_adapters,
+            adapters::controller::set_conversation_adapters,
+            server::controller::get_api_server,
+            server::controller::set_api_server,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
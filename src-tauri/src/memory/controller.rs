@@ -0,0 +1,38 @@
+use tauri::State;
+
+use crate::{
+    infrastructure::{database::Database, error::AppResult},
+    memory::{
+        models::{Fact, FactSource},
+        service,
+    },
+};
+
+#[tauri::command]
+pub async fn get_memory_facts(db: State<'_, Database>) -> AppResult<Vec<Fact>> {
+    service::get_facts(&db)
+}
+
+#[tauri::command]
+pub async fn add_memory_fact(content: String, db: State<'_, Database>) -> AppResult<Fact> {
+    service::add_fact(&db, &content, FactSource::User)
+}
+
+#[tauri::command]
+pub async fn update_memory_fact(
+    id: String,
+    content: String,
+    db: State<'_, Database>,
+) -> AppResult<Fact> {
+    service::update_fact(&db, &id, &content)
+}
+
+#[tauri::command]
+pub async fn accept_memory_fact(id: String, db: State<'_, Database>) -> AppResult<Fact> {
+    service::accept_fact(&db, &id)
+}
+
+#[tauri::command]
+pub async fn delete_memory_fact(id: String, db: State<'_, Database>) -> AppResult<()> {
+    service::delete_fact(&db, &id)
+}
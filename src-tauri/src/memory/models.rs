@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FactSource {
+    User,
+    Model,
+}
+
+impl FactSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FactSource::User => "user",
+            FactSource::Model => "model",
+        }
+    }
+
+    pub fn parse(value: &str) -> FactSource {
+        match value {
+            "model" => FactSource::Model,
+            _ => FactSource::User,
+        }
+    }
+}
+
+// Something about the user worth remembering across conversations
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Fact {
+    pub id: String,
+    pub content: String,
+    pub source: FactSource,
+    // Facts proposed by the model stay out of prompts until the user accepts them
+    pub accepted: bool,
+}
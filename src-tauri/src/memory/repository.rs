@@ -0,0 +1,94 @@
+use rusqlite::params;
+
+use crate::{
+    inference::models::{vector_from_bytes, vector_to_bytes},
+    infrastructure::{
+        database::Database,
+        error::{AppError, AppResult},
+    },
+    memory::models::{Fact, FactSource},
+};
+
+pub fn add_fact(db: &Database, fact: &Fact) -> AppResult<()> {
+    let conn = db.get_conn()?;
+    conn.execute(
+        "INSERT INTO memory_facts (id, content, source, accepted, created, updated)
+            VALUES (?1, ?2, ?3, ?4, DATETIME('now'), DATETIME('now'))",
+        params![fact.id, fact.content, fact.source.as_str(), fact.accepted],
+    )?;
+    Ok(())
+}
+
+pub fn get_facts(db: &Database) -> AppResult<Vec<Fact>> {
+    let conn = db.get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, content, source, accepted FROM memory_facts ORDER BY updated DESC, id",
+    )?;
+    let facts = stmt
+        .query_map([], |row| {
+            let source: String = row.get(2)?;
+            Ok(Fact {
+                id: row.get(0)?,
+                content: row.get(1)?,
+                source: FactSource::parse(&source),
+                accepted: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(facts)
+}
+
+// Editing a fact drops its embedding, it no longer describes the new content
+pub fn update_fact(db: &Database, id: &str, content: &str, accepted: bool) -> AppResult<()> {
+    let conn = db.get_conn()?;
+    let updated = conn.execute(
+        "UPDATE memory_facts
+            SET content = ?1, accepted = ?2, embedding = NULL, embedding_model = NULL,
+                updated = DATETIME('now')
+            WHERE id = ?3",
+        params![content, accepted, id],
+    )?;
+    if updated == 0 {
+        return Err(AppError::NotFound(format!("Fact {} not found", id)));
+    }
+    Ok(())
+}
+
+pub fn delete_fact(db: &Database, id: &str) -> AppResult<()> {
+    let conn = db.get_conn()?;
+    let deleted = conn.execute("DELETE FROM memory_facts WHERE id = ?1", params![id])?;
+    if deleted == 0 {
+        return Err(AppError::NotFound(format!("Fact {} not found", id)));
+    }
+    Ok(())
+}
+
+// Embeddings of accepted facts made by `embedding_model`, keyed by fact id
+pub fn get_embeddings(db: &Database, embedding_model: &str) -> AppResult<Vec<(String, Vec<f32>)>> {
+    let conn = db.get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, embedding FROM memory_facts
+            WHERE accepted = 1 AND embedding_model = ?1 AND embedding IS NOT NULL",
+    )?;
+    let embeddings = stmt
+        .query_map(params![embedding_model], |row| {
+            let embedding: Vec<u8> = row.get(1)?;
+            Ok((row.get(0)?, vector_from_bytes(&embedding)))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(embeddings)
+}
+
+pub fn set_embedding(
+    db: &Database,
+    id: &str,
+    embedding_model: &str,
+    vector: &[f32],
+) -> AppResult<()> {
+    let conn = db.get_conn()?;
+    conn.execute(
+        "UPDATE memory_facts SET embedding = ?1, embedding_model = ?2 WHERE id = ?3",
+        params![vector_to_bytes(vector), embedding_model, id],
+    )?;
+    Ok(())
+}
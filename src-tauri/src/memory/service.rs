@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::{
+    inference::{self, models::similarity, stop::partial_match_start},
+    infrastructure::{
+        self, consts,
+        context::Context,
+        database::Database,
+        error::{AppError, AppResult},
+    },
+    memory::{
+        models::{Fact, FactSource},
+        repository as dao,
+    },
+};
+
+const REMEMBER_OPEN: &str = "<remember>";
+const REMEMBER_CLOSE: &str = "</remember>";
+
+pub fn is_enabled(db: &Database) -> bool {
+    infrastructure::service::get_config(db, consts::MEMORY_ENABLED_KEY.to_string())
+        .is_ok_and(|value| value == "true")
+}
+
+pub fn get_facts(db: &Database) -> AppResult<Vec<Fact>> {
+    dao::get_facts(db)
+}
+
+// Facts saved by hand are accepted right away, proposals wait for the user
+pub fn add_fact(db: &Database, content: &str, source: FactSource) -> AppResult<Fact> {
+    let content = validate(content)?;
+    if let Some(existing) = dao::get_facts(db)?
+        .into_iter()
+        .find(|fact| fact.content.eq_ignore_ascii_case(&content))
+    {
+        return Ok(existing);
+    }
+
+    let fact = Fact {
+        id: Uuid::new_v4().to_string(),
+        content,
+        source,
+        accepted: source == FactSource::User,
+    };
+    dao::add_fact(db, &fact)?;
+    Ok(fact)
+}
+
+// Editing a proposal accepts it, the user has vouched for the new wording
+pub fn update_fact(db: &Database, id: &str, content: &str) -> AppResult<Fact> {
+    let content = validate(content)?;
+    dao::update_fact(db, id, &content, true)?;
+    find_fact(db, id)
+}
+
+pub fn accept_fact(db: &Database, id: &str) -> AppResult<Fact> {
+    let fact = find_fact(db, id)?;
+    update_fact(db, id, &fact.content)
+}
+
+pub fn delete_fact(db: &Database, id: &str) -> AppResult<()> {
+    dao::delete_fact(db, id)
+}
+
+// Accepted facts most related to `query` that fit the memory token budget. Without an
+// embedding model they come most recently updated first.
+pub fn relevant_facts(query: &str, ctx: &mut Context) -> AppResult<Vec<Fact>> {
+    let mut facts: Vec<Fact> = dao::get_facts(&ctx.db)?
+        .into_iter()
+        .filter(|fact| fact.accepted)
+        .collect();
+    if facts.is_empty() {
+        return Ok(facts);
+    }
+
+    let model = inference::service::embedding_model(&ctx.config).to_string();
+    if !model.is_empty() {
+        let scores = score_facts(query, &facts, &model, ctx)?;
+        facts.sort_by(|a, b| {
+            let score = |fact: &Fact| scores.get(&fact.id).copied().unwrap_or(f32::MIN);
+            score(b).total_cmp(&score(a))
+        });
+    }
+
+    let mut budget = consts::MEMORY_TOKEN_BUDGET * consts::CHARS_PER_TOKEN;
+    Ok(facts
+        .into_iter()
+        .filter(|fact| {
+            // A long fact is skipped, shorter ones after it may still fit
+            let fits = fact.content.len() <= budget;
+            if fits {
+                budget -= fact.content.len();
+            }
+            fits
+        })
+        .collect())
+}
+
+// Similarity of each fact to `query`, embedding the facts that have no vector from `model` yet
+fn score_facts(
+    query: &str,
+    facts: &[Fact],
+    model: &str,
+    ctx: &mut Context,
+) -> AppResult<HashMap<String, f32>> {
+    let mut vectors: HashMap<String, Vec<f32>> =
+        dao::get_embeddings(&ctx.db, model)?.into_iter().collect();
+
+    let missing: Vec<&Fact> = facts
+        .iter()
+        .filter(|fact| !vectors.contains_key(&fact.id))
+        .collect();
+    if !missing.is_empty() {
+        let texts: Vec<String> = missing.iter().map(|fact| fact.content.clone()).collect();
+        for (fact, vector) in missing
+            .into_iter()
+            .zip(inference::service::embed(&texts, ctx)?)
+        {
+            dao::set_embedding(&ctx.db, &fact.id, model, &vector)?;
+            vectors.insert(fact.id.clone(), vector);
+        }
+    }
+
+    let query = inference::service::embed(&[query.to_string()], ctx)?
+        .pop()
+        .ok_or_else(|| AppError::Internal("Query was not embedded".into()))?;
+    Ok(vectors
+        .into_iter()
+        .map(|(id, vector)| (id, similarity(&query, &vector)))
+        .collect())
+}
+
+// Splits `<remember>` proposals out of a reply, returning the reply without them.
+// Lines left empty once their tags are gone are dropped.
+pub fn take_proposals(reply: &str) -> (String, Vec<String>) {
+    let mut proposals = Vec::new();
+    let mut lines = Vec::new();
+
+    for line in reply.lines() {
+        let mut cleaned = String::new();
+        let mut rest = line;
+        while let Some(open) = rest.find(REMEMBER_OPEN)
+            && let Some(close) = rest[open..].find(REMEMBER_CLOSE)
+        {
+            cleaned.push_str(&rest[..open]);
+            let proposal = rest[open + REMEMBER_OPEN.len()..open + close].trim();
+            if !proposal.is_empty() {
+                proposals.push(proposal.to_string());
+            }
+            rest = &rest[open + close + REMEMBER_CLOSE.len()..];
+        }
+        if rest.len() == line.len() {
+            lines.push(line.to_string());
+            continue;
+        }
+        cleaned.push_str(rest);
+        if !cleaned.trim().is_empty() {
+            lines.push(cleaned.trim_end().to_string());
+        }
+    }
+    (lines.join("\n").trim().to_string(), proposals)
+}
+
+// Removes `<remember>` blocks from a streamed reply as it arrives, so the stream shows what
+// `take_proposals` stores. Text that may open or close a block is held back until it settles.
+#[derive(Default)]
+pub struct ProposalFilter {
+    pending: String,
+    inside: bool,
+    // Nothing but a block was on the current line, its line break goes with it
+    line_emptied: bool,
+    at_line_start: bool,
+}
+
+impl ProposalFilter {
+    pub fn new() -> Self {
+        ProposalFilter {
+            at_line_start: true,
+            ..Default::default()
+        }
+    }
+
+    // Text safe to show after `piece`
+    pub fn push(&mut self, piece: &str) -> String {
+        self.pending.push_str(piece);
+        let mut shown = String::new();
+        loop {
+            if self.inside {
+                // Kept whole in case the block is never closed
+                let Some(close) = self.pending.find(REMEMBER_CLOSE) else {
+                    return shown;
+                };
+                self.pending.drain(..close + REMEMBER_CLOSE.len());
+                self.inside = false;
+                self.line_emptied = self.at_line_start;
+                continue;
+            }
+
+            if self.line_emptied {
+                if self.pending.is_empty() {
+                    return shown;
+                }
+                if self.pending.starts_with('\n') {
+                    self.pending.remove(0);
+                }
+                self.line_emptied = false;
+            }
+            let (end, opens) = match self.pending.find(REMEMBER_OPEN) {
+                Some(open) => (open, true),
+                None => (partial_match_start(&self.pending, REMEMBER_OPEN), false),
+            };
+            let text: String = self.pending.drain(..end).collect();
+            self.show(&mut shown, &text);
+            if !opens {
+                return shown;
+            }
+            self.pending.drain(..REMEMBER_OPEN.len());
+            self.inside = true;
+        }
+    }
+
+    // Held back text once the reply ended, a block never closed is shown as written
+    pub fn finish(self) -> String {
+        if self.inside {
+            format!("{}{}", REMEMBER_OPEN, self.pending)
+        } else {
+            self.pending
+        }
+    }
+
+    fn show(&mut self, shown: &mut String, text: &str) {
+        if let Some(last) = text.chars().last() {
+            self.at_line_start = last == '\n';
+        }
+        shown.push_str(text);
+    }
+}
+
+fn validate(content: &str) -> AppResult<String> {
+    let content = content.trim();
+    if content.is_empty() {
+        return Err(AppError::Validation("A fact cannot be empty".into()));
+    }
+    Ok(content.to_string())
+}
+
+fn find_fact(db: &Database, id: &str) -> AppResult<Fact> {
+    dao::get_facts(db)?
+        .into_iter()
+        .find(|fact| fact.id == id)
+        .ok_or_else(|| AppError::NotFound(format!("Fact {} not found", id)))
+}
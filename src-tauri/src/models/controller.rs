@@ -4,39 +4,45 @@ use tauri::{State, Window, async_runtime::Mutex};
 
 use crate::{
     inference,
-    infrastructure::{self, context::Context, path_resolver},
+    infrastructure::{
+        context::Context,
+        database::Database,
+        error::{AppError, AppResult},
+        path_resolver,
+    },
     models::{
         self,
-        models::Model,
+        models::{Model, ModelVariant},
         service::{SET, UNSET},
     },
+    search,
 };
 
 #[tauri::command]
 pub async fn get_available_models(
     app_state: State<'_, Arc<Mutex<Context>>>,
-) -> Result<&'static HashMap<String, Model>, String> {
+) -> AppResult<HashMap<String, Model>> {
     let ctx = &mut app_state.lock().await;
-    Ok(ctx.config.get_available_models())
+    Ok(ctx.config.get_available_models().clone())
 }
 
 #[tauri::command]
-pub async fn get_default_model(
-    app_state: State<'_, Arc<Mutex<Context>>>,
-) -> Result<String, String> {
+pub async fn get_default_model(app_state: State<'_, Arc<Mutex<Context>>>) -> AppResult<String> {
     let ctx = &mut app_state.lock().await;
     Ok(ctx.config.default_model.clone())
 }
 
 #[tauri::command]
-pub async fn get_model_status(app_state: State<'_, Arc<Mutex<Context>>>) -> Result<String, String> {
+pub async fn get_model_status(app_state: State<'_, Arc<Mutex<Context>>>) -> AppResult<String> {
     let ctx = &mut app_state.lock().await;
     let name = &ctx.config.default_model;
     if name.is_empty() {
         return Ok(UNSET.into());
     }
 
-    let path = path_resolver::paths().app_local_data(&name).unwrap();
+    let path = path_resolver::paths()?
+        .app_local_data(name)
+        .map_err(AppError::Internal)?;
     if path.exists() {
         Ok(SET.into())
     } else {
@@ -47,17 +53,11 @@ pub async fn get_model_status(app_state: State<'_, Arc<Mutex<Context>>>) -> Resu
 #[tauri::command]
 pub async fn list_downloaded_models(
     app_state: State<'_, Arc<Mutex<Context>>>,
-) -> Result<Vec<String>, String> {
+) -> AppResult<Vec<String>> {
     let ctx = &mut app_state.lock().await;
-    let cfg = &ctx.config;
-    let mut found = vec![];
-    for (name, _) in cfg.get_available_models() {
-        let p = path_resolver::paths().app_local_data(&name).unwrap();
-        if p.exists() {
-            found.push(name.to_string());
-        }
-    }
-    Ok(found)
+    Ok(models::service::downloaded_models(
+        ctx.config.get_available_models(),
+    ))
 }
 
 #[tauri::command]
@@ -65,7 +65,7 @@ pub async fn download_model(
     model_name: String,
     window: Window,
     app_state: State<'_, Arc<Mutex<Context>>>,
-) -> Result<(), String> {
+) -> AppResult<()> {
     let url;
     let path;
     {
@@ -74,59 +74,112 @@ pub async fn download_model(
         url = cfg
             .get_available_models()
             .get(&model_name)
-            .ok_or("Model not found")?
+            .ok_or_else(|| model_not_found(&model_name))?
             .repo
             .clone();
-        path = path_resolver::paths()
+        path = path_resolver::paths()?
             .app_local_data(&model_name)
-            .unwrap()
+            .map_err(AppError::Internal)?
             .to_string_lossy()
             .to_string();
     }
 
-    let result = tauri::async_runtime::spawn_blocking(move || {
-        models::service::fetch_model(&url, &model_name, &path, window)
+    tauri::async_runtime::spawn_blocking(move || {
+        models::service::fetch_model(&url, &model_name, &path, &window)
     })
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?
+}
 
-    result.map_err(|e| format!("Model fetch failed: {:?}", e))?;
+#[tauri::command]
+pub async fn list_model_variants(
+    model_name: String,
+    app_state: State<'_, Arc<Mutex<Context>>>,
+) -> AppResult<Vec<ModelVariant>> {
+    let repo;
+    {
+        let ctx = &mut app_state.lock().await;
+        repo = ctx
+            .config
+            .get_available_models()
+            .get(&model_name)
+            .ok_or_else(|| model_not_found(&model_name))?
+            .repo
+            .clone();
+    }
 
-    Ok(())
+    tauri::async_runtime::spawn_blocking(move || models::service::list_variants(&repo)).await?
 }
 
 #[tauri::command]
-pub async fn delete_model(
+pub async fn download_model_variant(
     model_name: String,
+    file_name: String,
+    window: Window,
+    db: State<'_, Database>,
     app_state: State<'_, Arc<Mutex<Context>>>,
-) -> Result<(), String> {
-    let ctx = &mut app_state.lock().await;
-    let config = &mut ctx.config;
-    let path = path_resolver::paths().app_local_data(&model_name).unwrap();
-
-    if path.exists() {
-        std::fs::remove_file(&path)
-            .or_else(|_| std::fs::remove_dir_all(&path))
-            .map_err(|e| format!("Delete failed: {}", e))?;
+) -> AppResult<()> {
+    let base;
+    {
+        let ctx = &mut app_state.lock().await;
+        base = ctx
+            .config
+            .get_available_models()
+            .get(&model_name)
+            .ok_or_else(|| model_not_found(&model_name))?
+            .clone();
     }
 
-    if config.default_model == model_name {
-        config.default_model.clear();
-        ctx.inference = None;
+    let variant_name = file_name.clone();
+    let db = db.inner().clone();
+    let variant = tauri::async_runtime::spawn_blocking(move || {
+        let size = models::service::list_variants(&base.repo)?
+            .into_iter()
+            .find(|v| v.file_name == variant_name)
+            .ok_or_else(|| {
+                AppError::NotFound(format!(
+                    "Variant {} not found in {}",
+                    variant_name, base.repo
+                ))
+            })?
+            .size;
+        models::service::register_variant(&db, &base, &variant_name, size)
+    })
+    .await??;
 
-        infrastructure::service::set_config("model_name".into(), "".into())
-            .map_err(|e| e.to_string())?;
+    {
+        let ctx = &mut app_state.lock().await;
+        ctx.config.models.insert(file_name.clone(), variant);
     }
 
-    Ok(())
+    download_model(file_name, window, app_state).await
+}
+
+#[tauri::command]
+pub async fn delete_model(
+    model_name: String,
+    app_state: State<'_, Arc<Mutex<Context>>>,
+) -> AppResult<()> {
+    let mut ctx = app_state.lock().await;
+    models::service::delete_model(&model_name, &mut ctx)
 }
 
 #[tauri::command]
 pub async fn set_default_model(
     model_name: String,
     app_state: State<'_, Arc<Mutex<Context>>>,
-) -> Result<(), String> {
+) -> AppResult<()> {
     let mut ctx = app_state.lock().await;
 
-    inference::service::activate_model(model_name, &mut ctx).map_err(|e| e.to_string())
+    // An empty embedding model follows the chat model, so switching may change the vectors
+    let embedding_model = inference::service::embedding_model(&ctx.config).to_string();
+    // The default is saved before loading, a model that fails to load still changes it
+    let result = inference::service::activate_model(model_name, &mut ctx);
+    if inference::service::embedding_model(&ctx.config) != embedding_model {
+        search::service::index_in_background(app_state.inner().clone(), None);
+    }
+    result
+}
+
+fn model_not_found(model_name: &str) -> AppError {
+    AppError::NotFound(format!("Model {} not found", model_name))
 }
@@ -8,4 +8,13 @@ pub struct Model {
     pub is_thinking: bool,
     pub params: String,
     pub is_premium: bool,
+    pub quant: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModelVariant {
+    pub file_name: String,
+    pub quant: String,
+    pub size: f32,
+    pub is_downloaded: bool,
 }
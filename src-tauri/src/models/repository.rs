@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+
+use rusqlite::params;
+
+use crate::{
+    infrastructure::{database::Database, error::AppResult},
+    models::models::Model,
+};
+
+pub fn get_catalog_entries(db: &Database) -> AppResult<HashMap<String, Model>> {
+    let conn = db.get_conn()?;
+    let mut stmt = conn.prepare("SELECT file_name, body FROM model_catalog")?;
+    let rows = stmt.query_map([], |row| {
+        let file_name: String = row.get(0)?;
+        let body_json: String = row.get(1)?;
+        let model: Model = serde_json::from_str(&body_json).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e))
+        })?;
+        Ok((file_name, model))
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+pub fn add_catalog_entry(db: &Database, file_name: &str, model: &Model) -> AppResult<()> {
+    let conn = db.get_conn()?;
+    let body_json = serde_json::to_string(model)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    conn.execute(
+        "INSERT INTO model_catalog (file_name, body) VALUES (?1, ?2)
+            ON CONFLICT(file_name) DO UPDATE SET body = excluded.body",
+        params![file_name, body_json],
+    )?;
+    Ok(())
+}
+
+pub fn delete_catalog_entry(db: &Database, file_name: &str) -> AppResult<()> {
+    let conn = db.get_conn()?;
+    conn.execute(
+        "DELETE FROM model_catalog WHERE file_name = ?1",
+        params![file_name],
+    )?;
+    Ok(())
+}
@@ -1,20 +1,166 @@
-use anyhow::{Context, Result};
 use reqwest::blocking::Client;
+use std::collections::HashMap;
 use std::fs;
 use std::io::{Read, Write};
 use std::path::Path;
-use tauri::{Emitter, Window};
 use tempfile::NamedTempFile;
 
+use crate::inference;
+use crate::infrastructure::database::Database;
+use crate::infrastructure::error::{AppError, AppResult};
+use crate::infrastructure::events::{Event, EventSink};
+use crate::infrastructure::{consts, context, path_resolver, service as config_service};
+use crate::models::models::{Model, ModelVariant};
+use crate::models::repository as dao;
+use crate::search;
+
 pub const SET: &str = "SET";
 pub const UNSET: &str = "UNSET";
 
+#[derive(serde::Deserialize)]
+struct HfTreeEntry {
+    #[serde(rename = "type")]
+    kind: String,
+    path: String,
+    size: u64,
+    lfs: Option<HfLfsInfo>,
+}
+
+#[derive(serde::Deserialize)]
+struct HfLfsInfo {
+    size: u64,
+}
+
+fn http_client() -> AppResult<Client> {
+    Client::builder()
+        .user_agent("breve-model-fetcher/0.1")
+        .build()
+        .map_err(AppError::from)
+}
+
+// Built-in catalog merged with the variants the user has added
+pub fn load_catalog(db: &Database) -> HashMap<String, Model> {
+    let mut catalog = consts::default_models().clone();
+    match dao::get_catalog_entries(db) {
+        Ok(entries) => catalog.extend(entries),
+        Err(e) => eprintln!("Failed to load model catalog: {}", e),
+    }
+    catalog
+}
+
+// Catalog entries whose file is present on disk
+pub fn downloaded_models(catalog: &HashMap<String, Model>) -> Vec<String> {
+    catalog
+        .keys()
+        .filter(|name| {
+            path_resolver::paths()
+                .ok()
+                .and_then(|paths| paths.app_local_data(name).ok())
+                .is_some_and(|p| p.exists())
+        })
+        .cloned()
+        .collect()
+}
+
+// Lists the single-file GGUF quantizations published in a Hugging Face repo
+pub fn list_variants(repo: &str) -> AppResult<Vec<ModelVariant>> {
+    let tree_url = format!("https://huggingface.co/api/models/{}/tree/main", repo);
+
+    let resp = http_client()?.get(&tree_url).send()?;
+
+    if !resp.status().is_success() {
+        return Err(AppError::Network(format!(
+            "tree request failed (status: {})",
+            resp.status()
+        )));
+    }
+
+    let entries: Vec<HfTreeEntry> = resp.json()?;
+
+    let mut variants: Vec<ModelVariant> = entries
+        .into_iter()
+        .filter(|e| e.kind == "file" && is_model_file(&e.path))
+        .map(|e| {
+            let bytes = e.lfs.map(|lfs| lfs.size).unwrap_or(e.size);
+            let is_downloaded = path_resolver::paths()
+                .ok()
+                .and_then(|paths| paths.app_local_data(&e.path).ok())
+                .is_some_and(|p| p.exists());
+            ModelVariant {
+                quant: parse_quant(&e.path).unwrap_or_else(|| "Unknown".to_string()),
+                size: bytes as f32 / (1024.0 * 1024.0),
+                file_name: e.path,
+                is_downloaded,
+            }
+        })
+        .collect();
+
+    variants.sort_by(|a, b| a.size.total_cmp(&b.size));
+    Ok(variants)
+}
+
+// Builds a catalog entry for `file_name` from the model it was listed under and persists it
+pub fn register_variant(
+    db: &Database,
+    base: &Model,
+    file_name: &str,
+    size: f32,
+) -> AppResult<Model> {
+    let quant = parse_quant(file_name).unwrap_or_else(|| "Unknown".to_string());
+    let base_name = base
+        .name
+        .strip_suffix(&format!(" ({})", base.quant))
+        .unwrap_or(&base.name);
+    let model = Model {
+        name: format!("{} ({})", base_name, quant),
+        repo: base.repo.clone(),
+        size,
+        is_thinking: base.is_thinking,
+        params: base.params.clone(),
+        is_premium: base.is_premium,
+        quant,
+    };
+    dao::add_catalog_entry(db, file_name, &model)?;
+    Ok(model)
+}
+
+// Skips sub-directories, split shards and multimodal projectors, none of which load standalone
+fn is_model_file(path: &str) -> bool {
+    let lower = path.to_lowercase();
+    lower.ends_with(".gguf")
+        && !path.contains('/')
+        && !lower.contains("mmproj")
+        && !lower.contains("-of-0")
+}
+
+// Extracts the quantization tag, e.g. `Q4_K_M` from `Llama-3.2-3B-Instruct-Q4_K_M.gguf`
+pub fn parse_quant(file_name: &str) -> Option<String> {
+    let stem = file_name
+        .strip_suffix(".gguf")
+        .or_else(|| file_name.strip_suffix(".GGUF"))
+        .unwrap_or(file_name);
+
+    stem.rsplit(['-', '.'])
+        .map(|part| part.to_uppercase())
+        .find(|part| {
+            let digit_after = |prefix: &str| {
+                part.strip_prefix(prefix)
+                    .and_then(|rest| rest.chars().next())
+                    .is_some_and(|c| c.is_ascii_digit())
+            };
+            digit_after("Q")
+                || digit_after("IQ")
+                || digit_after("TQ")
+                || matches!(part.as_str(), "F16" | "BF16" | "F32")
+        })
+}
+
 pub fn fetch_model(
     model_url: &str,
     model_name: &str,
     model_path: &str,
-    window: Window,
-) -> Result<()> {
+    sink: &dyn EventSink,
+) -> AppResult<()> {
     let dest_path = Path::new(&model_path);
     if dest_path.exists() {
         return Ok(());
@@ -27,23 +173,13 @@ pub fn fetch_model(
         model_url, model_name
     );
 
-    let client = Client::builder()
-        .user_agent("breve-model-fetcher/0.1")
-        .build()
-        .context("failed to build HTTP client")?;
-
-    let mut resp = client
-        .get(&raw_url)
-        .send()
-        .context("failed to send download request")?;
+    let mut resp = http_client()?.get(&raw_url).send()?;
 
     if !resp.status().is_success() {
-        // Fallback: try hf_hub's get (no progress) to keep previous behavior
-        eprintln!(
-            "Raw download failed (status: {}), falling back to hf_hub get",
+        return Err(AppError::Network(format!(
+            "download failed (status: {})",
             resp.status()
-        );
-        return Ok(());
+        )));
     }
 
     // Get content length if provided
@@ -54,45 +190,121 @@ pub fn fetch_model(
         .and_then(|s| s.parse::<u64>().ok());
 
     // Create temp file while streaming
-    let mut tmpfile = NamedTempFile::new().context("failed to create temp file")?;
+    let mut tmpfile = NamedTempFile::new()?;
     let mut downloaded: u64 = 0;
     let mut buffer = [0u8; 8 * 1024];
 
     loop {
         let n = resp
             .read(&mut buffer)
-            .context("failed to read from response stream")?;
+            .map_err(|e| AppError::Network(format!("download interrupted: {}", e)))?;
         if n == 0 {
             break;
         }
-        tmpfile
-            .write_all(&buffer[..n])
-            .context("failed to write to temp file")?;
+        tmpfile.write_all(&buffer[..n])?;
         downloaded += n as u64;
 
         // Emit progress if total_size known, else emit bytes downloaded as fallback (as percentage 0..100 scaled)
         if let Some(total) = total_size {
             let pct = (downloaded as f64 / total as f64) * 100.0;
-            let _ = window.emit("download-progress", pct);
+            sink.emit(Event::DownloadProgress(pct));
         } else {
             // Without total, emit increasing values capped to 100 by using a heuristic
             let pct = (downloaded as f64 / (1024.0 * 1024.0 * 1024.0)) * 100.0; // assume up to 1GB
             let pct = pct.min(99.9);
-            let _ = window.emit("download-progress", pct);
+            sink.emit(Event::DownloadProgress(pct));
         }
     }
 
     // Ensure parent directory exists
     if let Some(parent) = dest_path.parent() {
-        fs::create_dir_all(parent).context("failed to create model_path directory")?;
+        fs::create_dir_all(parent)?;
     }
 
     // Persist temp file to destination path
     tmpfile
-        .persist(&dest_path)
-        .context("failed to move downloaded model to model_path")?;
+        .persist(dest_path)
+        .map_err(|e| AppError::from(e.error))?;
 
     // Final progress emit (100%) and end boolean
-    let _ = window.emit("download-progress", 100.0);
+    sink.emit(Event::DownloadProgress(100.0));
+    Ok(())
+}
+
+// Removes the model file with what was kept for it, and unloads it if it was the active model
+pub fn delete_model(model_name: &str, ctx: &mut context::Context) -> AppResult<()> {
+    let path = path_resolver::paths()?
+        .app_local_data(model_name)
+        .map_err(AppError::Internal)?;
+
+    if path.exists() {
+        fs::remove_file(&path).or_else(|_| fs::remove_dir_all(&path))?;
+    }
+
+    if ctx.config.default_model == model_name {
+        ctx.config.default_model.clear();
+        ctx.inference = None;
+
+        config_service::set_config(&ctx.db, "model_name".into(), "".into())?;
+    }
+    let mut embedder = inference::service::lock_embedder(&ctx.embedder);
+    if embedder
+        .as_ref()
+        .is_some_and(|embedder| embedder.config.default_model == model_name)
+    {
+        *embedder = None;
+    }
+    drop(embedder);
+
+    // Built-in entries stay listed so the model can be downloaded again
+    dao::delete_catalog_entry(&ctx.db, model_name)?;
+    if !consts::default_models().contains_key(model_name) {
+        ctx.config.models.remove(model_name);
+    }
+    if ctx.config.chat_templates.contains_key(model_name) {
+        inference::service::set_chat_template(model_name, None, ctx)?;
+    }
+    // Knowledge base documents keep their chunks, they are embedded again by the next model
+    search::repository::delete_model_embeddings(&ctx.db, model_name)?;
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quant_is_read_from_the_file_name() {
+        assert_eq!(
+            parse_quant("Llama-3.2-3B-Instruct-Q4_K_M.gguf").as_deref(),
+            Some("Q4_K_M")
+        );
+        assert_eq!(
+            parse_quant("SmolLM2-360M-Instruct.Q4_K_M.gguf").as_deref(),
+            Some("Q4_K_M")
+        );
+        assert_eq!(parse_quant("phi-4-iq2_xs.GGUF").as_deref(), Some("IQ2_XS"));
+        assert_eq!(
+            parse_quant("gemma-3-1b-it-bf16.gguf").as_deref(),
+            Some("BF16")
+        );
+    }
+
+    #[test]
+    fn unknown_quant_is_none() {
+        assert_eq!(parse_quant("Qwen3-1.7B.gguf"), None);
+        assert_eq!(parse_quant("model-final.gguf"), None);
+        assert_eq!(parse_quant(""), None);
+    }
+
+    #[test]
+    fn only_standalone_gguf_files_are_models() {
+        assert!(is_model_file("Llama-3.2-1B-Instruct-Q4_K_M.gguf"));
+        assert!(is_model_file("Llama-3.2-1B-Instruct-Q4_K_M.GGUF"));
+        assert!(!is_model_file("README.md"));
+        assert!(!is_model_file("older/model-Q4_K_M.gguf"));
+        assert!(!is_model_file("mmproj-model-f16.gguf"));
+        assert!(!is_model_file("model-Q8_0-00001-of-00002.gguf"));
+    }
+}
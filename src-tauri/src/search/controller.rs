@@ -0,0 +1,24 @@
+use std::sync::Arc;
+
+use tauri::{State, async_runtime::Mutex};
+
+use crate::{
+    infrastructure::{consts, context::Context, error::AppResult},
+    search::{models::MessageMatch, service},
+};
+
+#[tauri::command]
+pub async fn semantic_search(
+    query: String,
+    limit: Option<usize>,
+    app_state: State<'_, Arc<Mutex<Context>>>,
+) -> AppResult<Vec<MessageMatch>> {
+    let app_state_handle: Arc<Mutex<Context>> = app_state.inner().clone();
+    let limit = limit.unwrap_or(consts::SEARCH_DEFAULT_LIMIT);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut ctx = tauri::async_runtime::block_on(app_state_handle.lock());
+        service::semantic_search(&query, limit, &mut ctx)
+    })
+    .await?
+}
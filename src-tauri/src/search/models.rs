@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+// Stored message similar to a search query
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MessageMatch {
+    pub conversation_id: String,
+    pub conversation_title: String,
+    // Index of the message in the conversation body
+    pub position: u64,
+    pub role: String,
+    pub content: String,
+    pub score: f32,
+}
@@ -0,0 +1,103 @@
+use std::collections::HashSet;
+
+use rusqlite::params;
+
+use crate::{
+    inference::models::{vector_from_bytes, vector_to_bytes},
+    infrastructure::{database::Database, error::AppResult},
+};
+
+pub fn add_embeddings(
+    db: &Database,
+    conversation_id: &str,
+    embedding_model: &str,
+    embeddings: &[(u64, Vec<f32>)],
+) -> AppResult<()> {
+    let mut conn = db.get_conn()?;
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR REPLACE INTO message_embeddings
+                (conversation_id, position, embedding_model, embedding)
+                VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for (position, vector) in embeddings {
+            stmt.execute(params![
+                conversation_id,
+                position,
+                embedding_model,
+                vector_to_bytes(vector)
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn get_indexed_positions(
+    db: &Database,
+    conversation_id: &str,
+    embedding_model: &str,
+) -> AppResult<HashSet<u64>> {
+    let conn = db.get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT position FROM message_embeddings
+            WHERE conversation_id = ?1 AND embedding_model = ?2",
+    )?;
+    let positions = stmt
+        .query_map(params![conversation_id, embedding_model], |row| row.get(0))?
+        .collect::<Result<HashSet<u64>, _>>()?;
+    Ok(positions)
+}
+
+// Every embedding made by `embedding_model` as (conversation id, position, vector)
+pub fn get_embeddings(
+    db: &Database,
+    embedding_model: &str,
+) -> AppResult<Vec<(String, u64, Vec<f32>)>> {
+    let conn = db.get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT conversation_id, position, embedding FROM message_embeddings
+            WHERE embedding_model = ?1",
+    )?;
+    let embeddings = stmt
+        .query_map(params![embedding_model], |row| {
+            let embedding: Vec<u8> = row.get(2)?;
+            Ok((row.get(0)?, row.get(1)?, vector_from_bytes(&embedding)))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(embeddings)
+}
+
+// Vectors from another model are not comparable with new ones, returns how many of the
+// conversation's were dropped
+pub fn delete_other_models(
+    db: &Database,
+    conversation_id: &str,
+    embedding_model: &str,
+) -> AppResult<usize> {
+    let conn = db.get_conn()?;
+    let deleted = conn.execute(
+        "DELETE FROM message_embeddings WHERE conversation_id = ?1 AND embedding_model != ?2",
+        params![conversation_id, embedding_model],
+    )?;
+    Ok(deleted)
+}
+
+pub fn delete_model_embeddings(db: &Database, embedding_model: &str) -> AppResult<()> {
+    let conn = db.get_conn()?;
+    conn.execute(
+        "DELETE FROM message_embeddings WHERE embedding_model = ?1",
+        params![embedding_model],
+    )?;
+    Ok(())
+}
+
+pub fn delete_conversation_embeddings(db: &Database, conversation_id: &str) -> AppResult<()> {
+    let conn = db.get_conn()?;
+    conn.execute(
+        "DELETE FROM message_embeddings WHERE conversation_id = ?1",
+        params![conversation_id],
+    )?;
+    Ok(())
+}
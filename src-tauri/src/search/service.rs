@@ -0,0 +1,187 @@
+use std::{collections::HashMap, sync::Arc};
+
+use tauri::async_runtime::Mutex;
+
+use crate::{
+    conversation::{self, models::Conversation},
+    inference::{self, models::similarity},
+    infrastructure::{
+        consts,
+        context::Context,
+        database::Database,
+        error::{AppError, AppResult},
+    },
+    knowledge,
+    search::{models::MessageMatch, repository as dao},
+};
+
+// Embeds the messages of a conversation that are not indexed yet, returns how many were added
+pub fn index_conversation(conversation_id: &str, ctx: &mut Context) -> AppResult<usize> {
+    let db = ctx.db.clone();
+    let model = inference::service::embedding_model(&ctx.config).to_string();
+    index(&db, &model, &[conversation_id.to_string()], |texts| {
+        inference::service::embed(texts, ctx)
+    })
+}
+
+// Brings every conversation up to date, which re-embeds them all after the model changed.
+// Knowledge base documents from a previous model are embedded again along with them.
+pub fn index_all(ctx: &mut Context) -> AppResult<usize> {
+    let db = ctx.db.clone();
+    let model = inference::service::embedding_model(&ctx.config).to_string();
+    let ids = conversation::service::get_conversation_ids(&db)?;
+    let mut embed = |texts: &[String]| inference::service::embed(texts, ctx);
+    let added = index(&db, &model, &ids, &mut embed)?;
+    knowledge::service::reembed_documents(&db, &model, &mut embed)?;
+    Ok(added)
+}
+
+// Indexes one conversation, or all of them, on a blocking thread. The context is only locked
+// to read the settings and, when the chat model also embeds, for one batch at a time, so
+// replies never wait on a whole run.
+pub fn index_in_background(state: Arc<Mutex<Context>>, conversation_id: Option<String>) {
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Err(e) = index_shared(&state, conversation_id) {
+            eprintln!("Failed to index messages: {}", e);
+        }
+    });
+}
+
+fn index_shared(state: &Mutex<Context>, conversation_id: Option<String>) -> AppResult<usize> {
+    let (db, config, embedder) = {
+        let ctx = tauri::async_runtime::block_on(state.lock());
+        (ctx.db.clone(), ctx.config.clone(), ctx.embedder.clone())
+    };
+    let model = inference::service::embedding_model(&config).to_string();
+    let everything = conversation_id.is_none();
+    let ids = match conversation_id {
+        Some(id) => vec![id],
+        None => conversation::service::get_conversation_ids(&db)?,
+    };
+
+    let mut embed = |texts: &[String]| {
+        if !inference::service::embeds_with_chat_model(&config) {
+            return inference::service::embed_separately(&embedder, &config, texts);
+        }
+        let mut ctx = tauri::async_runtime::block_on(state.lock());
+        // Vectors are stored under `model`, a run outdated by a settings change stops here
+        if inference::service::embedding_model(&ctx.config) != model {
+            return Err(AppError::Validation(
+                "Embedding model changed while indexing".into(),
+            ));
+        }
+        inference::service::embed(texts, &mut ctx)
+    };
+    let added = index(&db, &model, &ids, &mut embed)?;
+    if everything {
+        knowledge::service::reembed_documents(&db, &model, &mut embed)?;
+    }
+    Ok(added)
+}
+
+fn index(
+    db: &Database,
+    model: &str,
+    conversation_ids: &[String],
+    mut embed: impl FnMut(&[String]) -> AppResult<Vec<Vec<f32>>>,
+) -> AppResult<usize> {
+    if model.is_empty() {
+        return Ok(0);
+    }
+    let mut added = 0;
+    for conversation_id in conversation_ids {
+        // Only the conversations being indexed lose their old vectors, the others keep theirs
+        // until they are indexed with `model` too
+        dao::delete_other_models(db, conversation_id, model)?;
+        let pending = pending_messages(db, conversation_id, model)?;
+        for batch in pending.chunks(consts::KB_EMBED_BATCH) {
+            let texts: Vec<String> = batch.iter().map(|(_, content)| content.clone()).collect();
+            let vectors = embed(&texts)?;
+            let embeddings: Vec<(u64, Vec<f32>)> = batch
+                .iter()
+                .map(|(position, _)| *position)
+                .zip(vectors)
+                .collect();
+            dao::add_embeddings(db, conversation_id, model, &embeddings)?;
+        }
+        added += pending.len();
+    }
+    Ok(added)
+}
+
+// Messages `model` has not embedded yet, as (position, content)
+fn pending_messages(
+    db: &Database,
+    conversation_id: &str,
+    model: &str,
+) -> AppResult<Vec<(u64, String)>> {
+    let Some(conversation) = conversation::service::get_conversation(db, conversation_id)? else {
+        return Ok(Vec::new());
+    };
+    let indexed = dao::get_indexed_positions(db, conversation_id, model)?;
+    Ok(conversation
+        .body
+        .iter()
+        .enumerate()
+        .map(|(position, msg)| (position as u64, msg))
+        .filter(|(position, msg)| {
+            msg.role != "system" && !msg.content.trim().is_empty() && !indexed.contains(position)
+        })
+        .map(|(position, msg)| (position, msg.content.clone()))
+        .collect())
+}
+
+// Stored messages ranked by similarity to `query`
+pub fn semantic_search(
+    query: &str,
+    limit: usize,
+    ctx: &mut Context,
+) -> AppResult<Vec<MessageMatch>> {
+    let model = inference::service::embedding_model(&ctx.config).to_string();
+    if model.is_empty() {
+        return Err(AppError::ModelLoadFailed(
+            "No embedding model is set".into(),
+        ));
+    }
+
+    let query = inference::service::embed(&[query.to_string()], ctx)?
+        .pop()
+        .ok_or_else(|| AppError::Internal("Query was not embedded".into()))?;
+
+    let mut scored: Vec<(String, u64, f32)> = dao::get_embeddings(&ctx.db, &model)?
+        .into_iter()
+        .map(|(id, position, vector)| (id, position, similarity(&query, &vector)))
+        .collect();
+    scored.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+    let mut conversations: HashMap<String, Option<Conversation>> = HashMap::new();
+    let mut matches = Vec::new();
+    for (id, position, score) in scored {
+        if matches.len() >= limit {
+            break;
+        }
+        if !conversations.contains_key(&id) {
+            let conversation = conversation::service::get_conversation(&ctx.db, &id)?;
+            conversations.insert(id.clone(), conversation);
+        }
+        // Skips embeddings left behind by a conversation that no longer has that message
+        let Some(msg) = conversations[&id]
+            .as_ref()
+            .and_then(|conv| conv.body.get(position as usize))
+        else {
+            continue;
+        };
+        matches.push(MessageMatch {
+            conversation_id: id.clone(),
+            conversation_title: conversations[&id]
+                .as_ref()
+                .map(|conv| conv.title.clone())
+                .unwrap_or_default(),
+            position,
+            role: msg.role.clone(),
+            content: msg.content.clone(),
+            score,
+        });
+    }
+    Ok(matches)
+}
@@ -0,0 +1,29 @@
+use std::sync::Arc;
+
+use tauri::{State, async_runtime::Mutex};
+
+use crate::{
+    infrastructure::{context::Context, database::Database, error::AppResult},
+    server::{
+        models::{ServerConfig, ServerStatus},
+        service,
+    },
+};
+
+#[tauri::command]
+pub async fn get_api_server(db: State<'_, Database>) -> AppResult<ServerStatus> {
+    Ok(service::status(&db))
+}
+
+#[tauri::command]
+pub async fn set_api_server(
+    config: ServerConfig,
+    db: State<'_, Database>,
+    app_state: State<'_, Arc<Mutex<Context>>>,
+) -> AppResult<ServerStatus> {
+    let db = db.inner().clone();
+    let app_state_handle: Arc<Mutex<Context>> = app_state.inner().clone();
+
+    tauri::async_runtime::spawn_blocking(move || service::apply(&db, config, app_state_handle))
+        .await?
+}
@@ -0,0 +1,97 @@
+use std::io::{self, Read, Write};
+
+use serde::de::DeserializeOwned;
+use tiny_http::{Header, Request, Response, StatusCode};
+
+use crate::infrastructure::error::{AppError, AppResult};
+
+pub fn header(name: &str, value: &str) -> AppResult<Header> {
+    Header::from_bytes(name.as_bytes(), value.as_bytes())
+        .map_err(|_| AppError::Internal(format!("Invalid {} header", name)))
+}
+
+pub fn respond_json(request: Request, status: u16, body: &serde_json::Value) {
+    respond(request, status, body.to_string(), "application/json");
+}
+
+pub fn respond_text(request: Request, status: u16, body: &str) {
+    respond(
+        request,
+        status,
+        body.to_string(),
+        "text/plain; charset=utf-8",
+    );
+}
+
+fn respond(request: Request, status: u16, body: String, content_type: &str) {
+    let mut response = Response::from_string(body).with_status_code(StatusCode(status));
+    // A response without its content type still reaches the client
+    match header("Content-Type", content_type) {
+        Ok(header) => response.add_header(header),
+        Err(e) => eprintln!("API response header skipped: {}", e),
+    }
+    if let Err(e) = request.respond(response) {
+        eprintln!("API response failed: {}", e);
+    }
+}
+
+pub fn read_json<T: DeserializeOwned>(request: &mut Request) -> AppResult<T> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .read_to_string(&mut body)
+        .map_err(|e| AppError::Validation(format!("Failed to read request body: {}", e)))?;
+    serde_json::from_str(&body)
+        .map_err(|e| AppError::Validation(format!("Invalid request body: {}", e)))
+}
+
+pub fn status(error: &AppError) -> u16 {
+    match error {
+        AppError::NotFound(_) | AppError::ModelNotDownloaded(_) => 404,
+        AppError::Validation(_) | AppError::ContextOverflow(_) | AppError::OutputTruncated(_) => {
+            400
+        }
+        AppError::ModelLoadFailed(_) => 503,
+        AppError::Database(_) | AppError::Network(_) | AppError::Internal(_) => 500,
+    }
+}
+
+pub fn bearer_token(request: &Request) -> Option<&str> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .and_then(|h| h.value.as_str().strip_prefix("Bearer "))
+}
+
+// Writes the response by hand so every event reaches the client as soon as it is
+// produced, tiny_http's own chunked encoder buffers output until the body ends
+pub struct EventStream {
+    writer: Box<dyn Write + Send>,
+}
+
+impl EventStream {
+    pub fn open(request: Request, content_type: &str) -> io::Result<EventStream> {
+        let mut writer = request.into_writer();
+        write!(
+            writer,
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nCache-Control: no-cache\r\nTransfer-Encoding: chunked\r\n\r\n",
+            content_type
+        )?;
+        writer.flush()?;
+        Ok(EventStream { writer })
+    }
+
+    pub fn send(&mut self, data: &str) -> io::Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        write!(self.writer, "{:x}\r\n{}\r\n", data.len(), data)?;
+        self.writer.flush()
+    }
+
+    pub fn close(mut self) -> io::Result<()> {
+        self.writer.write_all(b"0\r\n\r\n")?;
+        self.writer.flush()
+    }
+}
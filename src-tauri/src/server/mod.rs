@@ -0,0 +1,6 @@
+pub mod controller;
+pub mod http;
+pub mod models;
+pub mod ollama;
+pub mod openai;
+pub mod service;
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{inference::models::GenerationStats, infrastructure::consts};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ServerConfig {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub token: String,
+    // Most Ollama clients cannot send an API token
+    #[serde(default = "default_ollama_requires_token")]
+    pub ollama_requires_token: bool,
+}
+
+fn default_ollama_requires_token() -> bool {
+    true
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            enabled: false,
+            host: consts::DEFAULT_API_HOST.to_string(),
+            port: consts::DEFAULT_API_PORT,
+            token: String::new(),
+            ollama_requires_token: default_ollama_requires_token(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ServerStatus {
+    pub config: ServerConfig,
+    pub running: bool,
+    pub address: Option<String>,
+}
+
+// Sampling settings an API request may override for itself
+#[derive(Clone, Debug, Default)]
+pub struct SamplingOptions {
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u64>,
+    // Added to the configured stop sequences
+    pub stop: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Completion {
+    pub model: String,
+    pub content: String,
+    pub stats: GenerationStats,
+    // Either `stop` or `length`, as reported by the OpenAI API
+    pub finish_reason: &'static str,
+}
@@ -0,0 +1,303 @@
+use std::{sync::Arc, time::UNIX_EPOCH};
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tauri::async_runtime::Mutex;
+use tiny_http::Request;
+
+use crate::{
+    conversation::models::Conversation,
+    infrastructure::{clock, context::Context, path_resolver},
+    models::models::Model,
+    server::{
+        http::{self, EventStream},
+        models::{Completion, SamplingOptions},
+        service::{self, Prompt},
+    },
+};
+
+// Ollama reports this version to clients that gate features on it
+const COMPATIBLE_VERSION: &str = "0.5.0";
+
+#[derive(Deserialize, Default)]
+struct Options {
+    temperature: Option<f32>,
+    num_predict: Option<i64>,
+    #[serde(default)]
+    stop: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    role: String,
+    #[serde(default)]
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ChatRequest {
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    messages: Vec<ChatMessage>,
+    stream: Option<bool>,
+    #[serde(default)]
+    options: Options,
+}
+
+#[derive(Deserialize)]
+struct GenerateRequest {
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    prompt: String,
+    system: Option<String>,
+    #[serde(default)]
+    raw: bool,
+    stream: Option<bool>,
+    #[serde(default)]
+    options: Options,
+}
+
+#[derive(Deserialize)]
+struct ShowRequest {
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    name: String,
+}
+
+impl Options {
+    fn sampling(&self) -> SamplingOptions {
+        SamplingOptions {
+            temperature: self.temperature,
+            // Negative values mean unlimited in Ollama
+            max_tokens: self.num_predict.filter(|n| *n > 0).map(|n| n as u64),
+            stop: self.stop.clone(),
+        }
+    }
+}
+
+pub fn respond_error(request: Request, status: u16, message: &str) {
+    http::respond_json(request, status, &json!({ "error": message }));
+}
+
+pub fn version(request: Request) {
+    http::respond_json(request, 200, &json!({ "version": COMPATIBLE_VERSION }));
+}
+
+pub fn tags(request: Request, state: &Arc<Mutex<Context>>) {
+    let models: Vec<Value> = service::downloaded_models(state)
+        .into_iter()
+        .map(|(name, model)| {
+            let (size, modified) = file_info(&name);
+            json!({
+                "name": name,
+                "model": name,
+                "modified_at": modified,
+                "size": size,
+                "digest": "",
+                "details": details(&model),
+            })
+        })
+        .collect();
+    http::respond_json(request, 200, &json!({ "models": models }));
+}
+
+pub fn show(mut request: Request, state: &Arc<Mutex<Context>>) {
+    let body: ShowRequest = match http::read_json(&mut request) {
+        Ok(body) => body,
+        Err(e) => return respond_error(request, 400, e.message()),
+    };
+    let wanted = if body.model.is_empty() {
+        body.name
+    } else {
+        body.model
+    };
+    let wanted = wanted
+        .strip_suffix(":latest")
+        .unwrap_or(&wanted)
+        .to_string();
+
+    match service::downloaded_models(state)
+        .into_iter()
+        .find(|(name, _)| *name == wanted)
+    {
+        Some((name, model)) => {
+            let (_, modified) = file_info(&name);
+            http::respond_json(
+                request,
+                200,
+                &json!({
+                    "modelfile": "",
+                    "parameters": "",
+                    "template": "",
+                    "details": details(&model),
+                    "model_info": { "general.basename": model.name },
+                    "capabilities": ["completion"],
+                    "modified_at": modified,
+                }),
+            )
+        }
+        None => respond_error(request, 404, &format!("model '{}' not found", wanted)),
+    }
+}
+
+pub fn chat(mut request: Request, state: &Arc<Mutex<Context>>) {
+    let body: ChatRequest = match http::read_json(&mut request) {
+        Ok(body) => body,
+        Err(e) => return respond_error(request, 400, e.message()),
+    };
+    let model = match service::ensure_model(state, &body.model) {
+        Ok(model) => model,
+        Err(e) => return respond_error(request, http::status(&e), e.message()),
+    };
+
+    let mut conv = Conversation::new(String::new(), String::new());
+    for message in &body.messages {
+        conv.add_message(&message.role, &message.content);
+    }
+
+    respond(
+        request,
+        state,
+        &model,
+        Prompt::Chat(conv),
+        body.options.sampling(),
+        body.stream.unwrap_or(true),
+        |content| json!({ "message": { "role": "assistant", "content": content } }),
+    );
+}
+
+pub fn generate(mut request: Request, state: &Arc<Mutex<Context>>) {
+    let body: GenerateRequest = match http::read_json(&mut request) {
+        Ok(body) => body,
+        Err(e) => return respond_error(request, 400, e.message()),
+    };
+    let model = match service::ensure_model(state, &body.model) {
+        Ok(model) => model,
+        Err(e) => return respond_error(request, http::status(&e), e.message()),
+    };
+
+    // An empty prompt only asks for the model to be loaded
+    if body.prompt.is_empty() {
+        return http::respond_json(
+            request,
+            200,
+            &json!({
+                "model": model,
+                "created_at": created_at(),
+                "response": "",
+                "done": true,
+                "done_reason": "load",
+            }),
+        );
+    }
+
+    let prompt = if body.raw {
+        Prompt::Text(body.prompt)
+    } else {
+        let mut conv = Conversation::new(String::new(), String::new());
+        if let Some(system) = &body.system {
+            conv.add_message("system", system);
+        }
+        conv.add_message("user", &body.prompt);
+        Prompt::Chat(conv)
+    };
+
+    respond(
+        request,
+        state,
+        &model,
+        prompt,
+        body.options.sampling(),
+        body.stream.unwrap_or(true),
+        |content| json!({ "response": content }),
+    );
+}
+
+// Ollama streams by default, as newline-delimited JSON objects
+fn respond(
+    request: Request,
+    state: &Arc<Mutex<Context>>,
+    model: &str,
+    prompt: Prompt,
+    options: SamplingOptions,
+    stream: bool,
+    content: impl Fn(&str) -> Value,
+) {
+    let with_header = |mut value: Value, done: bool| {
+        value["model"] = json!(model);
+        value["created_at"] = json!(created_at());
+        value["done"] = json!(done);
+        value
+    };
+
+    if !stream {
+        return match service::complete(state, prompt, options, |_| {}) {
+            Ok(completion) => {
+                let mut value = with_header(content(&completion.content), true);
+                add_final_stats(&mut value, &completion);
+                http::respond_json(request, 200, &value)
+            }
+            Err(e) => respond_error(request, http::status(&e), e.message()),
+        };
+    }
+
+    let Ok(mut events) = EventStream::open(request, "application/x-ndjson") else {
+        return;
+    };
+    let result = service::complete(state, prompt, options, |piece| {
+        let _ = events.send(&format!("{}\n", with_header(content(piece), false)));
+    });
+    let last = match result {
+        Ok(completion) => {
+            let mut value = with_header(content(""), true);
+            add_final_stats(&mut value, &completion);
+            value
+        }
+        Err(e) => json!({ "error": e.message() }),
+    };
+    let _ = events.send(&format!("{}\n", last));
+    let _ = events.close();
+}
+
+// Durations are reported in nanoseconds
+fn add_final_stats(value: &mut Value, completion: &Completion) {
+    let stats = &completion.stats;
+    let nanos = |ms: f64| (ms * 1_000_000.0) as u64;
+    value["done_reason"] = json!(completion.finish_reason);
+    value["total_duration"] = json!(nanos(stats.prompt_ms + stats.generation_ms));
+    value["load_duration"] = json!(0);
+    value["prompt_eval_count"] = json!(stats.prompt_tokens);
+    value["prompt_eval_duration"] = json!(nanos(stats.prompt_ms));
+    value["eval_count"] = json!(stats.generated_tokens);
+    value["eval_duration"] = json!(nanos(stats.generation_ms));
+}
+
+fn details(model: &Model) -> Value {
+    json!({
+        "format": "gguf",
+        "family": model.name,
+        "parameter_size": model.params,
+        "quantization_level": model.quant,
+    })
+}
+
+fn file_info(file_name: &str) -> (u64, String) {
+    let metadata = path_resolver::paths()
+        .ok()
+        .and_then(|paths| paths.app_local_data(file_name).ok())
+        .and_then(|path| std::fs::metadata(path).ok());
+    let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+    let modified = metadata
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    (size, clock::rfc3339(modified))
+}
+
+fn created_at() -> String {
+    clock::rfc3339(clock::unix_now())
+}
@@ -0,0 +1,262 @@
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tauri::async_runtime::Mutex;
+use tiny_http::Request;
+use uuid::Uuid;
+
+use crate::{
+    conversation::models::Conversation,
+    infrastructure::context::Context,
+    server::{
+        http::{self, EventStream},
+        models::{Completion, SamplingOptions},
+        service::{self, Prompt},
+    },
+};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+// `stop` is either one string or a list of them
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Stop {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Stop {
+    fn into_vec(stop: Option<Stop>) -> Vec<String> {
+        match stop {
+            Some(Stop::One(stop)) => vec![stop],
+            Some(Stop::Many(stops)) => stops,
+            None => Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ContentPart {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    role: String,
+    #[serde(default)]
+    content: Option<MessageContent>,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionRequest {
+    messages: Vec<ChatMessage>,
+    #[serde(default)]
+    stream: bool,
+    temperature: Option<f32>,
+    max_tokens: Option<u64>,
+    max_completion_tokens: Option<u64>,
+    stop: Option<Stop>,
+}
+
+#[derive(Deserialize)]
+struct CompletionRequest {
+    prompt: String,
+    #[serde(default)]
+    stream: bool,
+    temperature: Option<f32>,
+    max_tokens: Option<u64>,
+    stop: Option<Stop>,
+}
+
+pub fn respond_error(request: Request, status: u16, message: &str) {
+    http::respond_json(
+        request,
+        status,
+        &json!({ "error": { "message": message, "type": "invalid_request_error" } }),
+    );
+}
+
+pub fn list_models(request: Request, state: &Arc<Mutex<Context>>) {
+    let data: Vec<Value> = service::downloaded_models(state)
+        .into_iter()
+        .map(
+            |(name, _)| json!({ "id": name, "object": "model", "created": 0, "owned_by": "breve" }),
+        )
+        .collect();
+    http::respond_json(request, 200, &json!({ "object": "list", "data": data }));
+}
+
+// The request's `model` is ignored, replies always come from the model loaded in the app
+pub fn chat_completions(mut request: Request, state: &Arc<Mutex<Context>>) {
+    let body: ChatCompletionRequest = match http::read_json(&mut request) {
+        Ok(body) => body,
+        Err(e) => return respond_error(request, 400, e.message()),
+    };
+
+    let mut conv = Conversation::new(String::new(), String::new());
+    for message in body.messages {
+        let content = match message.content {
+            Some(MessageContent::Text(text)) => text,
+            Some(MessageContent::Parts(parts)) => parts
+                .into_iter()
+                .filter_map(|part| part.text)
+                .collect::<Vec<_>>()
+                .join("\n"),
+            None => String::new(),
+        };
+        conv.add_message(&message.role, &content);
+    }
+    let options = SamplingOptions {
+        temperature: body.temperature,
+        max_tokens: body.max_completion_tokens.or(body.max_tokens),
+        stop: Stop::into_vec(body.stop),
+    };
+
+    let id = format!("chatcmpl-{}", Uuid::new_v4().simple());
+    if body.stream {
+        stream(
+            request,
+            state,
+            Prompt::Chat(conv),
+            options,
+            |model, delta, finish| {
+                json!({
+                    "id": id,
+                    "object": "chat.completion.chunk",
+                    "created": now(),
+                    "model": model,
+                    "choices": [{
+                        "index": 0,
+                        "delta": delta.map_or(json!({}), |content| json!({ "role": "assistant", "content": content })),
+                        "finish_reason": finish,
+                    }],
+                })
+            },
+        );
+        return;
+    }
+
+    match service::complete(state, Prompt::Chat(conv), options, |_| {}) {
+        Ok(completion) => http::respond_json(
+            request,
+            200,
+            &json!({
+                "id": id,
+                "object": "chat.completion",
+                "created": now(),
+                "model": completion.model,
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": completion.content },
+                    "finish_reason": completion.finish_reason,
+                }],
+                "usage": usage(&completion),
+            }),
+        ),
+        Err(e) => respond_error(request, http::status(&e), e.message()),
+    }
+}
+
+pub fn completions(mut request: Request, state: &Arc<Mutex<Context>>) {
+    let body: CompletionRequest = match http::read_json(&mut request) {
+        Ok(body) => body,
+        Err(e) => return respond_error(request, 400, e.message()),
+    };
+    let options = SamplingOptions {
+        temperature: body.temperature,
+        max_tokens: body.max_tokens,
+        stop: Stop::into_vec(body.stop),
+    };
+
+    let id = format!("cmpl-{}", Uuid::new_v4().simple());
+    if body.stream {
+        stream(
+            request,
+            state,
+            Prompt::Text(body.prompt),
+            options,
+            |model, delta, finish| {
+                json!({
+                    "id": id,
+                    "object": "text_completion",
+                    "created": now(),
+                    "model": model,
+                    "choices": [{ "index": 0, "text": delta.unwrap_or(""), "finish_reason": finish }],
+                })
+            },
+        );
+        return;
+    }
+
+    match service::complete(state, Prompt::Text(body.prompt), options, |_| {}) {
+        Ok(completion) => http::respond_json(
+            request,
+            200,
+            &json!({
+                "id": id,
+                "object": "text_completion",
+                "created": now(),
+                "model": completion.model,
+                "choices": [{
+                    "index": 0,
+                    "text": completion.content,
+                    "finish_reason": completion.finish_reason,
+                }],
+                "usage": usage(&completion),
+            }),
+        ),
+        Err(e) => respond_error(request, http::status(&e), e.message()),
+    }
+}
+
+// Server-sent events, `chunk` builds the payload for a text delta or, with `None`, the final event
+fn stream(
+    request: Request,
+    state: &Arc<Mutex<Context>>,
+    prompt: Prompt,
+    options: SamplingOptions,
+    chunk: impl Fn(&str, Option<&str>, Option<&str>) -> Value,
+) {
+    let Some(model) = service::active_model(state) else {
+        return respond_error(request, 503, "No model is loaded");
+    };
+    let Ok(mut events) = EventStream::open(request, "text/event-stream") else {
+        return;
+    };
+
+    let result = service::complete(state, prompt, options, |piece| {
+        let _ = events.send(&format!("data: {}\n\n", chunk(&model, Some(piece), None)));
+    });
+    let last = match result {
+        Ok(completion) => chunk(&model, None, Some(completion.finish_reason)),
+        Err(e) => json!({ "error": { "message": e.message(), "type": "server_error" } }),
+    };
+    let _ = events.send(&format!("data: {}\n\n", last));
+    let _ = events.send("data: [DONE]\n\n");
+    let _ = events.close();
+}
+
+fn usage(completion: &Completion) -> Value {
+    json!({
+        "prompt_tokens": completion.stats.prompt_tokens,
+        "completion_tokens": completion.stats.generated_tokens,
+        "total_tokens": completion.stats.prompt_tokens + completion.stats.generated_tokens,
+    })
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,242 @@
+use std::{
+    sync::{Arc, Mutex as StdMutex, MutexGuard},
+    thread::{self, JoinHandle},
+};
+
+use tauri::async_runtime::Mutex;
+use tiny_http::{Method, Request, Server};
+use uuid::Uuid;
+
+use crate::{
+    conversation::models::Conversation,
+    inference::{self, models::ChatPrompt},
+    infrastructure::{
+        self, consts,
+        context::Context,
+        database::Database,
+        error::{AppError, AppResult},
+    },
+    models::{self, models::Model},
+    server::{
+        http,
+        models::{Completion, SamplingOptions, ServerConfig, ServerStatus},
+        ollama, openai,
+    },
+};
+
+pub enum Prompt {
+    Chat(Conversation),
+    Text(String),
+}
+
+struct RunningServer {
+    server: Arc<Server>,
+    listener: JoinHandle<()>,
+    config: ServerConfig,
+    address: String,
+}
+
+static RUNNING: StdMutex<Option<RunningServer>> = StdMutex::new(None);
+
+// A listener that panicked leaves nothing inconsistent behind, so poisoning is ignored
+fn running() -> MutexGuard<'static, Option<RunningServer>> {
+    RUNNING.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn load_config(db: &Database) -> ServerConfig {
+    infrastructure::service::get_config(db, consts::API_SERVER_CONFIG_KEY.to_string())
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+pub fn status(db: &Database) -> ServerStatus {
+    let running = running();
+    match running.as_ref() {
+        Some(server) => ServerStatus {
+            config: server.config.clone(),
+            running: true,
+            address: Some(server.address.clone()),
+        },
+        None => ServerStatus {
+            config: load_config(db),
+            running: false,
+            address: None,
+        },
+    }
+}
+
+// Persists the settings and restarts the server so they take effect
+pub fn apply(
+    db: &Database,
+    mut config: ServerConfig,
+    state: Arc<Mutex<Context>>,
+) -> AppResult<ServerStatus> {
+    if config.token.is_empty() {
+        config.token = Uuid::new_v4().simple().to_string();
+    }
+    let raw = serde_json::to_string(&config).map_err(|e| AppError::Internal(e.to_string()))?;
+    infrastructure::service::set_config(db, consts::API_SERVER_CONFIG_KEY.to_string(), raw)?;
+
+    stop();
+    if config.enabled {
+        start(config, state)?;
+    }
+    Ok(status(db))
+}
+
+pub fn start(config: ServerConfig, state: Arc<Mutex<Context>>) -> AppResult<()> {
+    let address = format!("{}:{}", config.host, config.port);
+    let server = Arc::new(
+        Server::http(&address)
+            .map_err(|e| AppError::Network(format!("Failed to bind {}: {}", address, e)))?,
+    );
+
+    let incoming = server.clone();
+    let shared_config = Arc::new(config.clone());
+    let listener = thread::spawn(move || {
+        for request in incoming.incoming_requests() {
+            let state = state.clone();
+            let config = shared_config.clone();
+            thread::spawn(move || handle_request(request, &config, &state));
+        }
+    });
+
+    *running() = Some(RunningServer {
+        server,
+        listener,
+        config,
+        address,
+    });
+    Ok(())
+}
+
+pub fn stop() {
+    let running = running().take();
+    if let Some(running) = running {
+        running.server.unblock();
+        let _ = running.listener.join();
+    }
+}
+
+fn handle_request(request: Request, config: &ServerConfig, state: &Arc<Mutex<Context>>) {
+    let method = request.method().clone();
+    let path = request.url().split('?').next().unwrap_or("").to_string();
+    let authorized = http::bearer_token(&request) == Some(config.token.as_str());
+
+    if path == "/" || path == "/api" || path.starts_with("/api/") {
+        if !authorized && config.ollama_requires_token {
+            return ollama::respond_error(request, 401, "Invalid or missing API token");
+        }
+        return match (method, path.as_str()) {
+            // Ollama clients probe the root to detect a running server
+            (Method::Get | Method::Head, "/") => {
+                http::respond_text(request, 200, "Ollama is running")
+            }
+            (Method::Get, "/api/version") => ollama::version(request),
+            (Method::Get, "/api/tags") => ollama::tags(request, state),
+            (Method::Post, "/api/show") => ollama::show(request, state),
+            (Method::Post, "/api/chat") => ollama::chat(request, state),
+            (Method::Post, "/api/generate") => ollama::generate(request, state),
+            _ => ollama::respond_error(request, 404, "Unknown endpoint"),
+        };
+    }
+
+    if !authorized {
+        return openai::respond_error(request, 401, "Invalid or missing API token");
+    }
+    match (method, path.as_str()) {
+        (Method::Get, "/v1/models") => openai::list_models(request, state),
+        (Method::Post, "/v1/chat/completions") => openai::chat_completions(request, state),
+        (Method::Post, "/v1/completions") => openai::completions(request, state),
+        _ => openai::respond_error(request, 404, "Unknown endpoint"),
+    }
+}
+
+pub fn active_model(state: &Arc<Mutex<Context>>) -> Option<String> {
+    let ctx = tauri::async_runtime::block_on(state.lock());
+    ctx.inference
+        .as_ref()
+        .map(|inference| inference.config.default_model.clone())
+}
+
+// Ollama clients name the model on every request, it is loaded when it differs but the app's
+// default model stays the user's choice
+pub fn ensure_model(state: &Arc<Mutex<Context>>, model_name: &str) -> AppResult<String> {
+    let mut ctx = tauri::async_runtime::block_on(state.lock());
+    let model_name = model_name.strip_suffix(":latest").unwrap_or(model_name);
+    let loaded = ctx
+        .inference
+        .as_ref()
+        .map(|inference| inference.config.default_model.clone());
+
+    match loaded {
+        Some(loaded) if model_name.is_empty() || loaded == model_name => Ok(loaded),
+        _ if model_name.is_empty() => Err(no_model_loaded()),
+        _ => {
+            inference::service::load_model(model_name, &mut ctx)?;
+            ctx.inference
+                .as_ref()
+                .map(|inference| inference.config.default_model.clone())
+                .ok_or_else(no_model_loaded)
+        }
+    }
+}
+
+pub fn downloaded_models(state: &Arc<Mutex<Context>>) -> Vec<(String, Model)> {
+    let ctx = tauri::async_runtime::block_on(state.lock());
+    let catalog = ctx.config.get_available_models();
+    let mut names = models::service::downloaded_models(catalog);
+    names.sort();
+    names
+        .into_iter()
+        .filter_map(|name| catalog.get(&name).cloned().map(|model| (name, model)))
+        .collect()
+}
+
+// Runs a generation on the loaded model, holding the app context for its whole duration
+pub fn complete(
+    state: &Arc<Mutex<Context>>,
+    prompt: Prompt,
+    options: SamplingOptions,
+    on_piece: impl FnMut(&str),
+) -> AppResult<Completion> {
+    let mut guard = tauri::async_runtime::block_on(state.lock());
+    let ctx = &mut *guard;
+    let inference = ctx.inference.as_mut().ok_or_else(no_model_loaded)?;
+
+    let mut config = inference.config.clone();
+    if let Some(temperature) = options.temperature {
+        config.temperature = temperature.max(0.0);
+    }
+    if let Some(max_tokens) = options.max_tokens {
+        // The prompt was fitted assuming the configured output budget, never exceed it
+        config.max_output_length = max_tokens.clamp(1, config.max_output_length);
+    }
+    config.stop_sequences.extend(options.stop);
+
+    let prompt = match &prompt {
+        Prompt::Chat(conv) => inference::models::Prompt::Chat(ChatPrompt::new(conv)),
+        Prompt::Text(text) => inference::models::Prompt::Text(text),
+    };
+    // API requests always run on the base weights
+    inference.set_adapters(&[])?;
+    let (content, stats) = inference.generate(prompt, Some(&config), on_piece)?;
+    inference::service::record_generation(&ctx.db, &config.default_model, &stats);
+
+    let finish_reason = if stats.generated_tokens >= config.max_output_length {
+        "length"
+    } else {
+        "stop"
+    };
+    Ok(Completion {
+        model: config.default_model,
+        content,
+        stats,
+        finish_reason,
+    })
+}
+
+fn no_model_loaded() -> AppError {
+    AppError::ModelLoadFailed("No model is loaded".into())
+}
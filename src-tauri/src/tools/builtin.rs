@@ -0,0 +1,544 @@
+use std::f64::consts::{E, PI};
+
+use serde_json::{Value, json};
+
+use crate::{
+    infrastructure::{
+        clock, consts,
+        context::Context,
+        error::{AppError, AppResult},
+    },
+    search,
+    tools::{models::ToolDefinition, registry::Tool},
+};
+
+pub struct Calculator;
+
+impl Tool for Calculator {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "calculator".into(),
+            description: "Evaluates an arithmetic expression. Supports + - * / % ^, parentheses, \
+                sqrt, abs, ln, log, sin, cos, tan, pi and e."
+                .into(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "expression": { "type": "string", "description": "For example (2 + 3) * 4" }
+                },
+                "required": ["expression"]
+            }),
+        }
+    }
+
+    fn call(&self, arguments: &Value, _ctx: &mut Context) -> AppResult<String> {
+        let expression = string_argument(arguments, "expression")?;
+        let value = evaluate(expression)?;
+        Ok(format_number(value))
+    }
+}
+
+pub struct CurrentDateTime;
+
+impl Tool for CurrentDateTime {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "current_datetime".into(),
+            description: "Returns the current date, time and weekday in UTC.".into(),
+            parameters: json!({ "type": "object", "properties": {} }),
+        }
+    }
+
+    fn call(&self, _arguments: &Value, _ctx: &mut Context) -> AppResult<String> {
+        let now = clock::unix_now();
+        Ok(json!({
+            "utc": clock::rfc3339(now),
+            "weekday": clock::weekday(now),
+            "unix": now,
+        })
+        .to_string())
+    }
+}
+
+pub struct UnitConverter;
+
+impl Tool for UnitConverter {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "convert_units".into(),
+            description: "Converts a value between units of length, area, mass, volume, \
+                temperature, speed, time or data size, e.g. km to mi or C to F."
+                .into(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "value": { "type": "number" },
+                    "from": { "type": "string", "description": "Unit to convert from" },
+                    "to": { "type": "string", "description": "Unit to convert to" }
+                },
+                "required": ["value", "from", "to"]
+            }),
+        }
+    }
+
+    fn call(&self, arguments: &Value, _ctx: &mut Context) -> AppResult<String> {
+        let value = arguments
+            .get("value")
+            .and_then(|value| {
+                value
+                    .as_f64()
+                    .or_else(|| value.as_str().and_then(|raw| raw.trim().parse().ok()))
+            })
+            .ok_or_else(|| AppError::Validation("Missing number argument value".into()))?;
+        let from = string_argument(arguments, "from")?;
+        let to = string_argument(arguments, "to")?;
+
+        let converted = convert(value, from, to)?;
+        Ok(format!(
+            "{} {} = {} {}",
+            format_number(value),
+            from,
+            format_number(converted),
+            to
+        ))
+    }
+}
+
+pub struct ConversationSearch;
+
+impl Tool for ConversationSearch {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "search_conversations".into(),
+            description: "Searches the user's past conversations and returns the most \
+                related messages."
+                .into(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": 10 }
+                },
+                "required": ["query"]
+            }),
+        }
+    }
+
+    fn call(&self, arguments: &Value, ctx: &mut Context) -> AppResult<String> {
+        let query = string_argument(arguments, "query")?;
+        let limit = arguments
+            .get("limit")
+            .and_then(Value::as_u64)
+            .map_or(consts::TOOL_SEARCH_LIMIT, |limit| {
+                limit.clamp(1, 10) as usize
+            });
+
+        let matches: Vec<Value> = search::service::semantic_search(query, limit, ctx)?
+            .into_iter()
+            .map(|found| {
+                json!({
+                    "conversation": found.conversation_title,
+                    "role": found.role,
+                    "content": found.content,
+                })
+            })
+            .collect();
+        Ok(Value::Array(matches).to_string())
+    }
+}
+
+fn string_argument<'a>(arguments: &'a Value, name: &str) -> AppResult<&'a str> {
+    arguments
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| AppError::Validation(format!("Missing string argument {}", name)))
+}
+
+// Drops float noise such as 0.30000000000000004
+fn format_number(value: f64) -> String {
+    let rounded = (value * 1e10).round() / 1e10;
+    if rounded.fract() == 0.0 && rounded.abs() < 1e15 {
+        format!("{}", rounded as i64)
+    } else {
+        format!("{}", rounded)
+    }
+}
+
+pub fn evaluate(expression: &str) -> AppResult<f64> {
+    let mut parser = ExpressionParser {
+        chars: expression.chars().filter(|c| !c.is_whitespace()).collect(),
+        pos: 0,
+        depth: 0,
+    };
+    let value = parser.expression()?;
+    if parser.pos < parser.chars.len() {
+        return Err(parser.error());
+    }
+    if !value.is_finite() {
+        return Err(AppError::Validation(format!(
+            "{} has no finite value",
+            expression
+        )));
+    }
+    Ok(value)
+}
+
+// Recursive descent over: expression = term (+|- term)*, term = unary (*|/|% unary)*,
+// unary = -unary | power, power = primary (^ unary)?
+struct ExpressionParser {
+    chars: Vec<char>,
+    pos: usize,
+    // Nesting of the `unary` calls in progress, every recursion goes through one
+    depth: usize,
+}
+
+impl ExpressionParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn error(&self) -> AppError {
+        AppError::Validation(format!("Invalid expression near position {}", self.pos + 1))
+    }
+
+    fn expression(&mut self) -> AppResult<f64> {
+        let mut value = self.term()?;
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.pos += 1;
+            let rhs = self.term()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Ok(value)
+    }
+
+    fn term(&mut self) -> AppResult<f64> {
+        let mut value = self.unary()?;
+        while let Some(op @ ('*' | '/' | '%')) = self.peek() {
+            self.pos += 1;
+            let rhs = self.unary()?;
+            value = match op {
+                '*' => value * rhs,
+                '/' => value / rhs,
+                _ => value % rhs,
+            };
+        }
+        Ok(value)
+    }
+
+    // Expressions come from the model, deep nesting must not overflow the stack
+    fn unary(&mut self) -> AppResult<f64> {
+        if self.depth >= consts::CALCULATOR_MAX_DEPTH {
+            return Err(AppError::Validation(
+                "Expression is nested too deeply".into(),
+            ));
+        }
+        self.depth += 1;
+        let value = self.signed();
+        self.depth -= 1;
+        value
+    }
+
+    fn signed(&mut self) -> AppResult<f64> {
+        match self.peek() {
+            Some('-') => {
+                self.pos += 1;
+                Ok(-self.unary()?)
+            }
+            Some('+') => {
+                self.pos += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> AppResult<f64> {
+        let base = self.primary()?;
+        if self.peek() == Some('^') {
+            self.pos += 1;
+            return Ok(base.powf(self.unary()?));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> AppResult<f64> {
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let value = self.expression()?;
+                self.expect(')')?;
+                Ok(value)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => self.identifier(),
+            _ => Err(self.error()),
+        }
+    }
+
+    fn number(&mut self) -> AppResult<f64> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+            self.pos += 1;
+        }
+        // Exponent, as in 1.5e3
+        if self.peek().is_some_and(|c| c == 'e' || c == 'E')
+            && self.chars.get(self.pos + 1).is_some_and(|c| {
+                c.is_ascii_digit()
+                    || ((*c == '-' || *c == '+')
+                        && self
+                            .chars
+                            .get(self.pos + 2)
+                            .is_some_and(char::is_ascii_digit))
+            })
+        {
+            self.pos += 2;
+            while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                self.pos += 1;
+            }
+        }
+        let literal: String = self.chars[start..self.pos].iter().collect();
+        literal.parse().map_err(|_| self.error())
+    }
+
+    fn identifier(&mut self) -> AppResult<f64> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric()) {
+            self.pos += 1;
+        }
+        let name: String = self.chars[start..self.pos].iter().collect();
+        match name.to_lowercase().as_str() {
+            "pi" => return Ok(PI),
+            "e" => return Ok(E),
+            _ => {}
+        }
+
+        self.expect('(')?;
+        let argument = self.expression()?;
+        self.expect(')')?;
+        match name.to_lowercase().as_str() {
+            "sqrt" => Ok(argument.sqrt()),
+            "abs" => Ok(argument.abs()),
+            "ln" => Ok(argument.ln()),
+            "log" => Ok(argument.log10()),
+            "sin" => Ok(argument.sin()),
+            "cos" => Ok(argument.cos()),
+            "tan" => Ok(argument.tan()),
+            _ => Err(AppError::Validation(format!("Unknown function {}", name))),
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> AppResult<()> {
+        if self.peek() != Some(expected) {
+            return Err(self.error());
+        }
+        self.pos += 1;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Quantity {
+    Length,
+    Area,
+    Mass,
+    Volume,
+    Speed,
+    Time,
+    Data,
+}
+
+// Aliases of each unit with its size in the quantity's base unit
+const UNITS: &[(&[&str], Quantity, f64)] = &[
+    (
+        &["m", "meter", "meters", "metre", "metres"],
+        Quantity::Length,
+        1.0,
+    ),
+    (
+        &["km", "kilometer", "kilometers", "kilometre", "kilometres"],
+        Quantity::Length,
+        1000.0,
+    ),
+    (
+        &[
+            "cm",
+            "centimeter",
+            "centimeters",
+            "centimetre",
+            "centimetres",
+        ],
+        Quantity::Length,
+        0.01,
+    ),
+    (
+        &[
+            "mm",
+            "millimeter",
+            "millimeters",
+            "millimetre",
+            "millimetres",
+        ],
+        Quantity::Length,
+        0.001,
+    ),
+    (&["mi", "mile", "miles"], Quantity::Length, 1609.344),
+    (&["yd", "yard", "yards"], Quantity::Length, 0.9144),
+    (&["ft", "foot", "feet"], Quantity::Length, 0.3048),
+    (&["in", "inch", "inches"], Quantity::Length, 0.0254),
+    (
+        &["nmi", "nautical mile", "nautical miles"],
+        Quantity::Length,
+        1852.0,
+    ),
+    (
+        &["m2", "m²", "square meter", "square meters"],
+        Quantity::Area,
+        1.0,
+    ),
+    (
+        &["km2", "km²", "square kilometer", "square kilometers"],
+        Quantity::Area,
+        1e6,
+    ),
+    (
+        &["ft2", "ft²", "square foot", "square feet"],
+        Quantity::Area,
+        0.09290304,
+    ),
+    (&["ha", "hectare", "hectares"], Quantity::Area, 10_000.0),
+    (&["acre", "acres"], Quantity::Area, 4046.8564224),
+    (&["kg", "kilogram", "kilograms"], Quantity::Mass, 1.0),
+    (&["g", "gram", "grams"], Quantity::Mass, 0.001),
+    (&["mg", "milligram", "milligrams"], Quantity::Mass, 1e-6),
+    (
+        &["t", "tonne", "tonnes", "metric ton"],
+        Quantity::Mass,
+        1000.0,
+    ),
+    (
+        &["lb", "lbs", "pound", "pounds"],
+        Quantity::Mass,
+        0.45359237,
+    ),
+    (&["oz", "ounce", "ounces"], Quantity::Mass, 0.028349523125),
+    (&["st", "stone", "stones"], Quantity::Mass, 6.35029318),
+    (
+        &["l", "liter", "liters", "litre", "litres"],
+        Quantity::Volume,
+        1.0,
+    ),
+    (
+        &[
+            "ml",
+            "milliliter",
+            "milliliters",
+            "millilitre",
+            "millilitres",
+        ],
+        Quantity::Volume,
+        0.001,
+    ),
+    (
+        &["m3", "m³", "cubic meter", "cubic meters"],
+        Quantity::Volume,
+        1000.0,
+    ),
+    (&["gal", "gallon", "gallons"], Quantity::Volume, 3.785411784),
+    (&["qt", "quart", "quarts"], Quantity::Volume, 0.946352946),
+    (&["pt", "pint", "pints"], Quantity::Volume, 0.473176473),
+    (&["cup", "cups"], Quantity::Volume, 0.2365882365),
+    (
+        &["fl oz", "floz", "fluid ounce", "fluid ounces"],
+        Quantity::Volume,
+        0.0295735295625,
+    ),
+    (
+        &["tbsp", "tablespoon", "tablespoons"],
+        Quantity::Volume,
+        0.01478676478125,
+    ),
+    (
+        &["tsp", "teaspoon", "teaspoons"],
+        Quantity::Volume,
+        0.00492892159375,
+    ),
+    (&["m/s", "meters per second"], Quantity::Speed, 1.0),
+    (
+        &["km/h", "kph", "kmh", "kilometers per hour"],
+        Quantity::Speed,
+        1.0 / 3.6,
+    ),
+    (&["mph", "miles per hour"], Quantity::Speed, 0.44704),
+    (&["kn", "knot", "knots"], Quantity::Speed, 1852.0 / 3600.0),
+    (&["ft/s", "feet per second"], Quantity::Speed, 0.3048),
+    (&["s", "sec", "second", "seconds"], Quantity::Time, 1.0),
+    (
+        &["ms", "millisecond", "milliseconds"],
+        Quantity::Time,
+        0.001,
+    ),
+    (&["min", "minute", "minutes"], Quantity::Time, 60.0),
+    (&["h", "hr", "hour", "hours"], Quantity::Time, 3600.0),
+    (&["d", "day", "days"], Quantity::Time, 86_400.0),
+    (&["wk", "week", "weeks"], Quantity::Time, 604_800.0),
+    (&["bit", "bits"], Quantity::Data, 0.125),
+    (&["b", "byte", "bytes"], Quantity::Data, 1.0),
+    (&["kb", "kilobyte", "kilobytes"], Quantity::Data, 1e3),
+    (&["mb", "megabyte", "megabytes"], Quantity::Data, 1e6),
+    (&["gb", "gigabyte", "gigabytes"], Quantity::Data, 1e9),
+    (&["tb", "terabyte", "terabytes"], Quantity::Data, 1e12),
+    (&["kib", "kibibyte", "kibibytes"], Quantity::Data, 1024.0),
+    (
+        &["mib", "mebibyte", "mebibytes"],
+        Quantity::Data,
+        1024.0 * 1024.0,
+    ),
+    (
+        &["gib", "gibibyte", "gibibytes"],
+        Quantity::Data,
+        1024.0 * 1024.0 * 1024.0,
+    ),
+];
+
+pub fn convert(value: f64, from: &str, to: &str) -> AppResult<f64> {
+    if let (Some(from), Some(to)) = (temperature_unit(from), temperature_unit(to)) {
+        let celsius = match from {
+            'c' => value,
+            'f' => (value - 32.0) * 5.0 / 9.0,
+            _ => value - 273.15,
+        };
+        return Ok(match to {
+            'c' => celsius,
+            'f' => celsius * 9.0 / 5.0 + 32.0,
+            _ => celsius + 273.15,
+        });
+    }
+
+    let (from_quantity, from_factor) = linear_unit(from)?;
+    let (to_quantity, to_factor) = linear_unit(to)?;
+    if from_quantity != to_quantity {
+        return Err(AppError::Validation(format!(
+            "Cannot convert {} to {}",
+            from, to
+        )));
+    }
+    Ok(value * from_factor / to_factor)
+}
+
+fn linear_unit(unit: &str) -> AppResult<(Quantity, f64)> {
+    let unit = unit.trim().to_lowercase();
+    UNITS
+        .iter()
+        .find(|(aliases, _, _)| aliases.contains(&unit.as_str()))
+        .map(|(_, quantity, factor)| (*quantity, *factor))
+        .ok_or_else(|| AppError::Validation(format!("Unknown unit {}", unit)))
+}
+
+fn temperature_unit(unit: &str) -> Option<char> {
+    match unit.trim().to_lowercase().trim_start_matches('°') {
+        "c" | "celsius" => Some('c'),
+        "f" | "fahrenheit" => Some('f'),
+        "k" | "kelvin" => Some('k'),
+        _ => None,
+    }
+}
@@ -0,0 +1,16 @@
+use std::sync::Arc;
+
+use tauri::{State, async_runtime::Mutex};
+
+use crate::{
+    infrastructure::{context::Context, error::AppResult},
+    tools::models::ToolDefinition,
+};
+
+#[tauri::command]
+pub async fn get_tools(
+    app_state: State<'_, Arc<Mutex<Context>>>,
+) -> AppResult<Vec<ToolDefinition>> {
+    let ctx = app_state.lock().await;
+    Ok(ctx.tools.definitions())
+}
@@ -0,0 +1,6 @@
+pub mod builtin;
+pub mod controller;
+pub mod models;
+pub mod parser;
+pub mod registry;
+pub mod service;
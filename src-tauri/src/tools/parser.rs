@@ -0,0 +1,118 @@
+use serde_json::Value;
+
+use crate::inference::stop::partial_match_start;
+use crate::tools::models::{ToolCall, ToolDefinition};
+
+const TOOL_CALL_OPEN: &str = "<tool_call>";
+const TOOL_CALL_CLOSE: &str = "</tool_call>";
+// Llama 3.1 prefixes calls to built-in tools with it
+const PYTHON_TAG: &str = "<|python_tag|>";
+// How a reply made of a single bare call can start
+const BARE_OPENINGS: [&str; 3] = ["{", "```", PYTHON_TAG];
+
+// Tool calls in a reply, either Hermes style `<tool_call>` blocks as used by Qwen, or a
+// reply made of nothing but a Llama 3 style JSON object. The tags make a call explicit, a bare
+// object only counts when it names one of `tools`, so `{"name": "Bob"}` stays an answer.
+pub fn parse_tool_calls(reply: &str, tools: &[ToolDefinition]) -> Vec<ToolCall> {
+    if reply.contains(TOOL_CALL_OPEN) {
+        return tagged_calls(reply);
+    }
+    json_call(reply)
+        .filter(|call| tools.iter().any(|tool| tool.name == call.name))
+        .into_iter()
+        .collect()
+}
+
+fn tagged_calls(reply: &str) -> Vec<ToolCall> {
+    let mut calls = Vec::new();
+    let mut rest = reply;
+    while let Some(open) = rest.find(TOOL_CALL_OPEN) {
+        rest = &rest[open + TOOL_CALL_OPEN.len()..];
+        // Generation may stop right before the closing tag
+        let end = rest.find(TOOL_CALL_CLOSE).unwrap_or(rest.len());
+        if let Ok(value) = serde_json::from_str::<Value>(rest[..end].trim())
+            && let Some(call) = to_call(&value)
+        {
+            calls.push(call);
+        }
+        rest = &rest[end..];
+    }
+    calls
+}
+
+fn json_call(reply: &str) -> Option<ToolCall> {
+    let mut text = reply.trim();
+    text = text.strip_prefix(PYTHON_TAG).unwrap_or(text).trim();
+    if let Some(fenced) = text.strip_prefix("```") {
+        text = fenced
+            .trim_start_matches("json")
+            .trim_end()
+            .strip_suffix("```")?
+            .trim();
+    }
+    if !text.starts_with('{') {
+        return None;
+    }
+    to_call(&serde_json::from_str(text).ok()?)
+}
+
+// Accepts `arguments` or `parameters`, as an object or as a JSON encoded string, and the
+// OpenAI shape nesting both under `function`
+fn to_call(value: &Value) -> Option<ToolCall> {
+    let value = value.get("function").unwrap_or(value);
+    let name = value.get("name")?.as_str()?.to_string();
+    let arguments = match value.get("arguments").or_else(|| value.get("parameters")) {
+        Some(Value::String(raw)) => serde_json::from_str(raw).ok()?,
+        Some(arguments) => arguments.clone(),
+        None => Value::Object(Default::default()),
+    };
+    Some(ToolCall { name, arguments })
+}
+
+// Holds back the parts of a streamed reply that may be a tool call, that is the whole reply
+// when it opens like a bare call and everything from a `<tool_call>` tag on otherwise
+#[derive(Default)]
+pub struct CallFilter {
+    text: String,
+    shown: usize,
+    // Whether the reply opens like a bare call, unknown until its first characters settle it
+    bare: Option<bool>,
+}
+
+impl CallFilter {
+    // Text safe to show after `piece`
+    pub fn push(&mut self, piece: &str) -> String {
+        self.text.push_str(piece);
+        let body = self.text.trim_start();
+        if self.bare.is_none() && !body.is_empty() {
+            if BARE_OPENINGS
+                .iter()
+                .any(|opening| body.starts_with(opening))
+            {
+                self.bare = Some(true);
+            } else if !BARE_OPENINGS
+                .iter()
+                .any(|opening| opening.starts_with(body))
+            {
+                self.bare = Some(false);
+            }
+        }
+        if self.bare != Some(false) {
+            return String::new();
+        }
+
+        let end = self
+            .text
+            .find(TOOL_CALL_OPEN)
+            .unwrap_or_else(|| partial_match_start(&self.text, TOOL_CALL_OPEN))
+            .max(self.shown);
+        let shown = self.text[self.shown..end].to_string();
+        self.shown = end;
+        shown
+    }
+
+    // Text held back, to be shown when the reply turned out not to call a tool
+    pub fn finish(self) -> String {
+        self.text[self.shown..].to_string()
+    }
+}
@@ -0,0 +1,53 @@
+use serde_json::Value;
+
+use crate::{
+    infrastructure::{context::Context, error::AppResult},
+    tools::{builtin, models::ToolDefinition},
+};
+
+pub trait Tool: Send + Sync {
+    fn definition(&self) -> ToolDefinition;
+
+    // Result handed back to the model, errors are reported to it as well
+    fn call(&self, arguments: &Value, ctx: &mut Context) -> AppResult<String>;
+}
+
+#[derive(Default)]
+pub struct ToolRegistry {
+    tools: Vec<Box<dyn Tool>>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Offline tools shipped with the app
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(builtin::Calculator));
+        registry.register(Box::new(builtin::CurrentDateTime));
+        registry.register(Box::new(builtin::UnitConverter));
+        registry.register(Box::new(builtin::ConversationSearch));
+        registry
+    }
+
+    // A tool registered under an existing name replaces it
+    pub fn register(&mut self, tool: Box<dyn Tool>) {
+        let name = tool.definition().name;
+        self.tools
+            .retain(|existing| existing.definition().name != name);
+        self.tools.push(tool);
+    }
+
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.tools.iter().map(|tool| tool.definition()).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Tool> {
+        self.tools
+            .iter()
+            .find(|tool| tool.definition().name == name)
+            .map(|tool| tool.as_ref())
+    }
+}
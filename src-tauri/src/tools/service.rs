@@ -0,0 +1,18 @@
+use crate::{
+    infrastructure::{self, consts, context::Context, database::Database},
+    tools::{models::ToolCall, registry::ToolRegistry},
+};
+
+pub fn is_enabled(db: &Database) -> bool {
+    infrastructure::service::get_config(db, consts::TOOLS_ENABLED_KEY.to_string())
+        .is_ok_and(|value| value == "true")
+}
+
+// Runs one call, failures are returned as text so the model can correct itself
+pub fn run(registry: &ToolRegistry, call: &ToolCall, ctx: &mut Context) -> String {
+    let Some(tool) = registry.get(&call.name) else {
+        return format!("Error: unknown tool {}", call.name);
+    };
+    tool.call(&call.arguments, ctx)
+        .unwrap_or_else(|e| format!("Error: {}", e.message()))
+}
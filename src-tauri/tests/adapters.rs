@@ -0,0 +1,122 @@
+mod common;
+
+use breve_lib::{
+    adapters::{self, models::ActiveAdapter},
+    conversation,
+    inference::scripted::{ScriptedEngine, ScriptedReply},
+    infrastructure::{error::AppError, events::RecordingSink, path_resolver},
+};
+use common::{context_with, write_file};
+
+fn init_paths() {
+    path_resolver::init_dir_paths(std::env::temp_dir().join("breve-adapter-tests"));
+}
+
+#[test]
+fn only_gguf_files_are_imported() {
+    init_paths();
+    let ctx = context_with(ScriptedEngine::default());
+
+    let file = write_file(".gguf", b"GGUF\x03\x00\x00\x00adapter");
+    let adapter = adapters::service::import_adapter(&ctx.db, file.path()).unwrap();
+    assert_eq!(
+        adapters::service::get_adapters(&ctx.db).unwrap(),
+        vec![adapter.clone()]
+    );
+
+    let text = write_file(".gguf", "not an adapter");
+    assert!(matches!(
+        adapters::service::import_adapter(&ctx.db, text.path()),
+        Err(AppError::Validation(_))
+    ));
+
+    adapters::service::delete_adapter(&ctx.db, &adapter.id).unwrap();
+    assert!(adapters::service::get_adapters(&ctx.db).unwrap().is_empty());
+}
+
+#[test]
+fn adapters_switch_per_conversation_and_are_recorded_on_replies() {
+    init_paths();
+    let engine = ScriptedEngine::new([
+        ScriptedReply::text(&["Styled"]),
+        ScriptedReply::text(&["Plain"]),
+    ]);
+    let applied = engine.adapters();
+    let mut ctx = context_with(engine);
+
+    let file = write_file(".gguf", b"GGUF\x03\x00\x00\x00house-style");
+    let adapter = adapters::service::import_adapter(&ctx.db, file.path()).unwrap();
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Style").unwrap();
+    let active = adapters::service::set_conversation_adapters(
+        &ctx.db,
+        &conv_id,
+        vec![ActiveAdapter {
+            id: adapter.id.clone(),
+            name: String::new(),
+            scale: 0.8,
+        }],
+    )
+    .unwrap();
+    assert_eq!(active[0].name, adapter.name);
+
+    let sink = RecordingSink::new();
+    let reply = conversation::service::continue_conversation(
+        &conv_id,
+        "Write a memo",
+        &[],
+        &sink,
+        &mut ctx,
+    )
+    .unwrap();
+    assert_eq!(reply.adapters, active);
+    {
+        let applied = applied.lock().unwrap();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(applied[0].scale, 0.8);
+        assert!(applied[0].path.ends_with(format!("{}.gguf", adapter.id)));
+    }
+
+    // Deleting the adapter detaches it, the next reply uses the base weights
+    adapters::service::delete_adapter(&ctx.db, &adapter.id).unwrap();
+    let reply =
+        conversation::service::continue_conversation(&conv_id, "Again", &[], &sink, &mut ctx)
+            .unwrap();
+    assert!(reply.adapters.is_empty());
+    assert!(applied.lock().unwrap().is_empty());
+
+    let conv = conversation::service::get_conversation(&ctx.db, &conv_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(conv.body[1].adapters, active);
+}
+
+#[test]
+fn invalid_adapter_settings_are_rejected() {
+    init_paths();
+    let ctx = context_with(ScriptedEngine::default());
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Checks").unwrap();
+    let file = write_file(".gguf", b"GGUF\x03\x00\x00\x00");
+    let adapter = adapters::service::import_adapter(&ctx.db, file.path()).unwrap();
+
+    let with_scale = |scale: f32| ActiveAdapter {
+        id: adapter.id.clone(),
+        name: String::new(),
+        scale,
+    };
+    assert!(matches!(
+        adapters::service::set_conversation_adapters(&ctx.db, &conv_id, vec![with_scale(f32::NAN)]),
+        Err(AppError::Validation(_))
+    ));
+    assert!(matches!(
+        adapters::service::set_conversation_adapters(
+            &ctx.db,
+            &conv_id,
+            vec![with_scale(1.0), with_scale(0.5)]
+        ),
+        Err(AppError::Validation(_))
+    ));
+    assert!(matches!(
+        adapters::service::set_conversation_adapters(&ctx.db, "missing", vec![with_scale(1.0)]),
+        Err(AppError::NotFound(_))
+    ));
+}
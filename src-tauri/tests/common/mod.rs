@@ -0,0 +1,52 @@
+// Shared by the integration tests, each of which uses only part of it
+#![allow(dead_code)]
+
+use std::io::Write;
+
+use breve_lib::{
+    inference::{models::Inference, scripted::ScriptedEngine},
+    infrastructure::{
+        consts,
+        context::Context,
+        database::{Database, IN_MEMORY},
+    },
+};
+
+pub const MODEL: &str = "scripted";
+
+// Fresh database with `engine` loaded as the default model
+pub fn context_with(engine: ScriptedEngine) -> Context {
+    let db = Database::open(IN_MEMORY).expect("in-memory database");
+    let mut ctx = Context::init(db).expect("context");
+    ctx.config.default_model = MODEL.into();
+
+    let model_attrs = consts::default_models()
+        .values()
+        .next()
+        .expect("catalog is not empty")
+        .clone();
+    ctx.inference = Some(Inference::with_engine(
+        Box::new(engine),
+        model_attrs,
+        ctx.config.clone(),
+    ));
+    ctx
+}
+
+// Makes `name` the default model and has the loaded engine answer as that model, the way
+// activating a downloaded model would
+pub fn switch_model(ctx: &mut Context, name: &str) {
+    ctx.config.default_model = name.into();
+    if let Some(inference) = ctx.inference.as_mut() {
+        inference.config.default_model = name.into();
+    }
+}
+
+pub fn write_file(suffix: &str, content: impl AsRef<[u8]>) -> tempfile::NamedTempFile {
+    let mut file = tempfile::Builder::new()
+        .suffix(suffix)
+        .tempfile()
+        .expect("temp file");
+    file.write_all(content.as_ref()).expect("temp file written");
+    file
+}
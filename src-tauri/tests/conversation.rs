@@ -0,0 +1,286 @@
+mod common;
+
+use std::time::Duration;
+
+use breve_lib::{
+    attachments, conversation,
+    inference::{
+        self,
+        scripted::{ScriptedEngine, ScriptedReply},
+    },
+    infrastructure::{consts, error::AppError, events::RecordingSink},
+};
+use common::{MODEL, context_with, write_file};
+
+#[test]
+fn reply_is_streamed_and_persisted() {
+    let engine = ScriptedEngine::new([ScriptedReply::text(&["Hello", ",", " world"])]);
+    let prompts = engine.prompts();
+    let mut ctx = context_with(engine);
+    let sink = RecordingSink::new();
+
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Greeting").unwrap();
+    let reply =
+        conversation::service::continue_conversation(&conv_id, "Hi", &[], &sink, &mut ctx).unwrap();
+
+    assert_eq!(sink.streamed_text(), "Hello, world");
+    assert_eq!(reply.role, "assistant");
+    assert_eq!(reply.content, "Hello, world");
+    let stats = reply.stats.expect("reply carries stats");
+    assert_eq!(stats.generated_tokens, 3);
+    assert!(stats.prompt_tokens > 0);
+
+    let stored = conversation::service::get_conversation(&ctx.db, &conv_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.body.len(), 2);
+    assert_eq!(stored.body[0].content, "Hi");
+    assert_eq!(stored.body[1].content, "Hello, world");
+
+    let prompts = prompts.lock().unwrap();
+    assert_eq!(prompts.len(), 1);
+    assert!(prompts[0].ends_with("user: Hi\n"));
+
+    let samples = inference::service::get_inference_stats(&ctx.db).unwrap();
+    assert_eq!(samples.len(), 1);
+    assert_eq!(samples[0].model_name, MODEL);
+    assert_eq!(samples[0].samples, 1);
+}
+
+#[test]
+fn failed_generation_leaves_conversation_untouched() {
+    let error = AppError::ContextOverflow("Prompt does not fit".into());
+    let mut ctx = context_with(ScriptedEngine::new([ScriptedReply::failing(
+        &["partial"],
+        error.clone(),
+    )]));
+    let sink = RecordingSink::new();
+
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Broken").unwrap();
+    let result = conversation::service::continue_conversation(&conv_id, "Hi", &[], &sink, &mut ctx);
+
+    assert_eq!(result.unwrap_err(), error);
+    assert_eq!(sink.streamed_text(), "partial");
+    let stored = conversation::service::get_conversation(&ctx.db, &conv_id)
+        .unwrap()
+        .unwrap();
+    assert!(stored.body.is_empty());
+    assert!(
+        inference::service::get_inference_stats(&ctx.db)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn exhausted_script_is_an_error() {
+    let mut ctx = context_with(ScriptedEngine::new([ScriptedReply::text(&["Once"])]));
+    let sink = RecordingSink::new();
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Twice").unwrap();
+
+    conversation::service::continue_conversation(&conv_id, "First", &[], &sink, &mut ctx).unwrap();
+    let second =
+        conversation::service::continue_conversation(&conv_id, "Second", &[], &sink, &mut ctx);
+
+    assert!(matches!(second, Err(AppError::Internal(_))));
+}
+
+#[test]
+fn missing_model_is_reported() {
+    let mut ctx = context_with(ScriptedEngine::default());
+    ctx.inference = None;
+    let sink = RecordingSink::new();
+
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "No model").unwrap();
+    let result = conversation::service::continue_conversation(&conv_id, "Hi", &[], &sink, &mut ctx);
+
+    assert!(matches!(result, Err(AppError::ModelLoadFailed(_))));
+    assert!(sink.events().is_empty());
+}
+
+#[test]
+fn reply_is_cut_at_max_output_length() {
+    let mut ctx = context_with(ScriptedEngine::new([ScriptedReply::text(&[
+        "one", " two", " three", " four",
+    ])]));
+    if let Some(inference) = ctx.inference.as_mut() {
+        inference.config.max_output_length = 2;
+    }
+    let sink = RecordingSink::new();
+
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Short").unwrap();
+    let reply =
+        conversation::service::continue_conversation(&conv_id, "Count", &[], &sink, &mut ctx)
+            .unwrap();
+
+    assert_eq!(reply.content, "one two");
+    assert_eq!(reply.stats.unwrap().generated_tokens, 2);
+}
+
+#[test]
+fn token_delay_shows_up_in_timings() {
+    let engine = ScriptedEngine::new([ScriptedReply::text(&["a", "b", "c"])])
+        .with_token_delay(Duration::from_millis(20));
+    let mut ctx = context_with(engine);
+    let sink = RecordingSink::new();
+
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Slow").unwrap();
+    let stats = conversation::service::continue_conversation(&conv_id, "Go", &[], &sink, &mut ctx)
+        .unwrap()
+        .stats
+        .unwrap();
+
+    assert!(stats.time_to_first_token_ms >= 20.0);
+    assert!(stats.generation_ms >= 60.0);
+    assert!(stats.tokens_per_second <= 50.0);
+}
+
+#[test]
+fn attachments_are_decoded_stored_and_reopened() {
+    let engine = ScriptedEngine::new([ScriptedReply::text(&["Noted"])]);
+    let prompts = engine.prompts();
+    let mut ctx = context_with(engine);
+
+    // "Café crème brûlée" in windows-1252
+    let latin1: &[u8] = b"Caf\xe9 cr\xe8me br\xfbl\xe9e";
+    let file = write_file(".txt", latin1);
+
+    let sink = RecordingSink::new();
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Menu").unwrap();
+    conversation::service::continue_conversation(
+        &conv_id,
+        "What is on the menu?",
+        &[file.path().to_path_buf()],
+        &sink,
+        &mut ctx,
+    )
+    .unwrap();
+
+    let prompt = &prompts.lock().unwrap()[0];
+    assert!(prompt.contains("<attachment name="));
+    assert!(prompt.contains("Café crème brûlée\n</attachment>"));
+
+    let stored = conversation::service::get_conversation(&ctx.db, &conv_id)
+        .unwrap()
+        .unwrap();
+    let attachment = &stored.body[0].attachments[0];
+    assert_eq!(attachment.size, latin1.len() as u64);
+    assert_eq!(attachment.encoding, "windows-1252");
+    assert_eq!(attachment.hash.len(), 64);
+    assert!(!attachment.truncated);
+
+    let reopened = attachments::service::open_attachment(&ctx.db, &attachment.hash).unwrap();
+    assert_eq!(reopened.text, "Café crème brûlée");
+}
+
+#[test]
+fn long_attachments_are_cut_to_the_budget() {
+    let mut ctx = context_with(ScriptedEngine::new([ScriptedReply::text(&["Ok"])]));
+    ctx.config.max_context_length = 40;
+    let file = write_file(".log", "line of text\n".repeat(100));
+
+    let sink = RecordingSink::new();
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Logs").unwrap();
+    conversation::service::continue_conversation(
+        &conv_id,
+        "Summarize",
+        &[file.path().to_path_buf()],
+        &sink,
+        &mut ctx,
+    )
+    .unwrap();
+
+    let stored = conversation::service::get_conversation(&ctx.db, &conv_id)
+        .unwrap()
+        .unwrap();
+    let attachment = &stored.body[0].attachments[0];
+    assert!(attachment.truncated);
+    assert!(attachment.excerpt.chars().count() <= 40);
+    assert!(attachment.excerpt.ends_with("line of text"));
+
+    let reopened = attachments::service::open_attachment(&ctx.db, &attachment.hash).unwrap();
+    assert_eq!(reopened.text.len(), 1300);
+}
+
+#[test]
+fn binary_attachments_are_rejected() {
+    let mut ctx = context_with(ScriptedEngine::new([ScriptedReply::text(&["Ok"])]));
+    let file = write_file(".bin", [0x7f, b'E', b'L', b'F', 0, 0, 1]);
+
+    let sink = RecordingSink::new();
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Binary").unwrap();
+    let result = conversation::service::continue_conversation(
+        &conv_id,
+        "Read this",
+        &[file.path().to_path_buf()],
+        &sink,
+        &mut ctx,
+    );
+
+    assert!(matches!(result, Err(AppError::Validation(_))));
+}
+
+#[test]
+fn oversized_attachments_are_rejected() {
+    let mut ctx = context_with(ScriptedEngine::new([ScriptedReply::text(&["Ok"])]));
+    let file = tempfile::Builder::new().suffix(".txt").tempfile().unwrap();
+    file.as_file()
+        .set_len(consts::ATTACHMENT_MAX_BYTES + 1)
+        .unwrap();
+
+    let sink = RecordingSink::new();
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Huge").unwrap();
+    let result = conversation::service::continue_conversation(
+        &conv_id,
+        "Read this",
+        &[file.path().to_path_buf()],
+        &sink,
+        &mut ctx,
+    );
+
+    assert!(matches!(result, Err(AppError::Validation(_))));
+}
+
+#[test]
+fn attachment_names_are_escaped() {
+    let engine = ScriptedEngine::new([ScriptedReply::text(&["Ok"])]);
+    let prompts = engine.prompts();
+    let mut ctx = context_with(engine);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a\"><b.txt");
+    std::fs::write(&path, "notes").unwrap();
+
+    let sink = RecordingSink::new();
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Names").unwrap();
+    conversation::service::continue_conversation(&conv_id, "Read", &[path], &sink, &mut ctx)
+        .unwrap();
+
+    assert!(prompts.lock().unwrap()[0].contains("<attachment name=\"a&quot;&gt;&lt;b.txt\">"));
+}
+
+#[test]
+fn failed_turns_keep_no_originals() {
+    let error = AppError::ContextOverflow("Prompt does not fit".into());
+    let mut ctx = context_with(ScriptedEngine::new([ScriptedReply::failing(&[], error)]));
+    let file = write_file(".txt", "draft");
+
+    let sink = RecordingSink::new();
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Draft").unwrap();
+    let result = conversation::service::continue_conversation(
+        &conv_id,
+        "Read",
+        &[file.path().to_path_buf()],
+        &sink,
+        &mut ctx,
+    );
+
+    assert!(result.is_err());
+    let hash = attachments::service::attach(file.path(), 16)
+        .unwrap()
+        .0
+        .hash;
+    assert!(matches!(
+        attachments::service::open_attachment(&ctx.db, &hash),
+        Err(AppError::NotFound(_))
+    ));
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use breve_lib::{
+    inference::{
+        self,
+        grammar::from_json_schema,
+        scripted::{ScriptedEngine, ScriptedReply},
+    },
+    infrastructure::error::AppError,
+};
+use common::context_with;
+use serde_json::json;
+
+fn rule<'a>(grammar: &'a str, name: &str) -> &'a str {
+    let prefix = format!("{} ::= ", name);
+    grammar
+        .lines()
+        .find_map(|line| line.strip_prefix(prefix.as_str()))
+        .unwrap_or_else(|| panic!("no rule {} in\n{}", name, grammar))
+}
+
+#[test]
+fn objects_keep_required_properties_and_make_the_rest_optional() {
+    let grammar = from_json_schema(&json!({
+        "type": "object",
+        "properties": {
+            "name": { "type": "string" },
+            "age": { "type": "integer", "minimum": 0 },
+            "tags": { "type": "array", "items": { "type": "string" }, "maxItems": 3 }
+        },
+        "required": ["name", "age"]
+    }))
+    .unwrap();
+
+    assert!(grammar.starts_with("root ::= "));
+    assert_eq!(
+        rule(&grammar, "root"),
+        r#""{" space "\"age\"" space ":" space integer "," space "\"name\"" space ":" space string ("," space "\"tags\"" space ":" space root-tags)? "}" space"#
+    );
+    assert_eq!(
+        rule(&grammar, "root-tags"),
+        r#""[" space (string ("," space string){0,2})? "]" space"#
+    );
+    // Primitives are only emitted with everything they depend on
+    for name in ["integer", "integral-part", "string", "char", "space"] {
+        rule(&grammar, name);
+    }
+    assert!(!grammar.contains("boolean ::="));
+}
+
+#[test]
+fn enums_and_recursive_references_are_supported() {
+    let grammar = from_json_schema(&json!({ "enum": ["red", "green"] })).unwrap();
+    assert_eq!(rule(&grammar, "root"), r#"("\"red\"" | "\"green\"") space"#);
+
+    let grammar = from_json_schema(&json!({
+        "$defs": {
+            "node": {
+                "type": "object",
+                "properties": {
+                    "children": { "type": "array", "items": { "$ref": "#/$defs/node" } }
+                }
+            }
+        },
+        "$ref": "#/$defs/node"
+    }))
+    .unwrap();
+    assert_eq!(rule(&grammar, "root"), "ref-defs-node");
+    assert_eq!(rule(&grammar, "ref-defs-node"), "ref-defs-node-value");
+    assert!(
+        rule(&grammar, "ref-defs-node-value-children")
+            .contains(r#"(ref-defs-node ("," space ref-defs-node){0,})?"#)
+    );
+
+    // Pointers ending alike, or sanitized alike, still get a rule each
+    let grammar = from_json_schema(&json!({
+        "$defs": {
+            "home": { "properties": { "id": { "type": "integer" } } },
+            "work": { "properties": { "id": { "type": "string" } } },
+            "a_b": { "type": "boolean" },
+            "a-b": { "type": "null" }
+        },
+        "type": "object",
+        "properties": {
+            "home": { "$ref": "#/$defs/home/properties/id" },
+            "work": { "$ref": "#/$defs/work/properties/id" },
+            "x": { "$ref": "#/$defs/a_b" },
+            "y": { "$ref": "#/$defs/a-b" }
+        },
+        "required": ["home", "work", "x", "y"]
+    }))
+    .unwrap();
+    assert_eq!(rule(&grammar, "ref-defs-home-properties-id"), "integer");
+    assert_eq!(rule(&grammar, "ref-defs-work-properties-id"), "string");
+    assert_eq!(rule(&grammar, "ref-defs-a-b"), "boolean");
+    assert_eq!(rule(&grammar, "ref-defs-a-b1"), "null");
+
+    assert!(matches!(
+        from_json_schema(&json!({ "type": "tuple" })),
+        Err(AppError::Validation(_))
+    ));
+}
+
+#[test]
+fn structured_replies_are_parsed_and_constrained() {
+    let engine = ScriptedEngine::new([
+        ScriptedReply::text(&["{\"name\": \"Ada\", ", "\"age\": 36}"]),
+        ScriptedReply::text(&["{\"name\": ", "\"Ad", "a\", \"age\": 36}"]),
+        ScriptedReply::text(&["yes"]),
+    ]);
+    let grammars = engine.grammars();
+    let mut ctx = context_with(engine);
+    let schema = json!({
+        "type": "object",
+        "properties": { "name": { "type": "string" }, "age": { "type": "integer" } },
+        "required": ["name", "age"]
+    });
+
+    let person = inference::service::generate_structured(
+        "Ada Lovelace was 36 when she died.",
+        &schema,
+        &mut ctx,
+    )
+    .unwrap();
+    assert_eq!(person, json!({ "name": "Ada", "age": 36 }));
+    assert_eq!(
+        grammars.lock().unwrap()[0],
+        from_json_schema(&schema).unwrap()
+    );
+
+    // A reply cut off by the output limit is reported rather than returned half parsed
+    ctx.inference.as_mut().unwrap().config.max_output_length = 2;
+    assert!(matches!(
+        inference::service::generate_structured("Again", &schema, &mut ctx),
+        Err(AppError::OutputTruncated(_))
+    ));
+
+    // Raw GBNF is passed through as given
+    let gbnf = "root ::= \"yes\" | \"no\"\n";
+    let answer = inference::service::generate_constrained("Is water wet?", gbnf, &mut ctx).unwrap();
+    assert_eq!(answer, "yes");
+    assert_eq!(grammars.lock().unwrap()[2], gbnf);
+}
@@ -0,0 +1,184 @@
+mod common;
+
+use breve_lib::{
+    conversation,
+    inference::{
+        self,
+        scripted::{ScriptedEngine, ScriptedReply},
+    },
+    infrastructure::{error::AppError, events::RecordingSink},
+    knowledge,
+};
+use common::{context_with, switch_model, write_file};
+
+#[test]
+fn chunks_cover_the_text_with_overlap() {
+    let text = "alpha beta gamma delta. ".repeat(40);
+    let chunks = knowledge::service::split_into_chunks(&text, 100, 20);
+
+    assert!(chunks.len() > 1);
+    for (i, chunk) in chunks.iter().enumerate() {
+        assert_eq!(chunk.index, i as u64);
+        assert!(chunk.text.chars().count() <= 100);
+        assert_eq!(&text[chunk.start as usize..chunk.end as usize], chunk.text);
+    }
+    for pair in chunks.windows(2) {
+        assert!(pair[1].start < pair[0].end, "consecutive chunks overlap");
+        assert!(pair[1].start > pair[0].start);
+    }
+    assert_eq!(chunks.last().unwrap().end as usize, text.trim_end().len());
+}
+
+#[test]
+fn documents_are_stored_replaced_and_deleted() {
+    let mut ctx = context_with(ScriptedEngine::default());
+    let file = write_file(".md", "# Notes\n\nThe office plants need water on Mondays.");
+
+    let first = knowledge::service::add_document(file.path(), &mut ctx).unwrap();
+    assert_eq!(first.chunk_count, 1);
+    assert_eq!(first.embedding_model, "scripted");
+
+    // Adding the same file again replaces it instead of duplicating its chunks
+    let second = knowledge::service::add_document(file.path(), &mut ctx).unwrap();
+    let documents = knowledge::service::get_documents(&ctx.db).unwrap();
+    assert_eq!(documents, vec![second.clone()]);
+
+    knowledge::service::delete_document(&ctx.db, &second.id).unwrap();
+    assert!(
+        knowledge::service::get_documents(&ctx.db)
+            .unwrap()
+            .is_empty()
+    );
+    assert!(matches!(
+        knowledge::service::delete_document(&ctx.db, &first.id),
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[test]
+fn unsupported_files_are_rejected() {
+    let mut ctx = context_with(ScriptedEngine::default());
+    let file = write_file(".bin", "data");
+
+    assert!(matches!(
+        knowledge::service::add_document(file.path(), &mut ctx),
+        Err(AppError::Validation(_))
+    ));
+}
+
+#[test]
+fn replies_cite_retrieved_passages() {
+    let engine = ScriptedEngine::new([ScriptedReply::text(&["Water them on Mondays", " [1]."])]);
+    let prompts = engine.prompts();
+    let mut ctx = context_with(engine);
+
+    let plants = write_file(".txt", "The office plants need water on Mondays.");
+    let reports = write_file(".txt", "Quarterly reports are due in March.");
+    knowledge::service::add_document(reports.path(), &mut ctx).unwrap();
+    let document = knowledge::service::add_document(plants.path(), &mut ctx).unwrap();
+
+    let sink = RecordingSink::new();
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Plants").unwrap();
+    let reply = conversation::service::continue_conversation(
+        &conv_id,
+        "When do the office plants need water?",
+        &[],
+        &sink,
+        &mut ctx,
+    )
+    .unwrap();
+
+    assert!(prompts.lock().unwrap()[0].contains("[1] "));
+    assert_eq!(reply.citations.len(), 1);
+    let citation = &reply.citations[0];
+    assert_eq!(citation.marker, 1);
+    assert_eq!(citation.document_id, document.id);
+    assert_eq!(citation.text, "The office plants need water on Mondays.");
+
+    let stored = conversation::service::get_conversation(&ctx.db, &conv_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.body[1].citations, reply.citations);
+}
+
+#[test]
+fn unrelated_questions_get_no_sources() {
+    let engine = ScriptedEngine::new([ScriptedReply::text(&["Hello [1]"])]);
+    let prompts = engine.prompts();
+    let mut ctx = context_with(engine);
+
+    let file = write_file(".txt", "Quarterly reports are due in March.");
+    knowledge::service::add_document(file.path(), &mut ctx).unwrap();
+
+    let sink = RecordingSink::new();
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Hi").unwrap();
+    let reply =
+        conversation::service::continue_conversation(&conv_id, "hello there", &[], &sink, &mut ctx)
+            .unwrap();
+
+    assert!(!prompts.lock().unwrap()[0].contains("[1] "));
+    assert!(reply.citations.is_empty());
+}
+
+#[test]
+fn passages_stay_within_their_share_of_the_context() {
+    let mut ctx = context_with(ScriptedEngine::default());
+    let file = write_file(".txt", "The office plants need water on Mondays.");
+    knowledge::service::add_document(file.path(), &mut ctx).unwrap();
+    let question = "When do the office plants need water?";
+
+    assert_eq!(
+        knowledge::service::retrieve(question, &mut ctx)
+            .unwrap()
+            .len(),
+        1
+    );
+    // A quarter of 36 tokens leaves fewer characters than the passage has
+    ctx.config.max_context_length = 36;
+    assert!(
+        knowledge::service::retrieve(question, &mut ctx)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn documents_are_embedded_again_for_a_new_model() {
+    let mut ctx = context_with(ScriptedEngine::default());
+    let file = write_file(".txt", "The office plants need water on Mondays.");
+    let document = knowledge::service::add_document(file.path(), &mut ctx).unwrap();
+    assert_eq!(document.embedding_model, "scripted");
+
+    let db = ctx.db.clone();
+    let mut embedded = 0;
+    let updated = knowledge::service::reembed_documents(&db, "other", |texts| {
+        embedded += texts.len();
+        inference::service::embed(texts, &mut ctx)
+    })
+    .unwrap();
+
+    assert_eq!((updated, embedded), (1, 1));
+    let documents = knowledge::service::get_documents(&db).unwrap();
+    assert_eq!(documents[0].embedding_model, "other");
+    // Nothing is left to embed for that model
+    assert_eq!(
+        knowledge::service::reembed_documents(&db, "other", |_| unreachable!()).unwrap(),
+        0
+    );
+}
+
+#[test]
+fn switching_the_chat_model_keeps_documents_searchable() {
+    let mut ctx = context_with(ScriptedEngine::default());
+    let file = write_file(".txt", "The office plants need water on Mondays.");
+    knowledge::service::add_document(file.path(), &mut ctx).unwrap();
+
+    // An empty embedding model follows the chat model
+    switch_model(&mut ctx, "scripted-v2");
+    let passages =
+        knowledge::service::retrieve("When do the office plants need water?", &mut ctx).unwrap();
+
+    assert_eq!(passages.len(), 1);
+    let documents = knowledge::service::get_documents(&ctx.db).unwrap();
+    assert_eq!(documents[0].embedding_model, "scripted-v2");
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use breve_lib::{
+    conversation,
+    inference::scripted::{ScriptedEngine, ScriptedReply},
+    infrastructure::{self, consts, error::AppError, events::RecordingSink},
+    memory::{self, models::FactSource},
+};
+use common::context_with;
+
+fn enable_memory(db: &infrastructure::database::Database) {
+    infrastructure::service::set_config(db, consts::MEMORY_ENABLED_KEY.into(), "true".into())
+        .unwrap();
+}
+
+#[test]
+fn facts_can_be_added_edited_and_deleted() {
+    let ctx = context_with(ScriptedEngine::default());
+
+    let fact = memory::service::add_fact(&ctx.db, "  I write Rust ", FactSource::User).unwrap();
+    assert_eq!(fact.content, "I write Rust");
+    assert!(fact.accepted);
+    // The same fact is not stored twice
+    let again = memory::service::add_fact(&ctx.db, "i write rust", FactSource::User).unwrap();
+    assert_eq!(again.id, fact.id);
+
+    let edited = memory::service::update_fact(&ctx.db, &fact.id, "I write Rust and Go").unwrap();
+    assert_eq!(edited.content, "I write Rust and Go");
+    assert_eq!(memory::service::get_facts(&ctx.db).unwrap(), vec![edited]);
+
+    memory::service::delete_fact(&ctx.db, &fact.id).unwrap();
+    assert!(memory::service::get_facts(&ctx.db).unwrap().is_empty());
+    assert!(matches!(
+        memory::service::delete_fact(&ctx.db, &fact.id),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        memory::service::add_fact(&ctx.db, "  ", FactSource::User),
+        Err(AppError::Validation(_))
+    ));
+}
+
+#[test]
+fn proposals_are_taken_out_of_replies() {
+    let (reply, proposals) = memory::service::take_proposals(
+        "Nice to meet you!\n<remember>My team uses Postgres</remember>\nAsk me anything.",
+    );
+
+    assert_eq!(reply, "Nice to meet you!\nAsk me anything.");
+    assert_eq!(proposals, vec!["My team uses Postgres"]);
+}
+
+#[test]
+fn proposals_are_filtered_out_of_the_stream() {
+    let mut filter = memory::service::ProposalFilter::new();
+    let mut shown = String::new();
+    for piece in [
+        "Nice to meet you!\n<rem",
+        "ember>My team uses",
+        " Postgres</remem",
+        "ber>\nAsk me <b>anything</b>.",
+    ] {
+        shown.push_str(&filter.push(piece));
+    }
+    shown.push_str(&filter.finish());
+
+    assert_eq!(shown, "Nice to meet you!\nAsk me <b>anything</b>.");
+
+    // A block never closed is shown as written
+    let mut filter = memory::service::ProposalFilter::new();
+    let mut shown = filter.push("Ok <remember>half");
+    shown.push_str(&filter.finish());
+    assert_eq!(shown, "Ok <remember>half");
+}
+
+#[test]
+fn a_long_fact_does_not_crowd_out_shorter_ones() {
+    let mut ctx = context_with(ScriptedEngine::default());
+    let long = "Postgres ".repeat(consts::MEMORY_TOKEN_BUDGET * consts::CHARS_PER_TOKEN);
+    memory::service::add_fact(&ctx.db, &long, FactSource::User).unwrap();
+    memory::service::add_fact(&ctx.db, "I write Rust", FactSource::User).unwrap();
+
+    let facts = memory::service::relevant_facts("Postgres", &mut ctx).unwrap();
+
+    let contents: Vec<&str> = facts.iter().map(|fact| fact.content.as_str()).collect();
+    assert_eq!(contents, vec!["I write Rust"]);
+}
+
+#[test]
+fn memory_stays_out_of_prompts_until_enabled() {
+    let engine = ScriptedEngine::new([ScriptedReply::text(&["Hi"])]);
+    let prompts = engine.prompts();
+    let mut ctx = context_with(engine);
+    memory::service::add_fact(&ctx.db, "I write Rust", FactSource::User).unwrap();
+
+    let sink = RecordingSink::new();
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Off").unwrap();
+    conversation::service::continue_conversation(&conv_id, "Hello", &[], &sink, &mut ctx).unwrap();
+
+    let prompt = &prompts.lock().unwrap()[0];
+    assert!(!prompt.contains("I write Rust"));
+    assert!(!prompt.contains("<remember>"));
+}
+
+#[test]
+fn accepted_facts_are_injected_and_proposals_wait() {
+    let engine = ScriptedEngine::new([
+        ScriptedReply::text(&[
+            "Good choice.\n",
+            "<remember>My team uses Postgres</remember>",
+        ]),
+        ScriptedReply::text(&["Sure"]),
+    ]);
+    let prompts = engine.prompts();
+    let mut ctx = context_with(engine);
+    enable_memory(&ctx.db);
+    memory::service::add_fact(&ctx.db, "I write Rust", FactSource::User).unwrap();
+
+    let sink = RecordingSink::new();
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Stack").unwrap();
+    let reply = conversation::service::continue_conversation(
+        &conv_id,
+        "Which database should I use with Rust?",
+        &[],
+        &sink,
+        &mut ctx,
+    )
+    .unwrap();
+
+    assert_eq!(reply.content, "Good choice.");
+    // The proposal is left out of the stream as it is out of the stored reply
+    assert_eq!(sink.streamed_text(), "Good choice.\n");
+    assert!(prompts.lock().unwrap()[0].contains("- I write Rust"));
+
+    let proposal = memory::service::get_facts(&ctx.db)
+        .unwrap()
+        .into_iter()
+        .find(|fact| fact.source == FactSource::Model)
+        .expect("proposal saved");
+    assert_eq!(proposal.content, "My team uses Postgres");
+    assert!(!proposal.accepted);
+
+    memory::service::accept_fact(&ctx.db, &proposal.id).unwrap();
+    conversation::service::continue_conversation(&conv_id, "Thanks", &[], &sink, &mut ctx).unwrap();
+    assert!(prompts.lock().unwrap()[1].contains("- My team uses Postgres"));
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use breve_lib::{
+    configuration::models::Config,
+    inference::{self, scripted::ScriptedEngine, template::ChatTemplate},
+    infrastructure::{consts, path_resolver},
+    models, search,
+};
+use common::context_with;
+
+const VARIANT: &str = "Custom-3B-Instruct-Q5_K_M.gguf";
+
+#[test]
+fn deleting_a_model_removes_what_was_kept_for_it() {
+    path_resolver::init_dir_paths(std::env::temp_dir().join("breve-model-tests"));
+    let mut ctx = context_with(ScriptedEngine::default());
+
+    // A downloaded variant, listed in the catalog with its own template and vectors
+    let path = path_resolver::paths()
+        .unwrap()
+        .app_local_data(VARIANT)
+        .unwrap();
+    std::fs::write(&path, b"GGUF").unwrap();
+    let model = consts::default_models().values().next().unwrap().clone();
+    models::repository::add_catalog_entry(&ctx.db, VARIANT, &model).unwrap();
+    ctx.config.models.insert(VARIANT.into(), model);
+    inference::service::set_chat_template(VARIANT, Some(ChatTemplate::ChatMl), &mut ctx).unwrap();
+    search::repository::add_embeddings(&ctx.db, "notes", VARIANT, &[(0, vec![1.0, 0.0])]).unwrap();
+
+    models::service::delete_model(VARIANT, &mut ctx).unwrap();
+
+    assert!(!path.exists());
+    assert!(!ctx.config.models.contains_key(VARIANT));
+    assert!(
+        !models::repository::get_catalog_entries(&ctx.db)
+            .unwrap()
+            .contains_key(VARIANT)
+    );
+    assert!(Config::init(&ctx.db).chat_templates.is_empty());
+    assert!(
+        search::repository::get_embeddings(&ctx.db, VARIANT)
+            .unwrap()
+            .is_empty()
+    );
+}
@@ -0,0 +1,152 @@
+use breve_lib::{
+    configuration::{
+        self,
+        models::{Config, KvCacheType},
+    },
+    conversation::{self, models::Conversation},
+    infrastructure::{
+        self,
+        database::{Database, IN_MEMORY},
+        error::AppError,
+    },
+};
+
+fn db() -> Database {
+    Database::open(IN_MEMORY).expect("in-memory database")
+}
+
+#[test]
+fn in_memory_databases_are_isolated() {
+    let first = db();
+    let second = db();
+
+    infrastructure::repository::set_config(&first, "theme".into(), "dark".into()).unwrap();
+
+    assert_eq!(
+        infrastructure::repository::get_config(&first, "theme".into()).unwrap(),
+        Some("dark".to_string())
+    );
+    assert_eq!(
+        infrastructure::repository::get_config(&second, "theme".into()).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn conversation_crud() {
+    let db = db();
+    let mut conv = Conversation::new("conv-1".into(), "First".into());
+    conversation::repository::add_conversation(&db, &conv).unwrap();
+
+    let stored = conversation::repository::get_conversation(&db, "conv-1")
+        .unwrap()
+        .expect("conversation stored");
+    assert_eq!(stored.title, "First");
+    assert!(stored.body.is_empty());
+
+    conv.add_message("user", "Hello");
+    conv.add_message("assistant", "Hi there");
+    conv.update_title("Greeting");
+    conversation::repository::update_conversation(&db, &conv).unwrap();
+
+    let stored = conversation::repository::get_conversation(&db, "conv-1")
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.title, "Greeting");
+    assert_eq!(stored.body.len(), 2);
+    assert_eq!(stored.body[1].role, "assistant");
+    assert_eq!(stored.body[1].content, "Hi there");
+
+    let second = Conversation::new("conv-2".into(), "Second".into());
+    conversation::repository::add_conversation(&db, &second).unwrap();
+    let mut ids = conversation::repository::get_conversation_ids(&db).unwrap();
+    ids.sort();
+    assert_eq!(ids, vec!["conv-1".to_string(), "conv-2".to_string()]);
+
+    conversation::repository::delete_conversation(&db, "conv-1").unwrap();
+    assert!(
+        conversation::repository::get_conversation(&db, "conv-1")
+            .unwrap()
+            .is_none()
+    );
+    assert!(matches!(
+        conversation::repository::delete_conversation(&db, "conv-1"),
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[test]
+fn conversation_service_round_trip() {
+    let db = db();
+    let id = conversation::service::start_new_conversation(&db, "Service").unwrap();
+
+    let stored = conversation::service::get_conversation(&db, &id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.id, id);
+    assert_eq!(
+        conversation::service::get_conversation_ids(&db).unwrap(),
+        vec![id]
+    );
+}
+
+#[test]
+fn settings_round_trip() {
+    let db = db();
+
+    assert!(matches!(
+        infrastructure::service::get_config(&db, "model_name".into()),
+        Err(AppError::NotFound(_))
+    ));
+
+    infrastructure::service::set_config(&db, "model_name".into(), "a.gguf".into()).unwrap();
+    infrastructure::service::set_config(&db, "model_name".into(), "b.gguf".into()).unwrap();
+
+    assert_eq!(
+        infrastructure::service::get_config(&db, "model_name".into()).unwrap(),
+        "b.gguf"
+    );
+}
+
+#[test]
+fn model_config_round_trip() {
+    let db = db();
+    let defaults = Config::init(&db);
+
+    for (key, value) in [
+        ("temperature", "0.25"),
+        ("system_prompt", "\"Be brief.\""),
+        ("use_mlock", "true"),
+        ("kv_cache_type", "\"q8_0\""),
+        ("n_threads", "3"),
+    ] {
+        configuration::repository::set_model_config(&db, key.into(), value.into()).unwrap();
+    }
+
+    let config = Config::init(&db);
+    assert_eq!(config.temperature, 0.25);
+    assert_eq!(config.system_prompt, "Be brief.");
+    assert!(config.use_mlock);
+    assert_eq!(config.kv_cache_type, KvCacheType::Q8_0);
+    assert_eq!(config.n_threads, 3);
+
+    configuration::repository::clear_model_config(&db).unwrap();
+    let config = Config::init(&db);
+    assert_eq!(config.temperature, defaults.temperature);
+    assert_eq!(config.system_prompt, defaults.system_prompt);
+    assert_eq!(config.kv_cache_type, defaults.kv_cache_type);
+}
+
+#[test]
+fn invalid_model_config_falls_back_to_default() {
+    let db = db();
+    let defaults = Config::init(&db);
+
+    configuration::repository::set_model_config(&db, "temperature".into(), "\"hot\"".into())
+        .unwrap();
+    configuration::repository::set_model_config(&db, "batch_size".into(), "-5".into()).unwrap();
+
+    let config = Config::init(&db);
+    assert_eq!(config.temperature, defaults.temperature);
+    assert_eq!(config.batch_size, defaults.batch_size);
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use breve_lib::{
+    conversation::{self, models::Conversation},
+    inference::scripted::ScriptedEngine,
+    infrastructure::{context::Context, error::AppError},
+    search,
+};
+use common::{MODEL, context_with, switch_model};
+
+fn add_conversation(ctx: &Context, id: &str, messages: &[(&str, &str)]) {
+    let mut conv = Conversation::new(id.into(), id.into());
+    for (role, content) in messages {
+        conv.add_message(role, content);
+    }
+    conversation::repository::add_conversation(&ctx.db, &conv).unwrap();
+}
+
+#[test]
+fn search_ranks_messages_across_conversations() {
+    let mut ctx = context_with(ScriptedEngine::default());
+    add_conversation(
+        &ctx,
+        "garden",
+        &[
+            ("system", "Be brief"),
+            ("user", "How often should tomatoes be watered?"),
+            ("assistant", "Water tomatoes every other day."),
+        ],
+    );
+    add_conversation(&ctx, "taxes", &[("user", "When are quarterly taxes due?")]);
+
+    assert_eq!(search::service::index_all(&mut ctx).unwrap(), 3);
+    // Indexing again only embeds what is new
+    assert_eq!(search::service::index_all(&mut ctx).unwrap(), 0);
+
+    let matches = search::service::semantic_search("watering tomatoes", 2, &mut ctx).unwrap();
+    assert_eq!(matches.len(), 2);
+    assert!(matches.iter().all(|m| m.conversation_id == "garden"));
+    assert!(matches[0].score >= matches[1].score);
+    assert!(
+        matches.iter().all(|m| m.position > 0),
+        "system message is skipped"
+    );
+}
+
+#[test]
+fn changing_the_embedding_model_rebuilds_the_index() {
+    let mut ctx = context_with(ScriptedEngine::default());
+    add_conversation(&ctx, "notes", &[("user", "Remember the milk")]);
+    assert_eq!(search::service::index_all(&mut ctx).unwrap(), 1);
+
+    // An empty embedding model means the chat model embeds, so renaming it changes the vectors
+    switch_model(&mut ctx, "scripted-v2");
+    assert_eq!(search::service::index_all(&mut ctx).unwrap(), 1);
+
+    let matches = search::service::semantic_search("milk", 5, &mut ctx).unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].content, "Remember the milk");
+}
+
+#[test]
+fn indexing_one_conversation_keeps_the_others() {
+    let mut ctx = context_with(ScriptedEngine::default());
+    add_conversation(
+        &ctx,
+        "garden",
+        &[("user", "Water tomatoes every other day")],
+    );
+    add_conversation(&ctx, "taxes", &[("user", "When are quarterly taxes due?")]);
+    assert_eq!(search::service::index_all(&mut ctx).unwrap(), 2);
+
+    switch_model(&mut ctx, "scripted-v2");
+    assert_eq!(
+        search::service::index_conversation("taxes", &mut ctx).unwrap(),
+        1
+    );
+
+    // The garden conversation was not indexed again, its vectors are still there
+    switch_model(&mut ctx, MODEL);
+    let matches = search::service::semantic_search("tomatoes", 5, &mut ctx).unwrap();
+    assert!(matches.iter().any(|m| m.conversation_id == "garden"));
+}
+
+#[test]
+fn deleted_conversations_leave_the_index() {
+    let mut ctx = context_with(ScriptedEngine::default());
+    add_conversation(&ctx, "gone", &[("user", "Secret plans for the weekend")]);
+    search::service::index_all(&mut ctx).unwrap();
+
+    conversation::service::delete_conversation(&ctx.db, "gone").unwrap();
+
+    let matches = search::service::semantic_search("weekend plans", 5, &mut ctx).unwrap();
+    assert!(matches.is_empty());
+}
+
+#[test]
+fn search_needs_a_model() {
+    let mut ctx = context_with(ScriptedEngine::default());
+    ctx.config.default_model.clear();
+
+    assert!(matches!(
+        search::service::semantic_search("anything", 5, &mut ctx),
+        Err(AppError::ModelLoadFailed(_))
+    ));
+}
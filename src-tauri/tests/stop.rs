@@ -0,0 +1,61 @@
+mod common;
+
+use breve_lib::{
+    conversation,
+    inference::{
+        scripted::{ScriptedEngine, ScriptedReply},
+        stop::StopMatcher,
+    },
+    infrastructure::events::RecordingSink,
+};
+use common::context_with;
+
+#[test]
+fn partial_matches_are_held_until_resolved() {
+    let mut stop = StopMatcher::new(&["</answer>".to_string(), String::new()]);
+
+    assert_eq!(
+        stop.push("The sum is 4 <"),
+        ("The sum is 4 ".to_string(), false)
+    );
+    // `<b` cannot become `</answer>` anymore, so it is released
+    assert_eq!(stop.push("b>"), ("<b>".to_string(), false));
+    assert_eq!(stop.push(" </ans"), (" ".to_string(), false));
+    assert_eq!(stop.push("wer> trailing"), (String::new(), true));
+
+    let mut unfinished = StopMatcher::new(&["###".to_string()]);
+    assert_eq!(unfinished.push("Done #"), ("Done ".to_string(), false));
+    assert_eq!(unfinished.finish(), "#");
+}
+
+#[test]
+fn the_earliest_stop_string_wins() {
+    let mut stop = StopMatcher::new(&["END".to_string(), "\n\n".to_string()]);
+    assert_eq!(
+        stop.push("first\n\nsecond END"),
+        ("first".to_string(), true)
+    );
+}
+
+#[test]
+fn stop_strings_never_reach_the_stream() {
+    let engine = ScriptedEngine::new([ScriptedReply::text(&[
+        "Hello",
+        " there",
+        "\nUser",
+        ":",
+        " next turn",
+    ])]);
+    let mut ctx = context_with(engine);
+    if let Some(inference) = ctx.inference.as_mut() {
+        inference.config.stop_sequences = vec!["\nUser:".into()];
+    }
+
+    let sink = RecordingSink::new();
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Stops").unwrap();
+    let reply =
+        conversation::service::continue_conversation(&conv_id, "Hi", &[], &sink, &mut ctx).unwrap();
+
+    assert_eq!(reply.content, "Hello there");
+    assert_eq!(sink.streamed_text(), "Hello there");
+}
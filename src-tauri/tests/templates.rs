@@ -0,0 +1,135 @@
+mod common;
+
+use breve_lib::{
+    configuration::models::{Config, ConfigChange},
+    inference::{self, scripted::ScriptedEngine, template::ChatTemplate},
+    infrastructure::{consts, context::Context, error::AppError, path_resolver},
+};
+use common::{MODEL, context_with};
+
+// `MODEL` listed in the catalog with its file present, as a downloaded model would be
+fn install_model(ctx: &mut Context) {
+    path_resolver::init_dir_paths(std::env::temp_dir().join("breve-template-tests"));
+    let path = path_resolver::paths()
+        .unwrap()
+        .app_local_data(MODEL)
+        .unwrap();
+    std::fs::write(path, b"GGUF").unwrap();
+
+    let model = consts::default_models().values().next().unwrap().clone();
+    ctx.config.models.insert(MODEL.into(), model);
+}
+
+fn chat(messages: &[(&str, &str)]) -> Vec<(String, String)> {
+    messages
+        .iter()
+        .map(|(role, content)| (role.to_string(), content.to_string()))
+        .collect()
+}
+
+#[test]
+fn chatml_keeps_the_system_turn() {
+    let prompt = ChatTemplate::ChatMl.render(&chat(&[("system", "Be brief."), ("user", "Hi")]));
+    assert_eq!(
+        prompt,
+        "<|im_start|>system\nBe brief.<|im_end|>\n<|im_start|>user\nHi<|im_end|>\n<|im_start|>assistant\n"
+    );
+}
+
+#[test]
+fn templates_without_a_system_role_fold_it_into_the_first_user_turn() {
+    let messages = chat(&[
+        ("system", "Be brief."),
+        ("user", "Hi"),
+        ("assistant", "Hello"),
+        ("user", "Bye"),
+    ]);
+
+    assert_eq!(
+        ChatTemplate::Gemma.render(&messages),
+        "<bos><start_of_turn>user\nBe brief.\n\nHi<end_of_turn>\n\
+         <start_of_turn>model\nHello<end_of_turn>\n\
+         <start_of_turn>user\nBye<end_of_turn>\n<start_of_turn>model\n"
+    );
+    assert_eq!(
+        ChatTemplate::Mistral.render(&messages),
+        "<s>[INST] Be brief.\n\nHi [/INST] Hello</s>[INST] Bye [/INST]"
+    );
+    assert!(ChatTemplate::Llama3.render(&messages).starts_with(
+        "<|begin_of_text|><|start_header_id|>system<|end_header_id|>\n\nBe brief.<|eot_id|>"
+    ));
+}
+
+#[test]
+fn templates_are_guessed_from_file_names() {
+    let guesses = [
+        (
+            "Meta-Llama-3.1-8B-Instruct-Q4_K_M.gguf",
+            ChatTemplate::Llama3,
+        ),
+        ("gemma-3-1b-it-Q4_K_M.gguf", ChatTemplate::Gemma),
+        (
+            "Mistral-7B-Instruct-v0.3.Q4_K_M.gguf",
+            ChatTemplate::Mistral,
+        ),
+        ("Phi-3.5-mini-instruct-Q4_K_M.gguf", ChatTemplate::Phi),
+        (
+            "DeepSeek-R1-Distill-Qwen-1.5B-Q4_K_M.gguf",
+            ChatTemplate::DeepSeek,
+        ),
+        ("dolphin-2.9-Q4_K_M.gguf", ChatTemplate::ChatMl),
+    ];
+    for (name, template) in guesses {
+        assert_eq!(ChatTemplate::guess(name), template, "{}", name);
+    }
+}
+
+#[test]
+fn overrides_are_saved_per_model() {
+    let mut ctx = context_with(ScriptedEngine::default());
+    install_model(&mut ctx);
+
+    let change =
+        inference::service::set_chat_template(MODEL, Some(ChatTemplate::Gemma), &mut ctx).unwrap();
+    assert_eq!(change, ConfigChange::Sampler);
+    assert_eq!(
+        ctx.inference
+            .as_ref()
+            .unwrap()
+            .config
+            .chat_templates
+            .get(MODEL),
+        Some(&ChatTemplate::Gemma)
+    );
+    assert_eq!(
+        Config::init(&ctx.db).chat_templates.get(MODEL),
+        Some(&ChatTemplate::Gemma)
+    );
+
+    inference::service::set_chat_template(MODEL, None, &mut ctx).unwrap();
+    assert!(Config::init(&ctx.db).chat_templates.is_empty());
+}
+
+#[test]
+fn overrides_need_an_installed_model() {
+    let mut ctx = context_with(ScriptedEngine::default());
+
+    let result =
+        inference::service::set_chat_template("missing.gguf", Some(ChatTemplate::ChatMl), &mut ctx);
+    assert!(matches!(result, Err(AppError::NotFound(_))));
+    assert!(Config::init(&ctx.db).chat_templates.is_empty());
+}
+
+#[test]
+fn overrides_are_kept_only_once_applied() {
+    let mut ctx = context_with(ScriptedEngine::default());
+    install_model(&mut ctx);
+    // The model file is not a loadable GGUF, so applying the override fails
+    ctx.inference = None;
+
+    assert!(
+        inference::service::set_chat_template(MODEL, Some(ChatTemplate::Gemma), &mut ctx).is_err()
+    );
+    assert!(ctx.config.chat_templates.is_empty());
+    assert!(Config::init(&ctx.db).chat_templates.is_empty());
+}
@@ -0,0 +1,195 @@
+mod common;
+
+use breve_lib::{
+    conversation,
+    inference::scripted::{ScriptedEngine, ScriptedReply},
+    infrastructure::{self, consts, error::AppError, events::RecordingSink},
+    tools::{self, builtin, models::ToolCall},
+};
+use common::context_with;
+use serde_json::json;
+
+fn enable_tools(db: &infrastructure::database::Database) {
+    infrastructure::service::set_config(db, consts::TOOLS_ENABLED_KEY.into(), "true".into())
+        .unwrap();
+}
+
+#[test]
+fn tool_calls_are_parsed_from_both_formats() {
+    let definitions = tools::registry::ToolRegistry::builtin().definitions();
+    let hermes = tools::parser::parse_tool_calls(
+        "<tool_call>\n{\"name\": \"calculator\", \"arguments\": {\"expression\": \"2+2\"}}\n</tool_call>\n\
+         <tool_call>{\"name\": \"current_datetime\", \"arguments\": {}}</tool_call>",
+        &definitions,
+    );
+    assert_eq!(
+        hermes,
+        vec![
+            ToolCall {
+                name: "calculator".into(),
+                arguments: json!({ "expression": "2+2" }),
+            },
+            ToolCall {
+                name: "current_datetime".into(),
+                arguments: json!({}),
+            },
+        ]
+    );
+
+    let llama = tools::parser::parse_tool_calls(
+        "<|python_tag|>{\"name\": \"convert_units\", \"parameters\": \"{\\\"value\\\": 5, \\\"from\\\": \\\"km\\\", \\\"to\\\": \\\"mi\\\"}\"}",
+        &definitions,
+    );
+    assert_eq!(llama[0].name, "convert_units");
+    assert_eq!(llama[0].arguments["from"], "km");
+
+    assert!(tools::parser::parse_tool_calls("The answer is {42}.", &definitions).is_empty());
+    // A JSON answer is only a call when it names a known tool
+    assert!(
+        tools::parser::parse_tool_calls("{\"name\": \"Bob\", \"age\": 42}", &definitions)
+            .is_empty()
+    );
+}
+
+#[test]
+fn calculator_follows_precedence() {
+    assert_eq!(builtin::evaluate("2 + 3 * 4").unwrap(), 14.0);
+    assert_eq!(builtin::evaluate("(2 + 3) * 4").unwrap(), 20.0);
+    assert_eq!(builtin::evaluate("-2 ^ 2").unwrap(), -4.0);
+    assert_eq!(builtin::evaluate("2 ^ 3 ^ 2").unwrap(), 512.0);
+    assert_eq!(builtin::evaluate("sqrt(16) + 1.5e1 % 4").unwrap(), 7.0);
+    assert!(matches!(
+        builtin::evaluate("2 +"),
+        Err(AppError::Validation(_))
+    ));
+    assert!(matches!(
+        builtin::evaluate("1 / 0"),
+        Err(AppError::Validation(_))
+    ));
+    // Deep nesting is refused instead of overflowing the stack
+    let nested = format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000));
+    assert!(matches!(
+        builtin::evaluate(&nested),
+        Err(AppError::Validation(_))
+    ));
+    assert!(matches!(
+        builtin::evaluate(&"-".repeat(100_000)),
+        Err(AppError::Validation(_))
+    ));
+    assert_eq!(builtin::evaluate("((((2))))").unwrap(), 2.0);
+}
+
+#[test]
+fn units_convert_within_a_quantity() {
+    assert!((builtin::convert(10.0, "km", "mi").unwrap() - 6.213_711_922).abs() < 1e-6);
+    assert!((builtin::convert(100.0, "°C", "F").unwrap() - 212.0).abs() < 1e-9);
+    assert!((builtin::convert(1.0, "GiB", "MB").unwrap() - 1073.741_824).abs() < 1e-9);
+    assert!(matches!(
+        builtin::convert(1.0, "kg", "m"),
+        Err(AppError::Validation(_))
+    ));
+    assert!(matches!(
+        builtin::convert(1.0, "parsec", "m"),
+        Err(AppError::Validation(_))
+    ));
+}
+
+#[test]
+fn tool_results_are_fed_back_before_the_reply() {
+    let engine = ScriptedEngine::new([
+        ScriptedReply::text(&[
+            "<tool_call>{\"name\": \"calculator\", ",
+            "\"arguments\": {\"expression\": \"17 * 23\"}}</tool_call>",
+        ]),
+        ScriptedReply::text(&["17 times 23 is 391."]),
+    ]);
+    let prompts = engine.prompts();
+    let mut ctx = context_with(engine);
+    enable_tools(&ctx.db);
+
+    let sink = RecordingSink::new();
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Maths").unwrap();
+    let reply = conversation::service::continue_conversation(
+        &conv_id,
+        "What is 17 * 23?",
+        &[],
+        &sink,
+        &mut ctx,
+    )
+    .unwrap();
+    assert_eq!(reply.content, "17 times 23 is 391.");
+    // The call itself never reaches the stream
+    assert_eq!(sink.streamed_text(), "17 times 23 is 391.");
+
+    let prompts = prompts.lock().unwrap();
+    assert!(prompts[0].contains("<tools>"));
+    assert!(prompts[0].contains("\"name\":\"calculator\""));
+    assert!(prompts[1].contains("<tool_response>"));
+    assert!(prompts[1].contains("\"content\":\"391\""));
+
+    let conv = conversation::service::get_conversation(&ctx.db, &conv_id)
+        .unwrap()
+        .unwrap();
+    let roles: Vec<&str> = conv.body.iter().map(|msg| msg.role.as_str()).collect();
+    assert_eq!(roles, vec!["user", "assistant", "tool", "assistant"]);
+    assert_eq!(conv.body[1].tool_calls[0].name, "calculator");
+    assert_eq!(conv.body[2].name.as_deref(), Some("calculator"));
+}
+
+#[test]
+fn json_answers_are_not_taken_for_calls() {
+    let engine = ScriptedEngine::new([ScriptedReply::text(&["{\"name\": ", "\"Bob\"}"])]);
+    let mut ctx = context_with(engine);
+    enable_tools(&ctx.db);
+
+    let sink = RecordingSink::new();
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Json").unwrap();
+    let reply = conversation::service::continue_conversation(
+        &conv_id,
+        "Reply with a JSON object naming Bob",
+        &[],
+        &sink,
+        &mut ctx,
+    )
+    .unwrap();
+
+    assert_eq!(reply.content, "{\"name\": \"Bob\"}");
+    assert!(reply.tool_calls.is_empty());
+    assert_eq!(sink.streamed_text(), "{\"name\": \"Bob\"}");
+}
+
+#[test]
+fn unknown_tools_report_an_error_to_the_model() {
+    let engine = ScriptedEngine::new([
+        ScriptedReply::text(&["<tool_call>{\"name\": \"weather\", \"arguments\": {}}</tool_call>"]),
+        ScriptedReply::text(&["I cannot check the weather."]),
+    ]);
+    let prompts = engine.prompts();
+    let mut ctx = context_with(engine);
+    enable_tools(&ctx.db);
+
+    let sink = RecordingSink::new();
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Weather").unwrap();
+    conversation::service::continue_conversation(&conv_id, "Is it raining?", &[], &sink, &mut ctx)
+        .unwrap();
+
+    assert!(prompts.lock().unwrap()[1].contains("Error: unknown tool weather"));
+}
+
+#[test]
+fn tools_stay_out_of_prompts_until_enabled() {
+    let engine = ScriptedEngine::new([ScriptedReply::text(&[
+        "<tool_call>{\"name\": \"calculator\", \"arguments\": {\"expression\": \"1+1\"}}</tool_call>",
+    ])]);
+    let prompts = engine.prompts();
+    let mut ctx = context_with(engine);
+
+    let sink = RecordingSink::new();
+    let conv_id = conversation::service::start_new_conversation(&ctx.db, "Off").unwrap();
+    let reply =
+        conversation::service::continue_conversation(&conv_id, "1+1?", &[], &sink, &mut ctx)
+            .unwrap();
+
+    assert!(!prompts.lock().unwrap()[0].contains("<tools>"));
+    assert!(reply.tool_calls.is_empty());
+}
@@ -1,6 +1,56 @@
+export interface Citation {
+  marker: number;
+  document_id: string;
+  document_name: string;
+  start: number;
+  end: number;
+  text: string;
+}
+
+export interface Attachment {
+  name: string;
+  size: number;
+  hash: string;
+  encoding: string;
+  excerpt: string;
+  truncated: boolean;
+}
+
+export interface ToolCall {
+  name: string;
+  arguments: Record<string, unknown>;
+}
+
+// `parameters` is a JSON schema of the tool's arguments
+export interface ToolDefinition {
+  name: string;
+  description: string;
+  parameters: Record<string, unknown>;
+}
+
+// An imported GGUF LoRA adapter
+export interface Adapter {
+  id: string;
+  name: string;
+  size: number;
+}
+
+// Adapter attached to a conversation, `name` is filled in by the backend
+export interface ActiveAdapter {
+  id: string;
+  name?: string;
+  scale: number;
+}
+
 export interface Message {
-  role: 'system' | 'user' | 'assistant';
+  role: 'system' | 'user' | 'assistant' | 'tool';
   content: string;
+  citations?: Citation[];
+  attachments?: Attachment[];
+  tool_calls?: ToolCall[];
+  // Tool a `tool` message answers for
+  name?: string;
+  adapters?: ActiveAdapter[];
 }
 
 export interface Conversation {
@@ -25,4 +75,44 @@ export interface ModelConfig {
   system_prompt: string;
   max_output_length: number;
   max_context_length: number;
-}
\ No newline at end of file
+  // Strings that end a reply besides the model's end of turn tokens
+  stop_sequences?: string[];
+  // Built-in template per model file, overriding the one in the GGUF
+  chat_templates?: Record<string, ChatTemplate>;
+}
+
+export type ChatTemplate = 'chat_ml' | 'llama3' | 'gemma' | 'mistral' | 'phi' | 'deep_seek';
+export type AppErrorCode =
+  | 'NOT_FOUND'
+  | 'MODEL_NOT_DOWNLOADED'
+  | 'MODEL_LOAD_FAILED'
+  | 'CONTEXT_OVERFLOW'
+  | 'OUTPUT_TRUNCATED'
+  | 'DATABASE'
+  | 'NETWORK'
+  | 'VALIDATION'
+  | 'INTERNAL';
+
+// Shape of every error rejected by a backend command
+export interface AppError {
+  code: AppErrorCode;
+  message: string;
+}
+
+// Result of `semantic_search`, `position` indexes the conversation body
+export interface MessageMatch {
+  conversation_id: string;
+  conversation_title: string;
+  position: number;
+  role: string;
+  content: string;
+  score: number;
+}
+
+// Long-term memory, enabled with the `memory_enabled` setting
+export interface Fact {
+  id: string;
+  content: string;
+  source: 'user' | 'model';
+  accepted: boolean;
+}